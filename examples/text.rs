@@ -25,11 +25,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .to_string(),
         ),
         stream: None,
-        provider: Some(ModelProvider::Anthropic)
+        provider: Some(ModelProvider::Anthropic),
+        ..Default::default()
     };
 
     let response = client.text().generate(request).await?;
-    println!("{:?}", response);
+    println!("{}", response.text);
+    println!(
+        "Tokens: {} prompt, {} generated",
+        response.tokens_prompt, response.tokens_generated
+    );
 
     Ok(())
 }
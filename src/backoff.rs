@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Jitter {
+    #[default]
+    None,
+    // Anywhere between zero and the computed delay; spreads retries the most.
+    Full,
+    // At least half of the computed delay, plus a random share of the rest.
+    Equal,
+}
+
+// Exponential backoff schedule. Use `delay` to compute a single attempt's
+// wait, or iterate to get successive delays until `max_attempts` runs out.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    pub base: Duration,
+    pub max: Duration,
+    pub multiplier: f64,
+    pub jitter: Jitter,
+    pub max_attempts: Option<u32>,
+    attempt: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(100), Duration::from_secs(20)).with_jitter(Jitter::Full)
+    }
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max,
+            multiplier: 2.0,
+            jitter: Jitter::None,
+            max_attempts: None,
+            attempt: 0,
+        }
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self.base.as_secs_f64() * self.multiplier.powi(attempt as i32);
+        let capped = Duration::from_secs_f64(exponential.min(self.max.as_secs_f64()));
+
+        match self.jitter {
+            Jitter::None => capped,
+            Jitter::Full => capped.mul_f64(fastrand::f64()),
+            Jitter::Equal => {
+                let half = capped / 2;
+                half + half.mul_f64(fastrand::f64())
+            }
+        }
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        if self
+            .max_attempts
+            .is_some_and(|max_attempts| self.attempt >= max_attempts)
+        {
+            return None;
+        }
+
+        let delay = self.delay(self.attempt);
+        self.attempt = self.attempt.saturating_add(1);
+        Some(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delays_grow_and_cap() {
+        let delays: Vec<Duration> =
+            Backoff::new(Duration::from_millis(100), Duration::from_millis(500))
+                .with_max_attempts(5)
+                .collect();
+
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_millis(100),
+                Duration::from_millis(200),
+                Duration::from_millis(400),
+                Duration::from_millis(500),
+                Duration::from_millis(500),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let base = Duration::from_millis(100);
+        let full = Backoff::new(base, base).with_jitter(Jitter::Full);
+        let equal = Backoff::new(base, base).with_jitter(Jitter::Equal);
+
+        for _ in 0..100 {
+            assert!(full.delay(3) <= base);
+            let delay = equal.delay(3);
+            assert!(delay >= base / 2 && delay <= base);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{
+    backoff::{Backoff, Jitter},
+    config::CircuitBreakerConfig,
+    error::{BedrockError, Result},
+};
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Closed,
+    Open { until: Instant },
+    // A single probe call is let through to test whether the service recovered.
+    HalfOpen { probe_started: Instant },
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    phase: Phase,
+    consecutive_failures: u32,
+    first_failure_at: Option<Instant>,
+    failed_probes: u32,
+}
+
+// Shared between the text, image and embedding clients so an outage seen by
+// one of them protects the others too.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Arc<Mutex<BreakerState>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Arc::new(Mutex::new(BreakerState {
+                phase: Phase::Closed,
+                consecutive_failures: 0,
+                first_failure_at: None,
+                failed_probes: 0,
+            })),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        matches!(state.phase, Phase::Open { until } if Instant::now() < until)
+    }
+
+    pub async fn call<T>(&self, operation: impl Future<Output = Result<T>>) -> Result<T> {
+        self.acquire()?;
+
+        let result = operation.await;
+        match &result {
+            Err(e) if e.is_transient() => self.record_failure(),
+            // Any other outcome means Bedrock answered, so it is reachable.
+            _ => self.record_success(),
+        }
+        result
+    }
+
+    fn acquire(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+
+        match state.phase {
+            Phase::Closed => Ok(()),
+            Phase::Open { until } if now >= until => {
+                state.phase = Phase::HalfOpen { probe_started: now };
+                Ok(())
+            }
+            Phase::Open { until } => Err(BedrockError::CircuitOpen(format!(
+                "Bedrock calls suspended for another {}ms after repeated failures",
+                until.duration_since(now).as_millis()
+            ))),
+            // A probe that was dropped before finishing must not wedge the breaker.
+            Phase::HalfOpen { probe_started }
+                if now.duration_since(probe_started) >= self.config.cooldown =>
+            {
+                state.phase = Phase::HalfOpen { probe_started: now };
+                Ok(())
+            }
+            Phase::HalfOpen { .. } => Err(BedrockError::CircuitOpen(
+                "Bedrock recovery probe in progress".into(),
+            )),
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.phase = Phase::Closed;
+        state.consecutive_failures = 0;
+        state.first_failure_at = None;
+        state.failed_probes = 0;
+    }
+
+    // Jittered so that many clients sharing an outage do not probe in lockstep.
+    fn cooldown(&self, failed_probes: u32) -> std::time::Duration {
+        Backoff::new(self.config.cooldown, self.config.max_cooldown)
+            .with_jitter(Jitter::Equal)
+            .delay(failed_probes)
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+
+        if let Phase::HalfOpen { .. } = state.phase {
+            log::warn!("Bedrock recovery probe failed, reopening circuit");
+            state.failed_probes = state.failed_probes.saturating_add(1);
+            state.phase = Phase::Open {
+                until: now + self.cooldown(state.failed_probes),
+            };
+            return;
+        }
+
+        let window_expired = state
+            .first_failure_at
+            .is_none_or(|first| now.duration_since(first) > self.config.window);
+        if window_expired {
+            state.consecutive_failures = 0;
+            state.first_failure_at = Some(now);
+        }
+        state.consecutive_failures += 1;
+
+        if state.consecutive_failures >= self.config.failure_threshold {
+            log::warn!(
+                "Opening Bedrock circuit after {} consecutive failures",
+                state.consecutive_failures
+            );
+            state.phase = Phase::Open {
+                until: now + self.cooldown(0),
+            };
+            state.consecutive_failures = 0;
+            state.first_failure_at = None;
+        }
+    }
+}
+
+pub(crate) async fn guarded<T>(
+    breaker: Option<&CircuitBreaker>,
+    operation: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    match breaker {
+        Some(breaker) => breaker.call(operation).await,
+        None => operation.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn breaker(cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 2,
+            window: Duration::from_secs(60),
+            cooldown,
+            max_cooldown: cooldown * 4,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_opens_after_threshold_and_recovers_after_cooldown() {
+        let breaker = breaker(Duration::from_millis(20));
+        let fail = || async { Err::<(), _>(BedrockError::AwsError("timeout".into())) };
+
+        assert!(breaker.call(fail()).await.is_err());
+        assert!(!breaker.is_open());
+        assert!(breaker.call(fail()).await.is_err());
+        assert!(breaker.is_open());
+        assert!(matches!(
+            breaker.call(async { Ok(()) }).await,
+            Err(BedrockError::CircuitOpen(_))
+        ));
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(breaker.call(async { Ok(()) }).await.is_ok());
+        assert!(!breaker.is_open());
+    }
+
+    #[tokio::test]
+    async fn test_request_errors_do_not_trip_the_breaker() {
+        let breaker = breaker(Duration::from_secs(60));
+        for _ in 0..3 {
+            let _ = breaker
+                .call(async { Err::<(), _>(BedrockError::RequestError("bad".into())) })
+                .await;
+        }
+        assert!(!breaker.is_open());
+    }
+}
@@ -1,56 +1,223 @@
 use crate::{
+    bedrock::{
+        circuit_breaker::{guarded, CircuitBreaker},
+        log_request_id, map_aws_error,
+        payload_log::log_payload,
+        region_check::{check_region, RegionCheck},
+    },
     error::{BedrockError, Result},
-    models::ImageGenerationRequest,
+    models::{ImageFormat, ImageGenerationRequest, ImageGenerationResponse},
 };
 use aws_sdk_bedrockruntime::{primitives::Blob, Client};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use serde_json::json;
 
+const TITAN_MAX_SEED: u64 = 2_147_483_646;
+const STABILITY_MAX_SEED: u64 = u32::MAX as u64;
+
 #[derive(Clone)]
 pub struct ImageClient {
     client: Client,
+    circuit_breaker: Option<CircuitBreaker>,
+    log_payloads: bool,
+    region_check: Option<RegionCheck>,
 }
 
 impl ImageClient {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            circuit_breaker: None,
+            log_payloads: false,
+            region_check: None,
+        }
+    }
+
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(breaker);
+        self
+    }
+
+    pub fn with_payload_logging(mut self, enabled: bool) -> Self {
+        self.log_payloads = enabled;
+        self
+    }
+
+    pub(crate) fn with_region_check(mut self, check: RegionCheck) -> Self {
+        self.region_check = Some(check);
+        self
+    }
+
+    pub async fn generate(
+        &self,
+        request: ImageGenerationRequest,
+    ) -> Result<ImageGenerationResponse> {
+        let body: serde_json::Value =
+            serde_json::from_slice(&self.invoke(&request, "application/json").await?)
+                .map_err(|e| BedrockError::ResponseError(e.to_string()))?;
+        ImageGenerationResponse::from_body(Self::model_id(&request), &body)
+    }
+
+    // Returns the first image's bytes. PNG output is requested directly from
+    // models that support it; everything else is decoded from the JSON body.
+    pub async fn generate_bytes(&self, request: ImageGenerationRequest) -> Result<Vec<u8>> {
+        let model_id = Self::model_id(&request);
+        if request.output_format == ImageFormat::Png && Self::supports_raw_output(model_id) {
+            return self.invoke(&request, "image/png").await;
+        }
+
+        let response = self.generate(request).await?;
+        STANDARD
+            .decode(response.image_data())
+            .map_err(|e| BedrockError::ResponseError(format!("Invalid base64 image: {}", e)))
     }
 
-    pub async fn generate(&self, request: ImageGenerationRequest) -> Result<String> {
-        let model_id = request
+    fn model_id(request: &ImageGenerationRequest) -> &str {
+        request
             .model_id
             .as_deref()
-            .unwrap_or("amazon.titan-image-generator-v1");
-        let request_payload = json!({
-            "taskType": "TEXT_IMAGE",
-            "textToImageParams": {
-                "text": request.prompt,
+            .unwrap_or("amazon.titan-image-generator-v1")
+    }
+
+    // Titan only answers in JSON; Stability models honour an image accept type.
+    fn supports_raw_output(model_id: &str) -> bool {
+        model_id.starts_with("stability.")
+    }
+
+    async fn invoke(&self, request: &ImageGenerationRequest, accept: &str) -> Result<Vec<u8>> {
+        let model_id = Self::model_id(request);
+        check_region(self.region_check.as_ref(), model_id);
+        let cfg_scale = request.cfg_scale.unwrap_or(8.0);
+        let mut request_payload = if model_id.starts_with("stability.") {
+            json!({
+                "text_prompts": [{ "text": request.prompt, "weight": 1.0 }],
                 "width": request.width.unwrap_or(1024),
-                "height": request.height.unwrap_or(1024)
-            },
-            "imageGenerationConfig": {
-                "numberOfImages": request.num_images.unwrap_or(1),
-                "quality": "standard",
-                "cfgScale": 8.0
-            }
-        });
+                "height": request.height.unwrap_or(1024),
+                "samples": request.num_images.unwrap_or(1),
+                "steps": request.steps.unwrap_or(30),
+                "cfg_scale": cfg_scale
+            })
+        } else {
+            json!({
+                "taskType": "TEXT_IMAGE",
+                "textToImageParams": {
+                    "text": request.prompt,
+                    "width": request.width.unwrap_or(1024),
+                    "height": request.height.unwrap_or(1024)
+                },
+                "imageGenerationConfig": {
+                    "numberOfImages": request.num_images.unwrap_or(1),
+                    "quality": "standard",
+                    "cfgScale": cfg_scale
+                }
+            })
+        };
+        Self::apply_conditioning(&mut request_payload, request, model_id)?;
+        Self::apply_seed(&mut request_payload, request, model_id)?;
+
         let request_json = serde_json::to_string(&request_payload)
             .map_err(|e| BedrockError::SerializationError(e.to_string()))?;
 
         log::info!("Generating image with model: {}", model_id);
-        log::debug!("Image request payload: {}", request_json);
-
-        let response = self
-            .client
-            .invoke_model()
-            .model_id(model_id)
-            .content_type("application/json")
-            .accept("application/json")
-            .body(Blob::new(request_json.into_bytes()))
-            .send()
-            .await
-            .map_err(|e| BedrockError::AwsError(e.to_string()))?;
-
-        let response_bytes = response.body.into_inner();
-        String::from_utf8(response_bytes).map_err(|e| BedrockError::ResponseError(e.to_string()))
+        log_payload(
+            self.log_payloads,
+            "request",
+            model_id,
+            request_json.as_bytes(),
+        );
+
+        let response = guarded(self.circuit_breaker.as_ref(), async {
+            self.client
+                .invoke_model()
+                .model_id(model_id)
+                .content_type("application/json")
+                .accept(accept)
+                .body(Blob::new(request_json.into_bytes()))
+                .send()
+                .await
+                .map_err(map_aws_error)
+        })
+        .await?;
+        log_request_id(model_id, &response);
+
+        // Raw image bytes are not worth logging, only JSON bodies.
+        if accept == "application/json" {
+            log_payload(
+                self.log_payloads,
+                "response",
+                model_id,
+                response.body.as_ref(),
+            );
+        }
+        Ok(response.body.into_inner())
+    }
+
+    fn apply_seed(
+        payload: &mut serde_json::Value,
+        request: &ImageGenerationRequest,
+        model_id: &str,
+    ) -> Result<()> {
+        let Some(seed) = request.seed else {
+            return Ok(());
+        };
+
+        let stability = model_id.starts_with("stability.");
+        let max = if stability {
+            STABILITY_MAX_SEED
+        } else {
+            TITAN_MAX_SEED
+        };
+        if seed > max {
+            return Err(BedrockError::RequestError(format!(
+                "Seed {} is out of range for {}; expected 0 to {}",
+                seed, model_id, max
+            )));
+        }
+
+        if stability {
+            payload["seed"] = json!(seed);
+        } else {
+            payload["imageGenerationConfig"]["seed"] = json!(seed);
+        }
+        Ok(())
+    }
+
+    fn apply_conditioning(
+        payload: &mut serde_json::Value,
+        request: &ImageGenerationRequest,
+        model_id: &str,
+    ) -> Result<()> {
+        let Some(condition_image) = &request.condition_image else {
+            if request.control_mode.is_some() || request.control_strength.is_some() {
+                return Err(BedrockError::RequestError(
+                    "controlMode and controlStrength require a condition image".into(),
+                ));
+            }
+            return Ok(());
+        };
+
+        if !model_id.contains("titan-image-generator-v2") {
+            return Err(BedrockError::RequestError(format!(
+                "Image conditioning is only supported by Titan Image Generator v2, not {}",
+                model_id
+            )));
+        }
+
+        let params = &mut payload["textToImageParams"];
+        params["conditionImage"] = json!(condition_image);
+        if let Some(mode) = request.control_mode {
+            params["controlMode"] = json!(mode);
+        }
+        if let Some(strength) = request.control_strength {
+            if !(0.0..=1.0).contains(&strength) {
+                return Err(BedrockError::RequestError(format!(
+                    "controlStrength must be between 0.0 and 1.0, got {}",
+                    strength
+                )));
+            }
+            params["controlStrength"] = json!(strength);
+        }
+
+        Ok(())
     }
 }
@@ -1,19 +1,67 @@
+pub mod circuit_breaker;
 pub mod image_client;
+mod payload_log;
+mod region_check;
 pub mod text_client;
 pub mod vector_client;
 
 use crate::{
     config::{BedrockConfig, Config},
     error::Result,
-    storage::VectorStorageManager,
+    error::REQUEST_ID_MARKER,
+    models::EmbeddingInputType,
+    storage::{traits::VectorStorage, VectorStorageManager},
     BedrockError,
 };
-use aws_sdk_bedrockruntime::Client;
-use std::sync::Arc;
+use aws_runtime::env_config::file::{EnvConfigFileKind, EnvConfigFiles};
+use aws_sdk_bedrockruntime::{
+    error::{ProvideErrorMetadata, SdkError},
+    operation::RequestId,
+    Client,
+};
+use futures::{StreamExt, TryStreamExt};
+use std::{fmt::Debug, sync::Arc};
 
+pub use circuit_breaker::CircuitBreaker;
 pub use image_client::ImageClient;
+use region_check::RegionCheck;
 pub use text_client::TextClient;
-pub use vector_client::VectorClient;
+pub use vector_client::{VectorClient, DEFAULT_EMBEDDING_MODEL};
+
+pub(crate) fn map_aws_error<E, R>(e: SdkError<E, R>) -> BedrockError
+where
+    E: ProvideErrorMetadata + Debug,
+    R: Debug,
+    SdkError<E, R>: RequestId,
+{
+    log::error!("AWS SDK error details: {:?}", e);
+
+    // AWS support and CloudTrail lookups need the request id, so it is kept
+    // in the message; `BedrockError::request_id` reads it back.
+    let request_id = e
+        .request_id()
+        .map(|id| format!(" {}{})", REQUEST_ID_MARKER, id))
+        .unwrap_or_default();
+
+    if let Some(service_error) = e.as_service_error() {
+        log::error!("Service error code: {:?}", service_error.code());
+        log::error!("Service error message: {:?}", service_error.message());
+        BedrockError::AwsServiceError(format!(
+            "Bedrock service error: {} - {}{}",
+            service_error.code().unwrap_or("unknown"),
+            service_error.message().unwrap_or("no message"),
+            request_id
+        ))
+    } else {
+        BedrockError::AwsError(format!("AWS SDK error: {}{}", e, request_id))
+    }
+}
+
+pub(crate) fn log_request_id(model_id: &str, response: &impl RequestId) {
+    if let Some(request_id) = response.request_id() {
+        log::debug!("Bedrock request id for {}: {}", model_id, request_id);
+    }
+}
 
 #[derive(Clone)]
 pub struct BedrockClient {
@@ -21,14 +69,22 @@ pub struct BedrockClient {
     image_client: ImageClient,
     vector_client: VectorClient,
     storage: Option<Arc<VectorStorageManager>>,
+    prompt_template: Option<String>,
+    query_rewrite_model: Option<String>,
+    embedding_model: Option<String>,
 }
 
 impl BedrockClient {
     pub async fn new(bedrock_config: BedrockConfig) -> Result<Self> {
-        let aws_config = if let (Some(access_key), Some(secret_key)) =
+        let provider_defaults = bedrock_config.provider_defaults.clone();
+        let circuit_breaker = bedrock_config
+            .circuit_breaker
+            .clone()
+            .map(CircuitBreaker::new);
+        let mut aws_config = if let (Some(access_key), Some(secret_key)) =
             (&bedrock_config.access_key, &bedrock_config.secret_key)
         {
-            aws_config::from_env()
+            aws_config::defaults(aws_config::BehaviorVersion::latest())
                 .credentials_provider(aws_sdk_bedrockruntime::config::Credentials::new(
                     access_key,
                     secret_key,
@@ -43,18 +99,89 @@ impl BedrockClient {
                 ))
                 .load()
                 .await
+        } else if bedrock_config.credentials_file.is_some() || bedrock_config.profile.is_some() {
+            let files = EnvConfigFiles::builder().include_default_config_file(true);
+            let files = match &bedrock_config.credentials_file {
+                Some(path) => files.with_file(EnvConfigFileKind::Credentials, path),
+                None => files.include_default_credentials_file(true),
+            };
+            let mut provider = aws_config::profile::ProfileFileCredentialsProvider::builder()
+                .profile_files(files.build());
+            if let Some(profile) = &bedrock_config.profile {
+                provider = provider.profile_name(profile);
+            }
+
+            let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest())
+                .credentials_provider(provider.build());
+            if let Some(region) = bedrock_config.region.clone() {
+                loader = loader.region(aws_sdk_bedrockruntime::config::Region::new(region));
+            }
+            loader.load().await
         } else {
-            aws_config::load_from_env().await
+            aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await
         };
 
+        // The resolved credentials act as the base identity for the role exchange.
+        if let Some(role_arn) = bedrock_config.assume_role_arn {
+            let mut role_provider = aws_config::sts::AssumeRoleProvider::builder(role_arn)
+                .session_name("rgenai-bedrock-client")
+                .configure(&aws_config);
+            if let Some(external_id) = bedrock_config.external_id {
+                role_provider = role_provider.external_id(external_id);
+            }
+
+            aws_config = aws_config
+                .into_builder()
+                .credentials_provider(
+                    aws_sdk_bedrockruntime::config::SharedCredentialsProvider::new(
+                        role_provider.build().await,
+                    ),
+                )
+                .build();
+        }
+
         let client = Client::new(&aws_config);
 
+        let mut text_client = TextClient::new(client.clone())
+            .with_provider_defaults(provider_defaults)
+            .with_prompt_wrapper(
+                bedrock_config.prompt_prefix.clone(),
+                bedrock_config.prompt_suffix.clone(),
+            )
+            .with_stream_resumes(bedrock_config.max_stream_resumes)
+            .with_stream_fallback(bedrock_config.fallback_to_non_streaming);
+        let mut image_client = ImageClient::new(client.clone());
+        let mut vector_client = VectorClient::new(client.clone())
+            .with_long_input_strategy(bedrock_config.long_embedding_input);
+        if bedrock_config.log_payloads {
+            text_client = text_client.with_payload_logging(true);
+            image_client = image_client.with_payload_logging(true);
+            vector_client = vector_client.with_payload_logging(true);
+        }
+        if let Some(guard) = bedrock_config.input_guard.clone() {
+            text_client = text_client.with_input_guard(guard);
+        }
+        if let Some(breaker) = circuit_breaker {
+            text_client = text_client.with_circuit_breaker(breaker.clone());
+            image_client = image_client.with_circuit_breaker(breaker.clone());
+            vector_client = vector_client.with_circuit_breaker(breaker);
+        }
+        if let Some(region) = aws_config.region() {
+            let check = RegionCheck::new(region.as_ref());
+            text_client = text_client.with_region_check(check.clone());
+            image_client = image_client.with_region_check(check.clone());
+            vector_client = vector_client.with_region_check(check);
+        }
+
         Ok(Self {
-            text_client: TextClient::new(client.clone()),
-            image_client: ImageClient::new(client.clone()),
-            vector_client: VectorClient::new(client.clone()),
+            text_client,
+            image_client,
+            vector_client,
 
             storage: None,
+            prompt_template: None,
+            query_rewrite_model: None,
+            embedding_model: bedrock_config.embedding_model,
         })
     }
 
@@ -70,6 +197,70 @@ impl BedrockClient {
         Ok(client)
     }
 
+    pub async fn with_storage_backend(
+        bedrock_config: BedrockConfig,
+        backend: Arc<dyn VectorStorage>,
+    ) -> Result<Self> {
+        let mut client = Self::new(bedrock_config).await?;
+        client.storage = Some(Arc::new(VectorStorageManager::from_backend(backend)));
+
+        Ok(client)
+    }
+
+    pub async fn moderate(
+        &self,
+        text: &str,
+        guardrail_id: &str,
+    ) -> Result<crate::models::moderation::ModerationResult> {
+        self.text_client
+            .moderate(
+                text,
+                guardrail_id,
+                crate::models::moderation::DEFAULT_GUARDRAIL_VERSION,
+            )
+            .await
+    }
+
+    // Model used to condense a conversation into a standalone search query;
+    // a small, cheap model is enough.
+    pub fn with_query_rewrite_model(mut self, model_id: impl Into<String>) -> Self {
+        self.query_rewrite_model = Some(model_id.into());
+        self
+    }
+
+    pub fn with_embedding_model(mut self, model_id: impl Into<String>) -> Self {
+        self.embedding_model = Some(model_id.into());
+        self
+    }
+
+    pub fn embedding_model(&self) -> &str {
+        self.embedding_model
+            .as_deref()
+            .unwrap_or(DEFAULT_EMBEDDING_MODEL)
+    }
+
+    // A per-call model that differs from the bound one is almost always a
+    // bug: vectors from different models can't be compared.
+    fn resolve_embedding_model<'a>(&'a self, model_id: Option<&'a str>) -> &'a str {
+        if let (Some(requested), Some(bound)) = (model_id, self.embedding_model.as_deref()) {
+            if requested != bound {
+                log::warn!(
+                    "Using embedding model {} instead of the client's bound model {}; its vectors are not comparable with ones stored under {}",
+                    requested,
+                    bound,
+                    bound
+                );
+            }
+        }
+        model_id.unwrap_or_else(|| self.embedding_model())
+    }
+
+    // Templates use `{context}` and `{query}` placeholders.
+    pub fn with_prompt_template(mut self, template: impl Into<String>) -> Self {
+        self.prompt_template = Some(template.into());
+        self
+    }
+
     pub fn text(&self) -> &TextClient {
         &self.text_client
     }
@@ -86,38 +277,76 @@ impl BedrockClient {
         self.storage.as_ref()
     }
 
-    pub async fn embed_and_store(
+    pub async fn generate_text(&self, prompt: &str) -> Result<String> {
+        self.text_client.quick(prompt).await
+    }
+
+    // Embeds `text` as a search query; stored documents go through
+    // `embed_and_store`, which embeds them as documents.
+    pub async fn embed(&self, text: &str, model_id: Option<&str>) -> Result<Vec<f32>> {
+        self.embed_as(text, model_id, EmbeddingInputType::SearchQuery)
+            .await
+    }
+
+    async fn embed_as(
         &self,
         text: &str,
         model_id: Option<&str>,
-        metadata: Option<std::collections::HashMap<String, serde_json::Value>>,
-        namespace: Option<&str>,
-    ) -> Result<crate::models::storage::InsertResult> {
+        input_type: EmbeddingInputType,
+    ) -> Result<Vec<f32>> {
         let embedding_request = crate::models::embedding::EmbeddingRequest {
             text: text.to_string(),
-            model_id: model_id.map(String::from),
+            model_id: Some(
+                model_id
+                    .unwrap_or_else(|| self.embedding_model())
+                    .to_string(),
+            ),
+            input_type,
         };
 
-        let embedding_response = self
+        Ok(self
             .vector_client
             .generate_embedding(embedding_request)
-            .await?;
+            .await?
+            .embedding)
+    }
 
-        let response_json: serde_json::Value = serde_json::from_str(&embedding_response)
-            .map_err(|e| BedrockError::ResponseError(e.to_string()))?;
+    // Results keep the order of `texts`; at most `MAX_CONCURRENT_EMBEDDINGS`
+    // requests run at once.
+    pub async fn embed_batch(
+        &self,
+        texts: &[&str],
+        model_id: Option<&str>,
+        input_type: EmbeddingInputType,
+    ) -> Result<Vec<Vec<f32>>> {
+        futures::stream::iter(texts)
+            .map(|text| self.embed_as(text, model_id, input_type))
+            .buffered(vector_client::MAX_CONCURRENT_EMBEDDINGS)
+            .try_collect()
+            .await
+    }
 
-        let embedding = response_json["embedding"]
-            .as_array()
-            .ok_or_else(|| BedrockError::ResponseError("No embedding found in response".into()))?
-            .iter()
-            .filter_map(|v| v.as_f64().map(|f| f as f32))
-            .collect();
+    pub async fn embed_and_store(
+        &self,
+        text: &str,
+        model_id: Option<&str>,
+        metadata: Option<std::collections::HashMap<String, serde_json::Value>>,
+        namespace: Option<&str>,
+    ) -> Result<crate::models::storage::InsertResult> {
+        let model = self.resolve_embedding_model(model_id);
+        let embedding = self
+            .embed_as(text, Some(model), EmbeddingInputType::SearchDocument)
+            .await?;
 
         if let Some(storage) = &self.storage {
+            let mut metadata = metadata.unwrap_or_default();
+            crate::models::storage::describe_vector(&mut metadata, model, &embedding);
+
             let insert_record = crate::models::storage::VectorInsert {
                 id: None,
                 vector: embedding,
-                metadata: metadata.unwrap_or_default(),
+                sparse_vector: None,
+                metadata,
                 content: Some(text.to_string()),
                 namespace: namespace.map(String::from),
             };
@@ -129,6 +358,59 @@ impl BedrockClient {
             ))
         }
     }
+    pub async fn ingest_document(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        chunk_size: usize,
+        chunk_overlap: usize,
+        model_id: Option<&str>,
+        namespace: Option<&str>,
+    ) -> Result<Vec<crate::models::storage::InsertResult>> {
+        let path = path.as_ref();
+        let loader = crate::loaders::loader_for_path(path)?;
+        self.ingest_with_loader(
+            loader.as_ref(),
+            path,
+            chunk_size,
+            chunk_overlap,
+            model_id,
+            namespace,
+        )
+        .await
+    }
+
+    pub async fn ingest_with_loader(
+        &self,
+        loader: &dyn crate::loaders::DocumentLoader,
+        path: &std::path::Path,
+        chunk_size: usize,
+        chunk_overlap: usize,
+        model_id: Option<&str>,
+        namespace: Option<&str>,
+    ) -> Result<Vec<crate::models::storage::InsertResult>> {
+        let documents = loader.load(path)?;
+
+        let mut results = Vec::new();
+        for document in documents {
+            let chunks = crate::loaders::chunk_text(&document.text, chunk_size, chunk_overlap);
+            let chunk_count = chunks.len();
+            log::info!("Ingesting {} chunks from {}", chunk_count, path.display());
+
+            for (index, chunk) in chunks.into_iter().enumerate() {
+                let mut metadata = document.metadata.clone();
+                metadata.insert("chunk_index".to_string(), serde_json::json!(index));
+                metadata.insert("chunk_count".to_string(), serde_json::json!(chunk_count));
+
+                results.push(
+                    self.embed_and_store(&chunk, model_id, Some(metadata), namespace)
+                        .await?,
+                );
+            }
+        }
+
+        Ok(results)
+    }
+
     pub async fn semantic_search(
         &self,
         query: &str,
@@ -137,42 +419,40 @@ impl BedrockClient {
         namespace: Option<&str>,
         include_content: bool,
     ) -> Result<crate::models::storage::VectorSearchResponse> {
-        let embedding_request = crate::models::embedding::EmbeddingRequest {
-            text: query.to_string(),
-            model_id: model_id.map(String::from),
-        };
+        let model = self.resolve_embedding_model(model_id);
+        let embedding = self.embed(query, Some(model)).await?;
 
-        let embedding_response = self
-            .vector_client
-            .generate_embedding(embedding_request)
-            .await?;
-        let response_json: serde_json::Value = serde_json::from_str(&embedding_response)
-            .map_err(|e| BedrockError::ResponseError(e.to_string()))?;
+        if let Some(storage) = &self.storage {
+            let mut builder = crate::models::storage::VectorSearch::builder(embedding)
+                .limit(limit)
+                .include_content(include_content);
+            if let Some(namespace) = namespace {
+                builder = builder.namespace(namespace);
+            }
 
-        let embedding = response_json["embedding"]
-            .as_array()
-            .ok_or_else(|| BedrockError::ResponseError("No embedding found in response".into()))?
-            .iter()
-            .filter_map(|v| v.as_f64().map(|f| f as f32))
-            .collect();
+            let mut response = storage.search(builder.build()).await?;
 
-        if let Some(storage) = &self.storage {
-            let search_query = crate::models::storage::VectorSearch {
-                vector: embedding,
-                limit,
-                namespace: namespace.map(String::from),
-                filter: None,
-                include_metadata: true,
-                include_content,
-            };
+            // Filtered here rather than server-side so records stored before
+            // model tagging, which carry no `embedding_model`, stay searchable.
+            // Only records tagged with a different model are dropped.
+            let dropped = response.retain_embedding_model(model);
+            if dropped > 0 {
+                log::warn!(
+                    "Namespace {:?} contains vectors from embedding models other than {}; dropped {} results",
+                    namespace,
+                    model,
+                    dropped
+                );
+            }
 
-            storage.search(search_query).await
+            Ok(response)
         } else {
             Err(BedrockError::ConfigError(
                 "No storage backend configured".into(),
             ))
         }
     }
+    #[allow(clippy::too_many_arguments)]
     pub async fn generate_with_context(
         &self,
         query: &str,
@@ -183,39 +463,211 @@ impl BedrockClient {
         max_tokens: Option<i32>,
         temperature: Option<f32>,
     ) -> Result<String> {
+        self.generate_with_context_detailed(
+            query,
+            context_limit,
+            generation_model,
+            embedding_model,
+            namespace,
+            max_tokens,
+            temperature,
+        )
+        .await
+        .map(|response| response.answer)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn generate_with_context_detailed(
+        &self,
+        query: &str,
+        context_limit: usize,
+        generation_model: Option<&str>,
+        embedding_model: Option<&str>,
+        namespace: Option<&str>,
+        max_tokens: Option<i32>,
+        temperature: Option<f32>,
+    ) -> Result<crate::models::rag::RagResponse> {
+        let context = self
+            .retrieve_context(query, context_limit, embedding_model, namespace)
+            .await?;
+
+        self.generate_from_context_detailed(
+            query,
+            context,
+            generation_model,
+            max_tokens,
+            temperature,
+        )
+        .await
+    }
+
+    async fn retrieve_context(
+        &self,
+        query: &str,
+        context_limit: usize,
+        embedding_model: Option<&str>,
+        namespace: Option<&str>,
+    ) -> Result<Vec<String>> {
         let search_results = self
             .semantic_search(query, context_limit, embedding_model, namespace, true)
             .await?;
-        let context: Vec<String> = search_results
+        Ok(search_results
             .results
-            .iter()
-            .filter_map(|result| result.content.as_ref())
-            .cloned()
-            .collect();
+            .into_iter()
+            .filter_map(|result| result.content)
+            .collect())
+    }
+
+    pub async fn generate_from_context(
+        &self,
+        query: &str,
+        context: Vec<String>,
+        generation_model: Option<&str>,
+        max_tokens: Option<i32>,
+        temperature: Option<f32>,
+    ) -> Result<String> {
+        self.generate_from_context_detailed(
+            query,
+            context,
+            generation_model,
+            max_tokens,
+            temperature,
+        )
+        .await
+        .map(|response| response.answer)
+    }
+
+    pub async fn generate_from_context_detailed(
+        &self,
+        query: &str,
+        context: Vec<String>,
+        generation_model: Option<&str>,
+        max_tokens: Option<i32>,
+        temperature: Option<f32>,
+    ) -> Result<crate::models::rag::RagResponse> {
+        self.answer_from_context(
+            &[],
+            query,
+            context,
+            generation_model,
+            max_tokens,
+            temperature,
+        )
+        .await
+    }
 
+    async fn answer_from_context(
+        &self,
+        history: &[crate::models::rag::ConversationTurn],
+        query: &str,
+        context: Vec<String>,
+        generation_model: Option<&str>,
+        max_tokens: Option<i32>,
+        temperature: Option<f32>,
+    ) -> Result<crate::models::rag::RagResponse> {
         if context.is_empty() {
             log::warn!("No relevant context found for query");
         }
+
         let context_text = context.join("\n\n");
-        let enhanced_prompt = if !context_text.is_empty() {
-            format!(
-                "Context:\n{}\n\nQuestion: {}\n\nAnswer based on the provided context:",
-                context_text, query
-            )
-        } else {
-            format!("Question: {}\n\nAnswer:", query)
-        };
+        let enhanced_prompt = crate::models::rag::build_context_prompt(
+            self.prompt_template.as_deref(),
+            history,
+            query,
+            &context_text,
+        );
 
         let text_request = crate::models::text::TextGenerationRequest {
-            prompt: enhanced_prompt,
+            prompt: enhanced_prompt.clone(),
             max_tokens,
             temperature,
             model_id: generation_model.map(String::from),
             stream: None,
             provider: None,
+            ..Default::default()
         };
 
-        let response = self.text_client.generate(text_request).await?;
+        let answer = self.text_client.generate(text_request).await?.text;
+        Ok(crate::models::rag::RagResponse {
+            answer,
+            enhanced_prompt,
+            context_text,
+            context,
+            search_query: None,
+        })
+    }
+
+    // Multi-turn RAG: follow-ups like "what about its battery?" are rewritten
+    // into a standalone query for retrieval. The answer is generated for the
+    // user's own message, with the conversation in the prompt.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn generate_with_conversation(
+        &self,
+        history: &[crate::models::rag::ConversationTurn],
+        query: &str,
+        context_limit: usize,
+        generation_model: Option<&str>,
+        embedding_model: Option<&str>,
+        namespace: Option<&str>,
+        max_tokens: Option<i32>,
+        temperature: Option<f32>,
+    ) -> Result<crate::models::rag::RagResponse> {
+        let standalone = self.rewrite_query(history, query).await?;
+        let context = self
+            .retrieve_context(&standalone, context_limit, embedding_model, namespace)
+            .await?;
+        let mut response = self
+            .answer_from_context(
+                history,
+                query,
+                context,
+                generation_model,
+                max_tokens,
+                temperature,
+            )
+            .await?;
+        response.search_query = Some(standalone);
         Ok(response)
     }
+
+    pub async fn rewrite_query(
+        &self,
+        history: &[crate::models::rag::ConversationTurn],
+        query: &str,
+    ) -> Result<String> {
+        if history.is_empty() {
+            return Ok(query.to_string());
+        }
+
+        let transcript = crate::models::rag::conversation_transcript(history);
+        let prompt = format!(
+            "Rewrite the final user message as a standalone search query, using the conversation for any missing context. Reply with the query only.\n\nConversation:\n{}\n\nFinal user message: {}\n\nStandalone query:",
+            transcript, query
+        );
+
+        let response = self
+            .text_client
+            .generate(crate::models::text::TextGenerationRequest {
+                prompt,
+                max_tokens: Some(128),
+                temperature: Some(0.0),
+                model_id: self.query_rewrite_model.clone(),
+                ..Default::default()
+            })
+            .await?;
+
+        let rewritten = Some(response.text.trim().trim_matches('"').trim().to_string())
+            .filter(|text| !text.is_empty());
+
+        match rewritten {
+            Some(rewritten) => {
+                log::debug!("Rewrote query {:?} as {:?}", query, rewritten);
+                Ok(rewritten)
+            }
+            None => {
+                log::warn!("Query rewrite returned no text; searching with the original message");
+                Ok(query.to_string())
+            }
+        }
+    }
 }
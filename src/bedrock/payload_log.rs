@@ -0,0 +1,79 @@
+use serde_json::Value;
+
+const MAX_LOGGED_PAYLOAD_LEN: usize = 4096;
+const MAX_LOGGED_VALUE_LEN: usize = 256;
+const MAX_LOGGED_ARRAY_LEN: usize = 16;
+
+// Base64 images and embedding vectors dwarf everything else in a payload, so
+// long strings and arrays are summarised before the whole body is capped.
+pub(crate) fn log_payload(enabled: bool, direction: &str, model_id: &str, body: &[u8]) {
+    if !enabled {
+        return;
+    }
+
+    let rendered = match serde_json::from_slice::<Value>(body) {
+        Ok(mut json) => {
+            shorten(&mut json);
+            json.to_string()
+        }
+        Err(_) => String::from_utf8_lossy(body).into_owned(),
+    };
+
+    log::info!(
+        "Bedrock {} for {}: {}",
+        direction,
+        model_id,
+        truncate(&rendered, MAX_LOGGED_PAYLOAD_LEN)
+    );
+}
+
+fn shorten(value: &mut Value) {
+    match value {
+        Value::String(s) if s.chars().count() > MAX_LOGGED_VALUE_LEN => {
+            *s = format!(
+                "{}... ({} chars)",
+                truncate(s, MAX_LOGGED_VALUE_LEN),
+                s.chars().count()
+            );
+        }
+        Value::Array(items) if items.len() > MAX_LOGGED_ARRAY_LEN => {
+            let total = items.len();
+            items.truncate(MAX_LOGGED_ARRAY_LEN);
+            items.iter_mut().for_each(shorten);
+            items.push(Value::String(format!("... ({} items)", total)));
+        }
+        Value::Array(items) => items.iter_mut().for_each(shorten),
+        Value::Object(map) => map.values_mut().for_each(shorten),
+        _ => {}
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => &text[..idx],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_shorten_summarises_large_values() {
+        let mut payload = json!({
+            "image": "a".repeat(1000),
+            "embedding": vec![0.5; 100],
+            "prompt": "short"
+        });
+        shorten(&mut payload);
+
+        assert!(payload["image"].as_str().unwrap().ends_with("(1000 chars)"));
+        assert_eq!(
+            payload["embedding"].as_array().unwrap().len(),
+            MAX_LOGGED_ARRAY_LEN + 1
+        );
+        assert_eq!(payload["prompt"], "short");
+    }
+}
@@ -0,0 +1,68 @@
+use crate::models::ModelRegistry;
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+// Flags models used outside the regions they are listed for before the call
+// fails with an opaque validation error. The static table goes stale, so this
+// only ever warns, once per model and client.
+#[derive(Debug, Clone)]
+pub(crate) struct RegionCheck {
+    region: String,
+    warned: Arc<Mutex<HashSet<String>>>,
+}
+
+impl RegionCheck {
+    pub(crate) fn new(region: impl Into<String>) -> Self {
+        Self {
+            region: region.into(),
+            warned: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    pub(crate) fn check(&self, model_id: &str) {
+        if ModelRegistry::is_available_in(model_id, &self.region) != Some(false) {
+            return;
+        }
+
+        if self.warned.lock().unwrap().insert(model_id.to_string()) {
+            log::warn!(
+                "Model {} is not listed for {} (listed: {}); if calls fail, check `aws bedrock list-foundation-models --region {}`",
+                model_id,
+                self.region,
+                ModelRegistry::known_regions(model_id)
+                    .unwrap_or_default()
+                    .join(", "),
+                self.region
+            );
+        }
+    }
+}
+
+pub(crate) fn check_region(check: Option<&RegionCheck>, model_id: &str) {
+    if let Some(check) = check {
+        check.check(model_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlisted_models_are_only_warned_about_once() {
+        let check = RegionCheck::new("eu-west-1");
+
+        check.check("ai21.jamba-1-5-large-v1:0");
+        check.check("ai21.jamba-1-5-large-v1:0");
+        check.check("amazon.titan-text-express-v1");
+        check.check("arn:aws:bedrock:eu-west-1:123:inference-profile/x");
+
+        let warned = check.warned.lock().unwrap();
+        assert_eq!(
+            warned.iter().collect::<Vec<_>>(),
+            ["ai21.jamba-1-5-large-v1:0"]
+        );
+    }
+}
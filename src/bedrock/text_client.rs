@@ -1,37 +1,327 @@
 use crate::{
+    backoff::Backoff,
+    bedrock::{
+        circuit_breaker::{guarded, CircuitBreaker},
+        log_request_id, map_aws_error,
+        payload_log::log_payload,
+        region_check::{check_region, RegionCheck},
+    },
     error::{BedrockError, Result},
-    models::{StreamChunk, TextGenerationRequest},
+    models::{
+        embedding::estimate_tokens,
+        text::{continuation_prompt, json_repair_prompt, parse_json},
+        ChatMessage, ContentBlock, GenerationPreset, InputGuard, MessageContent, ModerationResult,
+        Role, SamplingDefaults, StreamChunk, StreamEvent, StreamSummary, StructuredResponse,
+        TextGenerationRequest, TextGenerationResponse, DEFAULT_GUARDRAIL_VERSION,
+        STREAM_RESUMED_REASON, SUPPORTED_IMAGE_MEDIA_TYPES,
+    },
     ModelProvider,
 };
-use aws_sdk_bedrockruntime::{error::ProvideErrorMetadata, primitives::Blob, Client};
-use futures::stream::Stream;
+use aws_sdk_bedrockruntime::{
+    operation::RequestId,
+    primitives::Blob,
+    types::{
+        ContentBlock as ConverseContentBlock, ContentBlockDelta, ConversationRole, ConverseOutput,
+        ConverseStreamOutput, GuardrailAction, GuardrailContentBlock, GuardrailContentSource,
+        GuardrailTextBlock, ImageBlock, ImageFormat, ImageSource, InferenceConfiguration, Message,
+        SystemContentBlock, Trace,
+    },
+    Client,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::{
+    stream::{Stream, StreamExt},
+    Future,
+};
 use serde_json::json;
-use std::pin::Pin;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    time::Instant,
+};
 use tokio_stream::wrappers::ReceiverStream;
 
 #[derive(Clone)]
 pub struct TextClient {
     client: Client,
+    provider_defaults: HashMap<ModelProvider, SamplingDefaults>,
+    circuit_breaker: Option<CircuitBreaker>,
+    log_payloads: bool,
+    prompt_prefix: Option<String>,
+    prompt_suffix: Option<String>,
+    max_stream_resumes: u32,
+    stream_fallback: bool,
+    region_check: Option<RegionCheck>,
+    input_guard: Option<InputGuard>,
+    // Shared between clones so presets registered later are seen everywhere.
+    presets: Arc<RwLock<HashMap<String, GenerationPreset>>>,
 }
 
 impl TextClient {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            provider_defaults: HashMap::new(),
+            circuit_breaker: None,
+            log_payloads: false,
+            prompt_prefix: None,
+            prompt_suffix: None,
+            max_stream_resumes: 0,
+            stream_fallback: false,
+            region_check: None,
+            input_guard: None,
+            presets: Arc::new(RwLock::new(
+                GenerationPreset::builtin()
+                    .into_iter()
+                    .map(|(name, preset)| (name.to_string(), preset))
+                    .collect(),
+            )),
+        }
+    }
+
+    // Registers or replaces a named preset that requests can refer to.
+    pub fn register_preset(&self, name: impl Into<String>, preset: GenerationPreset) {
+        self.presets.write().unwrap().insert(name.into(), preset);
+    }
+
+    pub fn preset(&self, name: &str) -> Option<GenerationPreset> {
+        self.presets.read().unwrap().get(name).cloned()
+    }
+
+    fn resolve_preset<'a>(
+        &self,
+        request: &'a TextGenerationRequest,
+    ) -> Result<Cow<'a, TextGenerationRequest>> {
+        let Some(name) = &request.preset else {
+            return Ok(Cow::Borrowed(request));
+        };
+        let preset = self.preset(name).ok_or_else(|| {
+            BedrockError::RequestError(format!("Unknown generation preset: {}", name))
+        })?;
+        Ok(Cow::Owned(preset.apply_to(request)))
+    }
+
+    // How many times `generate_stream` may re-invoke the model after a
+    // transient mid-stream failure; zero disables resuming.
+    pub fn with_stream_resumes(mut self, max_resumes: u32) -> Self {
+        self.max_stream_resumes = max_resumes;
+        self
+    }
+
+    // When the model or region doesn't support streaming, `generate_stream`
+    // falls back to `generate` and yields the whole answer as one final chunk.
+    pub fn with_stream_fallback(mut self, enabled: bool) -> Self {
+        self.stream_fallback = enabled;
+        self
+    }
+
+    // Wraps every prompt this client sends. Chat-format models receive the
+    // prefix as the system prompt, ahead of any request `system_prompt`;
+    // prompt-format models get both prepended to the prompt text.
+    pub fn with_prompt_wrapper(mut self, prefix: Option<String>, suffix: Option<String>) -> Self {
+        self.prompt_prefix = prefix;
+        self.prompt_suffix = suffix;
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(breaker);
+        self
+    }
+
+    // Rejects requests whose text breaks the guard's limits before anything
+    // is sent, including the moderation guardrail.
+    pub fn with_input_guard(mut self, guard: InputGuard) -> Self {
+        self.input_guard = Some(guard);
+        self
+    }
+
+    pub fn with_payload_logging(mut self, enabled: bool) -> Self {
+        self.log_payloads = enabled;
+        self
+    }
+
+    pub(crate) fn with_region_check(mut self, check: RegionCheck) -> Self {
+        self.region_check = Some(check);
+        self
+    }
+
+    pub fn with_provider_defaults(
+        mut self,
+        provider_defaults: HashMap<ModelProvider, SamplingDefaults>,
+    ) -> Self {
+        self.provider_defaults = provider_defaults;
+        self
+    }
+
+    pub async fn moderate(
+        &self,
+        text: &str,
+        guardrail_id: &str,
+        guardrail_version: &str,
+    ) -> Result<ModerationResult> {
+        let content = GuardrailTextBlock::builder()
+            .text(text)
+            .build()
+            .map_err(|e| BedrockError::RequestError(e.to_string()))?;
+
+        log::info!(
+            "Applying guardrail: {} ({})",
+            guardrail_id,
+            guardrail_version
+        );
+
+        let response = guarded(self.circuit_breaker.as_ref(), async {
+            self.client
+                .apply_guardrail()
+                .guardrail_identifier(guardrail_id)
+                .guardrail_version(guardrail_version)
+                .source(GuardrailContentSource::Input)
+                .content(GuardrailContentBlock::Text(content))
+                .send()
+                .await
+                .map_err(map_aws_error)
+        })
+        .await?;
+
+        Ok(ModerationResult {
+            flagged: response.action() == &GuardrailAction::GuardrailIntervened,
+            action: response.action().as_str().to_string(),
+            reason: response.action_reason().map(String::from),
+            outputs: response
+                .outputs()
+                .iter()
+                .filter_map(|output| output.text().map(String::from))
+                .collect(),
+        })
+    }
+
+    async fn check_moderation(&self, request: &TextGenerationRequest) -> Result<()> {
+        if let Some(guard) = &self.input_guard {
+            guard.check(request)?;
+        }
+        let Some(guardrail_id) = request.moderation_guardrail_id.as_deref() else {
+            return Ok(());
+        };
+        let guardrail_version = request
+            .moderation_guardrail_version
+            .as_deref()
+            .unwrap_or(DEFAULT_GUARDRAIL_VERSION);
+
+        // Messages replace or extend the prompt, so their text is screened too.
+        let text = [request.prompt.clone()]
+            .into_iter()
+            .chain(
+                request
+                    .messages
+                    .iter()
+                    .flatten()
+                    .map(|message| message.content.text()),
+            )
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let verdict = self
+            .moderate(&text, guardrail_id, guardrail_version)
+            .await?;
+        if verdict.flagged {
+            return Err(BedrockError::ContentFiltered(
+                verdict
+                    .reason
+                    .unwrap_or_else(|| format!("Prompt blocked by guardrail {}", guardrail_id)),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub async fn generate(&self, request: TextGenerationRequest) -> Result<TextGenerationResponse> {
+        self.check_moderation(&request).await?;
+        self.generate_unchecked(request).await
     }
 
-    pub async fn generate(&self, request: TextGenerationRequest) -> Result<String> {
+    // `generate` without the input guard and moderation, for continuations
+    // whose only new text is the model's own output.
+    async fn generate_unchecked(
+        &self,
+        request: TextGenerationRequest,
+    ) -> Result<TextGenerationResponse> {
         let model_id = request
             .model_id
             .as_deref()
             .unwrap_or("amazon.titan-text-express-v1");
 
-        let request_payload = match request.provider.unwrap_or(ModelProvider::Amazon) {
+        let provider = request
+            .provider
+            .clone()
+            .unwrap_or_else(|| Self::provider_for_model(model_id));
+        check_region(self.region_check.as_ref(), model_id);
+        let request_payload = self.text_payload(&request, model_id, &provider)?;
+
+        let request_json = serde_json::to_string(&request_payload)
+            .map_err(|e| BedrockError::SerializationError(e.to_string()))?;
+
+        log::info!("Invoking model: {}", model_id);
+        log_payload(
+            self.log_payloads,
+            "request",
+            model_id,
+            request_json.as_bytes(),
+        );
+
+        let trace = request.trace.unwrap_or(false);
+        let response = guarded(self.circuit_breaker.as_ref(), async {
+            self.client
+                .invoke_model()
+                .model_id(model_id)
+                .content_type("application/json")
+                .accept("application/json")
+                .set_trace(trace.then_some(Trace::Enabled))
+                .body(Blob::new(request_json.into_bytes()))
+                .send()
+                .await
+                .map_err(map_aws_error)
+        })
+        .await?;
+        log_request_id(model_id, &response);
+        let request_id = response.request_id().map(String::from);
+
+        let response_bytes = response.body.into_inner();
+        log_payload(self.log_payloads, "response", model_id, &response_bytes);
+        let body: serde_json::Value = serde_json::from_slice(&response_bytes)
+            .map_err(|e| BedrockError::ResponseError(e.to_string()))?;
+
+        // Model ids can be inference profile ARNs, so trust an explicit
+        // provider over the id prefix `from_body` dispatches on.
+        let parsed = match provider {
+            ModelProvider::Mistral => TextGenerationResponse::from_mistral(model_id, &body),
+            ModelProvider::AI21 => TextGenerationResponse::from_ai21(model_id, &body),
+            _ => TextGenerationResponse::from_body(model_id, &body),
+        }?;
+        Ok(TextGenerationResponse {
+            request_id,
+            trace: trace
+                .then(|| body.get("amazon-bedrock-trace").cloned())
+                .flatten(),
+            ..parsed
+        })
+    }
+
+    fn text_payload(
+        &self,
+        request: &TextGenerationRequest,
+        model_id: &str,
+        provider: &ModelProvider,
+    ) -> Result<serde_json::Value> {
+        let request = &*self.resolve_preset(request)?;
+        let mut payload = match provider {
             ModelProvider::Amazon => json!({
                 "inputText": request.prompt,
                 "textGenerationConfig": {
                     "maxTokenCount": request.max_tokens.unwrap_or(512),
-                    "temperature": request.temperature.unwrap_or(0.7),
-                    "topP": 0.9
+                    "temperature": request.temperature.unwrap_or(0.7)
                 }
             }),
             ModelProvider::Anthropic => json!({
@@ -48,136 +338,389 @@ impl TextClient {
             ModelProvider::Cohere => json!({
                 "prompt": request.prompt,
                 "max_tokens": request.max_tokens.unwrap_or(512),
-                "temperature": request.temperature.unwrap_or(0.7),
-                "p": 0.9
+                "temperature": request.temperature.unwrap_or(0.7)
+            }),
+            ModelProvider::AI21 if Self::is_jamba(model_id) => json!({
+                "messages": [
+                    {
+                        "role": "user",
+                        "content": request.prompt
+                    }
+                ],
+                "max_tokens": request.max_tokens.unwrap_or(512),
+                "temperature": request.temperature.unwrap_or(0.7)
             }),
             ModelProvider::AI21 => json!({
                 "prompt": request.prompt,
                 "maxTokens": request.max_tokens.unwrap_or(512),
-                "temperature": request.temperature.unwrap_or(0.7),
-                "topP": 0.9
+                "temperature": request.temperature.unwrap_or(0.7)
             }),
-            ModelProvider::Meta | ModelProvider::Mistral => json!({
+            ModelProvider::Mistral if Self::is_mistral_chat(model_id) => {
+                let mut payload = json!({
+                    "messages": [
+                        {
+                            "role": "user",
+                            "content": request.prompt
+                        }
+                    ],
+                    "max_tokens": request.max_tokens.unwrap_or(512),
+                    "temperature": request.temperature.unwrap_or(0.7)
+                });
+                if let Some(safe_prompt) = request.safe_prompt {
+                    payload["safe_prompt"] = json!(safe_prompt);
+                }
+                payload
+            }
+            ModelProvider::Meta => json!({
+                "prompt": request.prompt,
+                "max_gen_len": request.max_tokens.unwrap_or(512),
+                "temperature": request.temperature.unwrap_or(0.7)
+            }),
+            ModelProvider::Mistral => json!({
                 "prompt": request.prompt,
                 "max_tokens": request.max_tokens.unwrap_or(512),
-                "temperature": request.temperature.unwrap_or(0.7),
-                "top_p": 0.9
+                "temperature": request.temperature.unwrap_or(0.7)
             }),
         };
-        let request_json = serde_json::to_string(&request_payload)
-            .map_err(|e| BedrockError::SerializationError(e.to_string()))?;
+        if request.safe_prompt.is_some() && payload.get("safe_prompt").is_none() {
+            log::warn!("safe_prompt is only supported by Mistral chat models - ignoring");
+        }
+        Self::apply_messages(&mut payload, request, model_id)?;
+        Self::check_sampling(request)?;
+        Self::apply_stop_sequences(&mut payload, provider, model_id, request)?;
+        self.apply_prompt_framing(&mut payload, request);
+        self.apply_sampling(&mut payload, provider, model_id, request);
+        Self::apply_num_completions(&mut payload, provider, model_id, request.num_completions)?;
 
-        log::info!("Invoking model: {}", model_id);
-        log::debug!("Text generation request payload: {}", request_json);
-
-        let response = self
-            .client
-            .invoke_model()
-            .model_id(model_id)
-            .content_type("application/json")
-            .accept("application/json")
-            .body(Blob::new(request_json.into_bytes()))
-            .send()
-            .await
-            .map_err(|e| {
-                log::error!("AWS SDK Text Generation Error details: {:?}", e);
-
-                if let Some(service_error) = e.as_service_error() {
-                    log::error!("Service error code: {:?}", service_error.code());
-                    log::error!("Service error message: {:?}", service_error.message());
-                    BedrockError::AwsServiceError(format!(
-                        "Bedrock service error: {} - {}",
-                        service_error.code().unwrap_or("unknown"),
-                        service_error.message().unwrap_or("no message")
-                    ))
-                } else {
-                    BedrockError::AwsError(format!("AWS SDK error: {}", e))
+        Ok(payload)
+    }
+
+    // Generates and parses JSON output. When parsing fails the model is asked
+    // to fix its own output, up to `max_repairs` times, before giving up.
+    pub async fn generate_json(
+        &self,
+        request: TextGenerationRequest,
+        max_repairs: u32,
+    ) -> Result<StructuredResponse> {
+        let mut raw = self.generate_text(request.clone()).await?;
+        let mut repair_attempts = 0;
+
+        loop {
+            let error = match parse_json(&raw) {
+                Ok(value) => {
+                    return Ok(StructuredResponse {
+                        value,
+                        raw,
+                        repair_attempts,
+                    })
                 }
-            })?;
+                Err(e) if repair_attempts >= max_repairs => return Err(e),
+                Err(e) => e,
+            };
 
-        let response_bytes = response.body.into_inner();
-        String::from_utf8(response_bytes).map_err(|e| BedrockError::ResponseError(e.to_string()))
+            repair_attempts += 1;
+            log::warn!(
+                "Repairing invalid JSON output (attempt {}/{}): {}",
+                repair_attempts,
+                max_repairs,
+                error
+            );
+            raw = self
+                .generate_text(TextGenerationRequest {
+                    prompt: json_repair_prompt(&raw, &error),
+                    temperature: Some(0.0),
+                    num_completions: None,
+                    messages: None,
+                    ..request.clone()
+                })
+                .await?;
+        }
     }
 
-    pub async fn generate_stream(
+    async fn generate_text(&self, request: TextGenerationRequest) -> Result<String> {
+        Ok(self.generate(request).await?.text)
+    }
+
+    // Keeps generating while the output stops at the model's limit, up to
+    // `max_continuations` extra calls. The request's `max_tokens`, when set,
+    // caps the output of all calls together. The response holds the joined
+    // text and the token usage summed over every call.
+    pub async fn generate_complete(
         &self,
         request: TextGenerationRequest,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>> {
-        let model_id = request
-            .model_id
-            .as_deref()
-            .unwrap_or("amazon.titan-text-express-v1");
+        max_continuations: u32,
+    ) -> Result<TextGenerationResponse> {
+        let mut response = self.generate(request.clone()).await?;
+        let mut continuations = 0;
 
-        let mut request_payload = self.build_request_payload(&request, model_id)?;
-        match model_id {
-            id if id.starts_with("amazon.titan") => {
-                if let Some(obj) = request_payload.as_object_mut() {
-                    if let Some(config) = obj.get_mut("textGenerationConfig") {
-                        if let Some(config_obj) = config.as_object_mut() {
-                            config_obj.insert("stream".to_string(), json!(true));
-                        }
-                    }
-                }
+        while response.is_truncated() && continuations < max_continuations {
+            continuations += 1;
+            log::debug!(
+                "Output hit max_tokens; continuing ({}/{})",
+                continuations,
+                max_continuations
+            );
+            match self.continue_response(&request, &response).await? {
+                Some(extended) => response = extended,
+                None => break,
             }
-            id if id.starts_with("anthropic.claude") => {
-                if let Some(obj) = request_payload.as_object_mut() {
-                    obj.insert("stream".to_string(), json!(true));
-                }
+        }
+        Ok(response)
+    }
+
+    // One more call picking up where `response` stopped, joined onto it with
+    // the token usage summed. `None` once the request's `max_tokens` is spent.
+    pub(crate) async fn continue_response(
+        &self,
+        request: &TextGenerationRequest,
+        response: &TextGenerationResponse,
+    ) -> Result<Option<TextGenerationResponse>> {
+        let Some((next_request, partial)) =
+            Self::continuation_request(request, &response.text, response.tokens_generated)
+        else {
+            return Ok(None);
+        };
+        let next = self.generate_unchecked(next_request).await?;
+        Ok(Some(TextGenerationResponse {
+            text: format!("{}{}", partial, next.text),
+            tokens_generated: response.tokens_generated + next.tokens_generated,
+            tokens_prompt: response.tokens_prompt + next.tokens_prompt,
+            finish_reason: next.finish_reason,
+            candidates: None,
+            request_id: next.request_id,
+            trace: next.trace,
+            model: response.model.clone(),
+        }))
+    }
+
+    // Chat requests continue from an assistant turn holding the output so
+    // far, which Claude extends in place; prompt requests are re-framed with
+    // `continuation_prompt`. `max_tokens` shrinks by the `generated` tokens
+    // already produced, and there is nothing to continue once it is spent.
+    // Returns the request and the partial output to prepend to its text.
+    pub(crate) fn continuation_request(
+        request: &TextGenerationRequest,
+        partial: &str,
+        generated: i32,
+    ) -> Option<(TextGenerationRequest, String)> {
+        let max_tokens = match request.max_tokens {
+            Some(max_tokens) if max_tokens <= generated => return None,
+            max_tokens => max_tokens.map(|max_tokens| max_tokens - generated),
+        };
+        let next = TextGenerationRequest {
+            // The prompt was already checked on the first call.
+            moderation_guardrail_id: None,
+            num_completions: None,
+            max_tokens,
+            ..request.clone()
+        };
+        Some(match &request.messages {
+            Some(messages) => {
+                // Claude rejects an assistant turn ending in whitespace.
+                let partial = partial.trim_end();
+                let mut messages = messages.clone();
+                messages.push(ChatMessage::assistant(partial));
+                (
+                    TextGenerationRequest {
+                        messages: Some(messages),
+                        ..next
+                    },
+                    partial.to_string(),
+                )
             }
-            _ => {}
+            None => (
+                TextGenerationRequest {
+                    prompt: continuation_prompt(&request.prompt, partial),
+                    ..next
+                },
+                partial.to_string(),
+            ),
+        })
+    }
+
+    // Sends the same request to every model concurrently, for side-by-side
+    // comparison. Results come back in `model_ids` order and one model
+    // failing doesn't affect the others.
+    pub async fn generate_multi(
+        &self,
+        request: TextGenerationRequest,
+        model_ids: Vec<String>,
+    ) -> Vec<(String, Result<TextGenerationResponse>)> {
+        futures::future::join_all(model_ids.into_iter().map(|model_id| {
+            let request = TextGenerationRequest {
+                model_id: Some(model_id.clone()),
+                // Let each model's own provider be inferred from its id.
+                provider: None,
+                ..request.clone()
+            };
+            async move {
+                let result = self.generate(request).await;
+                (model_id, result)
+            }
+        }))
+        .await
+    }
+
+    pub async fn quick(&self, prompt: &str) -> Result<String> {
+        self.generate_text(TextGenerationRequest {
+            prompt: prompt.to_string(),
+            ..Default::default()
+        })
+        .await
+    }
+
+    // Used when the request sets no provider. ARNs that don't name their
+    // model are treated as Anthropic, anything else unknown as Titan.
+    fn provider_for_model(model_id: &str) -> ModelProvider {
+        ModelProvider::from_model_id(model_id).unwrap_or_else(|| {
+            if model_id.starts_with("arn:aws:bedrock") {
+                ModelProvider::Anthropic
+            } else {
+                ModelProvider::Amazon
+            }
+        })
+    }
+
+    // Jamba models use AI21's chat format; Jurassic-2 keeps the legacy
+    // prompt/completions format.
+    fn is_jamba(model_id: &str) -> bool {
+        model_id.starts_with("ai21.jamba")
+    }
+
+    // Mistral Large takes the chat format; the smaller instruct models keep
+    // the legacy prompt format.
+    fn is_mistral_chat(model_id: &str) -> bool {
+        model_id.starts_with("mistral.mistral-large")
+    }
+
+    fn apply_num_completions(
+        payload: &mut serde_json::Value,
+        provider: &ModelProvider,
+        model_id: &str,
+        num_completions: Option<i32>,
+    ) -> Result<()> {
+        let Some(num_completions) = num_completions else {
+            return Ok(());
+        };
+        if num_completions < 1 {
+            return Err(BedrockError::RequestError(
+                "num_completions must be at least 1".into(),
+            ));
         }
 
-        let request_json = serde_json::to_string(&request_payload)
+        let (field, max) = match provider {
+            ModelProvider::AI21 if Self::is_jamba(model_id) => ("n", 16),
+            ModelProvider::AI21 => ("numResults", 16),
+            ModelProvider::Cohere => ("num_generations", 5),
+            _ if num_completions == 1 => return Ok(()),
+            other => {
+                return Err(BedrockError::RequestError(format!(
+                    "{:?} models do not support multiple completions",
+                    other
+                )))
+            }
+        };
+        if num_completions > max {
+            return Err(BedrockError::RequestError(format!(
+                "{:?} models support at most {} completions, requested {}",
+                provider, max, num_completions
+            )));
+        }
+
+        payload[field] = json!(num_completions);
+        Ok(())
+    }
+
+    pub async fn invoke_raw(
+        &self,
+        model_id: &str,
+        payload: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        check_region(self.region_check.as_ref(), model_id);
+        let request_json = serde_json::to_string(&payload)
             .map_err(|e| BedrockError::SerializationError(e.to_string()))?;
 
-        log::info!("Invoking streaming model: {}", model_id);
+        log::info!("Invoking model (raw): {}", model_id);
+        log_payload(
+            self.log_payloads,
+            "request",
+            model_id,
+            request_json.as_bytes(),
+        );
 
-        let response = self
-            .client
-            .invoke_model_with_response_stream()
-            .model_id(model_id)
-            .content_type("application/json")
-            .accept("application/json")
-            .body(Blob::new(request_json.into_bytes()))
-            .send()
-            .await
-            .map_err(|e| BedrockError::AwsError(e.to_string()))?;
+        let response = guarded(self.circuit_breaker.as_ref(), async {
+            self.client
+                .invoke_model()
+                .model_id(model_id)
+                .content_type("application/json")
+                .accept("application/json")
+                .body(Blob::new(request_json.into_bytes()))
+                .send()
+                .await
+                .map_err(map_aws_error)
+        })
+        .await?;
+        log_request_id(model_id, &response);
 
-        let model_id = model_id.to_string();
+        log_payload(
+            self.log_payloads,
+            "response",
+            model_id,
+            response.body.as_ref(),
+        );
+        serde_json::from_slice(response.body.as_ref())
+            .map_err(|e| BedrockError::ResponseError(e.to_string()))
+    }
+
+    pub async fn invoke_raw_stream(
+        &self,
+        model_id: &str,
+        payload: serde_json::Value,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<serde_json::Value>> + Send>>> {
+        check_region(self.region_check.as_ref(), model_id);
+        let request_json = serde_json::to_string(&payload)
+            .map_err(|e| BedrockError::SerializationError(e.to_string()))?;
+
+        log::info!("Invoking streaming model (raw): {}", model_id);
+        log_payload(
+            self.log_payloads,
+            "request",
+            model_id,
+            request_json.as_bytes(),
+        );
+
+        let response = guarded(self.circuit_breaker.as_ref(), async {
+            self.client
+                .invoke_model_with_response_stream()
+                .model_id(model_id)
+                .content_type("application/json")
+                .accept("application/json")
+                .body(Blob::new(request_json.into_bytes()))
+                .send()
+                .await
+                .map_err(map_aws_error)
+        })
+        .await?;
+        log_request_id(model_id, &response);
 
-        // Convert EventReceiver to a Stream using a channel
         let (tx, rx) = tokio::sync::mpsc::channel(100);
         let mut event_receiver = response.body;
 
-        tokio::spawn(async move {
+        let task = tokio::spawn(async move {
             loop {
                 match event_receiver.recv().await {
-                    Ok(Some(event)) => {
-                        let result = match event {
-                            aws_sdk_bedrockruntime::types::ResponseStream::Chunk(chunk) => {
-                                if let Some(bytes) = chunk.bytes {
-                                    let chunk_str =
-                                        String::from_utf8_lossy(bytes.as_ref()).to_string();
-                                    Self::parse_stream_chunk_static(&chunk_str, &model_id)
-                                } else {
-                                    Ok(StreamChunk {
-                                        chunk: String::new(),
-                                        done: false,
-                                        finish_reason: None,
-                                    })
-                                }
-                            }
-                            _ => Ok(StreamChunk {
-                                chunk: String::new(),
-                                done: true,
-                                finish_reason: Some("complete".to_string()),
-                            }),
+                    Ok(Some(aws_sdk_bedrockruntime::types::ResponseStream::Chunk(chunk))) => {
+                        let Some(bytes) = chunk.bytes else {
+                            continue;
                         };
+                        let result = serde_json::from_slice(bytes.as_ref())
+                            .map_err(|e| BedrockError::ResponseError(e.to_string()));
 
                         if tx.send(result).await.is_err() {
                             break;
                         }
                     }
+                    Ok(Some(_)) => continue,
                     Ok(None) => break,
                     Err(e) => {
                         let _ = tx.send(Err(BedrockError::AwsError(e.to_string()))).await;
@@ -187,95 +730,871 @@ impl TextClient {
             }
         });
 
-        Ok(Box::pin(ReceiverStream::new(rx)))
+        Ok(Box::pin(AbortOnDrop::new(ReceiverStream::new(rx), task)))
+    }
+
+    // Generates through the Converse API, which takes the same message and
+    // inference-config shape for every provider, and is the recommended entry
+    // point. `generate` keeps the per-provider invoke_model payloads for what
+    // Converse doesn't cover: `top_k`, `num_completions`, `trace` and
+    // Mistral's `safe_prompt`.
+    pub async fn converse(&self, request: TextGenerationRequest) -> Result<TextGenerationResponse> {
+        self.check_moderation(&request).await?;
+        let request = &*self.resolve_preset(&request)?;
+        let model_id = request
+            .model_id
+            .as_deref()
+            .unwrap_or("amazon.titan-text-express-v1");
+        check_region(self.region_check.as_ref(), model_id);
+        let messages = self.converse_messages(request)?;
+        let inference_config = Self::inference_config(request)?;
+
+        log::info!("Conversing with model: {}", model_id);
+        let response = guarded(self.circuit_breaker.as_ref(), async {
+            self.client
+                .converse()
+                .model_id(model_id)
+                .set_messages(Some(messages))
+                .set_system(self.converse_system(request))
+                .inference_config(inference_config)
+                .send()
+                .await
+                .map_err(map_aws_error)
+        })
+        .await?;
+        log_request_id(model_id, &response);
+
+        let Some(ConverseOutput::Message(message)) = response.output() else {
+            return Err(BedrockError::ResponseError(format!(
+                "No message in {} Converse response",
+                model_id
+            )));
+        };
+        let text = message
+            .content()
+            .iter()
+            .filter_map(|block| block.as_text().ok())
+            .map(String::as_str)
+            .collect();
+        let usage = response.usage();
+
+        Ok(TextGenerationResponse {
+            text,
+            model: model_id.to_string(),
+            tokens_generated: usage.map_or(0, |usage| usage.output_tokens()),
+            tokens_prompt: usage.map_or(0, |usage| usage.input_tokens()),
+            finish_reason: Some(response.stop_reason().as_str().to_string()),
+            candidates: None,
+            request_id: response.request_id().map(String::from),
+            trace: None,
+        })
     }
 
-    fn build_request_payload(
+    // Streaming `converse`. The stop reason arrives on its own chunk, followed
+    // by a final one carrying token usage.
+    pub async fn converse_stream(
         &self,
-        request: &TextGenerationRequest,
-        model_id: &str,
-    ) -> Result<serde_json::Value> {
-        let payload = match model_id {
-            id if id.starts_with("amazon.titan") => json!({
-                "inputText": request.prompt,
-                "textGenerationConfig": {
-                    "maxTokenCount": request.max_tokens.unwrap_or(512),
-                    "temperature": request.temperature.unwrap_or(0.7),
-                    "topP": 0.9
-                }
-            }),
-            id if id.starts_with("meta.llama") => json!({
-                "prompt": request.prompt,
-                "max_gen_len": request.max_tokens.unwrap_or(512),
-                "temperature": request.temperature.unwrap_or(0.7),
-                "top_p": 0.9
-            }),
-            id if id.starts_with("mistral.mistral") => json!({
-                "prompt": request.prompt,
-                "max_tokens": request.max_tokens.unwrap_or(512),
-                "temperature": request.temperature.unwrap_or(0.7),
-                "top_p": 0.9
-            }),
-            id if id.starts_with("arn:aws:bedrock") => json!({
-                "messages": [
-                    {
-                        "role": "user",
-                        "content": request.prompt
+        request: TextGenerationRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>> {
+        self.check_moderation(&request).await?;
+        let request = &*self.resolve_preset(&request)?;
+        let model_id = request
+            .model_id
+            .as_deref()
+            .unwrap_or("amazon.titan-text-express-v1");
+        check_region(self.region_check.as_ref(), model_id);
+        let messages = self.converse_messages(request)?;
+        let inference_config = Self::inference_config(request)?;
+
+        log::info!("Conversing with streaming model: {}", model_id);
+        let response = guarded(self.circuit_breaker.as_ref(), async {
+            self.client
+                .converse_stream()
+                .model_id(model_id)
+                .set_messages(Some(messages))
+                .set_system(self.converse_system(request))
+                .inference_config(inference_config)
+                .send()
+                .await
+                .map_err(map_aws_error)
+        })
+        .await?;
+        log_request_id(model_id, &response);
+
+        let (tx, rx) = tokio::sync::mpsc::channel(100);
+        let mut events = response.stream;
+        let task = tokio::spawn(async move {
+            loop {
+                let chunk = |text: String, finish_reason: Option<String>| StreamChunk {
+                    chunk: text,
+                    done: false,
+                    finish_reason,
+                    tokens_prompt: None,
+                    tokens_generated: None,
+                };
+                let result = match events.recv().await {
+                    Ok(Some(ConverseStreamOutput::ContentBlockDelta(event))) => {
+                        match event.delta() {
+                            Some(ContentBlockDelta::Text(text)) => Ok(chunk(text.clone(), None)),
+                            _ => continue,
+                        }
                     }
-                ],
-                "max_tokens": request.max_tokens.unwrap_or(512),
-                "temperature": request.temperature.unwrap_or(0.7),
-                "anthropic_version": "bedrock-2023-05-31"
-            }),
-            id if id.starts_with("anthropic.claude") => json!({
-                "messages": [
-                    {
-                        "role": "user",
-                        "content": request.prompt
+                    Ok(Some(ConverseStreamOutput::MessageStop(event))) => Ok(chunk(
+                        String::new(),
+                        Some(event.stop_reason().as_str().to_string()),
+                    )),
+                    Ok(Some(ConverseStreamOutput::Metadata(event))) => {
+                        let usage = event.usage();
+                        Ok(StreamChunk {
+                            done: true,
+                            tokens_prompt: usage.map(|usage| usage.input_tokens()),
+                            tokens_generated: usage.map(|usage| usage.output_tokens()),
+                            ..chunk(String::new(), None)
+                        })
                     }
-                ],
-                "max_tokens": request.max_tokens.unwrap_or(512),
-                "temperature": request.temperature.unwrap_or(0.7),
-                "anthropic_version": "bedrock-2023-05-31"
-            }),
-            id if id.starts_with("ai21.") => json!({
-                "prompt": request.prompt,
-                "maxTokens": request.max_tokens.unwrap_or(512),
-                "temperature": request.temperature.unwrap_or(0.7),
-                "topP": 0.9
-            }),
-            id if id.starts_with("cohere.command") => json!({
-                "prompt": request.prompt,
-                "max_tokens": request.max_tokens.unwrap_or(512),
-                "temperature": request.temperature.unwrap_or(0.7),
-                "p": 0.9
-            }),
-            _ => {
-                return Err(BedrockError::RequestError(format!(
-                    "Unsupported model ID: {}",
-                    model_id
-                )))
+                    Ok(Some(_)) => continue,
+                    Ok(None) => break,
+                    Err(e) => Err(BedrockError::AwsError(e.to_string())),
+                };
+
+                let failed = result.is_err();
+                if tx.send(result).await.is_err() || failed {
+                    break;
+                }
             }
-        };
+        });
 
-        Ok(payload)
+        Ok(Box::pin(AbortOnDrop::new(ReceiverStream::new(rx), task)))
     }
 
-    fn parse_stream_chunk_static(chunk_str: &str, model_id: &str) -> Result<StreamChunk> {
-        let json: serde_json::Value = serde_json::from_str(chunk_str)
-            .map_err(|e| BedrockError::ResponseError(e.to_string()))?;
+    // The request's conversation, or its prompt as one user message, with the
+    // client's prompt suffix appended to the last message.
+    fn converse_messages(&self, request: &TextGenerationRequest) -> Result<Vec<Message>> {
+        let mut messages = request
+            .messages
+            .clone()
+            .unwrap_or_else(|| vec![ChatMessage::user(request.prompt.as_str())]);
+        messages.retain(|message| message.role != Role::System);
+        if let (Some(suffix), Some(last)) = (
+            self.prompt_suffix.as_deref().filter(|s| !s.is_empty()),
+            messages.last_mut(),
+        ) {
+            last.content = match &last.content {
+                MessageContent::Text(text) => format!("{}\n\n{}", text, suffix).into(),
+                MessageContent::Blocks(blocks) => {
+                    let mut blocks = blocks.clone();
+                    blocks.push(ContentBlock::Text(suffix.to_string()));
+                    blocks.into()
+                }
+            };
+        }
 
-        let stream_chunk = match model_id {
-            id if id.starts_with("amazon.titan") => StreamChunk {
-                chunk: json["outputText"].as_str().unwrap_or("").to_string(),
-                done: json["completionReason"].is_string(),
-                finish_reason: json["completionReason"].as_str().map(String::from),
-            },
-            id if id.starts_with("meta.llama") => StreamChunk {
+        messages
+            .into_iter()
+            .map(|message| {
+                let blocks = match message.content {
+                    MessageContent::Text(text) => vec![ContentBlock::Text(text)],
+                    MessageContent::Blocks(blocks) => blocks,
+                };
+                let content = blocks
+                    .into_iter()
+                    .map(Self::converse_block)
+                    .collect::<Result<Vec<_>>>()?;
+                Message::builder()
+                    .role(ConversationRole::from(message.role.as_str()))
+                    .set_content(Some(content))
+                    .build()
+                    .map_err(|e| BedrockError::RequestError(e.to_string()))
+            })
+            .collect()
+    }
+
+    fn converse_block(block: ContentBlock) -> Result<ConverseContentBlock> {
+        match block {
+            ContentBlock::Text(text) => Ok(ConverseContentBlock::Text(text)),
+            ContentBlock::Image { base64, media_type } => {
+                if !SUPPORTED_IMAGE_MEDIA_TYPES.contains(&media_type.as_str()) {
+                    return Err(BedrockError::RequestError(format!(
+                        "Unsupported image media type {}, expected one of: {}",
+                        media_type,
+                        SUPPORTED_IMAGE_MEDIA_TYPES.join(", ")
+                    )));
+                }
+                let bytes = STANDARD.decode(&base64).map_err(|e| {
+                    BedrockError::RequestError(format!("Invalid base64 image: {}", e))
+                })?;
+                ImageBlock::builder()
+                    .format(ImageFormat::from(media_type.trim_start_matches("image/")))
+                    .source(ImageSource::Bytes(Blob::new(bytes)))
+                    .build()
+                    .map(ConverseContentBlock::Image)
+                    .map_err(|e| BedrockError::RequestError(e.to_string()))
+            }
+        }
+    }
+
+    fn converse_system(&self, request: &TextGenerationRequest) -> Option<Vec<SystemContentBlock>> {
+        let system: Vec<SystemContentBlock> = self
+            .system_parts(request)
+            .into_iter()
+            .map(SystemContentBlock::Text)
+            .collect();
+        (!system.is_empty()).then_some(system)
+    }
+
+    // The client's prompt prefix, the request's system prompt, then the text
+    // of any system turns in its conversation.
+    fn system_parts(&self, request: &TextGenerationRequest) -> Vec<String> {
+        let system_turns = request
+            .messages
+            .iter()
+            .flatten()
+            .filter(|message| message.role == Role::System)
+            .map(|message| message.content.text());
+        [self.prompt_prefix.clone(), request.system_prompt.clone()]
+            .into_iter()
+            .flatten()
+            .chain(system_turns)
+            .filter(|part| !part.is_empty())
+            .collect()
+    }
+
+    fn inference_config(request: &TextGenerationRequest) -> Result<InferenceConfiguration> {
+        Self::check_sampling(request)?;
+        Self::check_stop_sequences(request)?;
+        Ok(InferenceConfiguration::builder()
+            .set_max_tokens(request.max_tokens)
+            .set_temperature(request.temperature)
+            .set_top_p(request.top_p)
+            .set_stop_sequences(request.stop_sequences.clone())
+            .build())
+    }
+
+    // Client-side so a bad value fails with a clear message rather than a
+    // provider-specific validation error.
+    fn check_sampling(request: &TextGenerationRequest) -> Result<()> {
+        if let Some(top_p) = request.top_p.filter(|top_p| !(0.0..=1.0).contains(top_p)) {
+            return Err(BedrockError::RequestError(format!(
+                "top_p must be between 0.0 and 1.0, got {}",
+                top_p
+            )));
+        }
+        if let Some(top_k) = request.top_k.filter(|top_k| *top_k < 1) {
+            return Err(BedrockError::RequestError(format!(
+                "top_k must be at least 1, got {}",
+                top_k
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_stop_sequences(request: &TextGenerationRequest) -> Result<()> {
+        match &request.stop_sequences {
+            Some(stops) if stops.is_empty() => Err(BedrockError::RequestError(
+                "stop_sequences is empty; leave it unset to use none".to_string(),
+            )),
+            Some(stops) if stops.iter().any(String::is_empty) => Err(BedrockError::RequestError(
+                "stop_sequences contains an empty string".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Field name and maximum count per provider; `None` has no documented cap.
+    fn apply_stop_sequences(
+        payload: &mut serde_json::Value,
+        provider: &ModelProvider,
+        model_id: &str,
+        request: &TextGenerationRequest,
+    ) -> Result<()> {
+        let Some(stops) = &request.stop_sequences else {
+            return Ok(());
+        };
+        Self::check_stop_sequences(request)?;
+
+        let (field, max) = match provider {
+            ModelProvider::Amazon => ("stopSequences", None),
+            ModelProvider::Anthropic => ("stop_sequences", Some(8191)),
+            ModelProvider::Cohere => ("stop_sequences", Some(4)),
+            ModelProvider::Mistral => ("stop", Some(10)),
+            ModelProvider::AI21 if Self::is_jamba(model_id) => ("stop", None),
+            ModelProvider::AI21 => ("stopSequences", None),
+            ModelProvider::Meta => {
+                return Err(BedrockError::RequestError(format!(
+                    "{} does not support stop sequences",
+                    model_id
+                )))
+            }
+        };
+        if let Some(max) = max.filter(|max| stops.len() > *max) {
+            return Err(BedrockError::RequestError(format!(
+                "{} accepts at most {} stop sequences, got {}",
+                model_id,
+                max,
+                stops.len()
+            )));
+        }
+
+        let target = match payload.get_mut("textGenerationConfig") {
+            Some(config) => config,
+            None => payload,
+        };
+        target[field] = json!(stops);
+        Ok(())
+    }
+
+    pub async fn generate_stream(
+        &self,
+        request: TextGenerationRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>> {
+        self.check_moderation(&request).await?;
+
+        if request.num_completions.is_some_and(|n| n > 1) {
+            return Err(BedrockError::RequestError(
+                "Multiple completions are not supported for streaming".into(),
+            ));
+        }
+
+        let stream = match self.open_stream(&request).await {
+            Ok(stream) => stream,
+            Err(e) if self.stream_fallback && Self::is_streaming_unsupported(&e) => {
+                log::warn!(
+                    "Streaming unsupported, falling back to a single response: {}",
+                    e
+                );
+                return self.generate_as_stream(request).await;
+            }
+            Err(e) => return Err(e),
+        };
+        if self.max_stream_resumes == 0 {
+            return Ok(stream);
+        }
+        Ok(self.resume_on_failure(request, stream))
+    }
+
+    fn is_streaming_unsupported(error: &BedrockError) -> bool {
+        match error {
+            BedrockError::AwsServiceError(msg) => {
+                let msg = msg.to_ascii_lowercase();
+                msg.contains("validationexception") && msg.contains("stream")
+            }
+            _ => false,
+        }
+    }
+
+    async fn generate_as_stream(
+        &self,
+        request: TextGenerationRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>> {
+        // Moderation already ran in `generate_stream`.
+        let chunk = StreamChunk::from(self.generate_unchecked(request).await?);
+        Ok(Box::pin(futures::stream::once(async move { Ok(chunk) })))
+    }
+
+    // Forwards `stream`, and when it fails with a transient error re-invokes
+    // the model with the partial output so far, emitting a marker chunk
+    // before the continuation.
+    fn resume_on_failure(
+        &self,
+        request: TextGenerationRequest,
+        mut stream: Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>,
+    ) -> Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>> {
+        let client = self.clone();
+        let (tx, rx) = tokio::sync::mpsc::channel(100);
+
+        let task = tokio::spawn(async move {
+            let backoff = Backoff::default();
+            let mut partial = String::new();
+            let mut resumes = 0;
+
+            while let Some(item) = stream.next().await {
+                let mut error = match item {
+                    Ok(chunk) => {
+                        partial.push_str(&chunk.chunk);
+                        if tx.send(Ok(chunk)).await.is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                    Err(e) => e,
+                };
+
+                loop {
+                    // Chunks carry no token counts until the end, so the
+                    // output so far is estimated from its length.
+                    let continuation = Self::continuation_request(
+                        &request,
+                        &partial,
+                        estimate_tokens(&partial) as i32,
+                    );
+                    let Some((continuation, _)) = continuation
+                        .filter(|_| error.is_transient() && resumes < client.max_stream_resumes)
+                    else {
+                        let _ = tx.send(Err(error)).await;
+                        return;
+                    };
+                    resumes += 1;
+                    log::warn!(
+                        "Stream interrupted after {} chars, resuming ({}/{}): {}",
+                        partial.len(),
+                        resumes,
+                        client.max_stream_resumes,
+                        error
+                    );
+                    tokio::time::sleep(backoff.delay(resumes - 1)).await;
+
+                    match client.open_stream(&continuation).await {
+                        Ok(next) => {
+                            stream = next;
+                            break;
+                        }
+                        Err(e) => error = e,
+                    }
+                }
+
+                let marker = StreamChunk {
+                    chunk: String::new(),
+                    done: false,
+                    finish_reason: Some(STREAM_RESUMED_REASON.to_string()),
+                    tokens_prompt: None,
+                    tokens_generated: None,
+                };
+                if tx.send(Ok(marker)).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        Box::pin(AbortOnDrop::new(ReceiverStream::new(rx), task))
+    }
+
+    // Sets the body's streaming switch for models that have one. Llama,
+    // Mistral and AI21 Jamba take none and reject unknown keys; the streaming
+    // endpoint alone makes them stream. AI21 Jurassic can't stream at all, and
+    // Bedrock's rejection is what `with_stream_fallback` recovers from.
+    fn enable_streaming(payload: &mut serde_json::Value, model_id: &str) {
+        match model_id {
+            id if id.starts_with("amazon.titan") => {
+                if let Some(config) = payload
+                    .get_mut("textGenerationConfig")
+                    .and_then(|config| config.as_object_mut())
+                {
+                    config.insert("stream".to_string(), json!(true));
+                }
+            }
+            id if id.starts_with("anthropic.claude") || id.starts_with("cohere.command") => {
+                if let Some(obj) = payload.as_object_mut() {
+                    obj.insert("stream".to_string(), json!(true));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn open_stream(
+        &self,
+        request: &TextGenerationRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>> {
+        let model_id = request
+            .model_id
+            .as_deref()
+            .unwrap_or("amazon.titan-text-express-v1");
+
+        let provider = request
+            .provider
+            .clone()
+            .unwrap_or_else(|| Self::provider_for_model(model_id));
+        check_region(self.region_check.as_ref(), model_id);
+        // A stream carries a single completion.
+        let single = TextGenerationRequest {
+            num_completions: None,
+            ..request.clone()
+        };
+        let mut request_payload = self.text_payload(&single, model_id, &provider)?;
+        Self::enable_streaming(&mut request_payload, model_id);
+
+        let request_json = serde_json::to_string(&request_payload)
+            .map_err(|e| BedrockError::SerializationError(e.to_string()))?;
+
+        log::info!("Invoking streaming model: {}", model_id);
+        log_payload(
+            self.log_payloads,
+            "request",
+            model_id,
+            request_json.as_bytes(),
+        );
+
+        let response = guarded(self.circuit_breaker.as_ref(), async {
+            self.client
+                .invoke_model_with_response_stream()
+                .model_id(model_id)
+                .content_type("application/json")
+                .accept("application/json")
+                .body(Blob::new(request_json.into_bytes()))
+                .send()
+                .await
+                .map_err(map_aws_error)
+        })
+        .await?;
+        log_request_id(model_id, &response);
+
+        let model_id = model_id.to_string();
+
+        // Convert EventReceiver to a Stream using a channel
+        let (tx, rx) = tokio::sync::mpsc::channel(100);
+        let mut event_receiver = response.body;
+
+        let task = tokio::spawn(async move {
+            let mut buffer = JsonChunkBuffer::default();
+            loop {
+                match event_receiver.recv().await {
+                    Ok(Some(event)) => {
+                        let results = match event {
+                            aws_sdk_bedrockruntime::types::ResponseStream::Chunk(chunk) => {
+                                if let Some(bytes) = chunk.bytes {
+                                    Self::parse_stream_bytes(&mut buffer, bytes.as_ref(), &model_id)
+                                } else {
+                                    vec![Ok(StreamChunk {
+                                        chunk: String::new(),
+                                        done: false,
+                                        finish_reason: None,
+                                        tokens_prompt: None,
+                                        tokens_generated: None,
+                                    })]
+                                }
+                            }
+                            _ => vec![Ok(StreamChunk {
+                                chunk: String::new(),
+                                done: true,
+                                finish_reason: Some("complete".to_string()),
+                                tokens_prompt: None,
+                                tokens_generated: None,
+                            })],
+                        };
+
+                        for result in results {
+                            if tx.send(result).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Ok(None) => {
+                        if let Some(rest) = buffer.take_remainder() {
+                            let _ = tx
+                                .send(Err(BedrockError::ResponseError(format!(
+                                    "Stream ended inside an incomplete chunk: {}",
+                                    rest
+                                ))))
+                                .await;
+                        }
+                        break;
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Err(BedrockError::AwsError(e.to_string()))).await;
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Box::pin(AbortOnDrop::new(ReceiverStream::new(rx), task)))
+    }
+
+    // `generate_stream` with running token usage interleaved as events, for
+    // live cost displays.
+    pub async fn generate_stream_events(
+        &self,
+        request: TextGenerationRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send>>> {
+        let stream = self.generate_stream(request).await?;
+        Ok(Self::with_usage_events(stream))
+    }
+
+    fn with_usage_events(
+        stream: impl Stream<Item = Result<StreamChunk>> + Send + 'static,
+    ) -> Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send>> {
+        let mut input_tokens = None;
+        let mut output_tokens = None;
+        Box::pin(stream.flat_map(move |item| {
+            let mut events = Vec::with_capacity(2);
+            match item {
+                Ok(chunk) => {
+                    input_tokens = chunk.tokens_prompt.or(input_tokens);
+                    let usage = chunk
+                        .tokens_generated
+                        .filter(|count| output_tokens != Some(*count));
+                    events.push(Ok(StreamEvent::Text(chunk)));
+                    if let Some(count) = usage {
+                        output_tokens = Some(count);
+                        events.push(Ok(StreamEvent::Usage {
+                            input_tokens,
+                            output_tokens_so_far: count,
+                        }));
+                    }
+                }
+                Err(e) => events.push(Err(e)),
+            }
+            futures::stream::iter(events)
+        }))
+    }
+
+    pub async fn generate_stream_with_summary(
+        &self,
+        request: TextGenerationRequest,
+    ) -> Result<(
+        Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>,
+        impl Future<Output = StreamSummary>,
+    )> {
+        let started = Instant::now();
+        let mut stream = self.generate_stream(request).await?;
+
+        let (tx, rx) = tokio::sync::mpsc::channel(100);
+        let (summary_tx, summary_rx) = tokio::sync::oneshot::channel();
+
+        // The summary resolves once the stream ends or the caller drops it.
+        // Dropping the caller's stream also drops the inner one, which stops
+        // the generation.
+        tokio::spawn(async move {
+            let mut summary = StreamSummary::default();
+            loop {
+                let item = tokio::select! {
+                    item = stream.next() => item,
+                    _ = tx.closed() => break,
+                };
+                let Some(item) = item else {
+                    break;
+                };
+                if let Ok(chunk) = &item {
+                    summary.record(chunk);
+                }
+                if tx.send(item).await.is_err() {
+                    break;
+                }
+            }
+            drop(stream);
+            summary.duration = started.elapsed();
+            let _ = summary_tx.send(summary);
+        });
+
+        let summary = async move { summary_rx.await.unwrap_or_default() };
+        Ok((Box::pin(ReceiverStream::new(rx)), summary))
+    }
+
+    // Replaces the single prompt message with the request's conversation.
+    // Only Anthropic payloads carry content blocks; other chat formats get
+    // each message's text, and prompt-only models a transcript of the turns.
+    // System turns are left to `apply_prompt_framing`.
+    fn apply_messages(
+        payload: &mut serde_json::Value,
+        request: &TextGenerationRequest,
+        model_id: &str,
+    ) -> Result<()> {
+        let Some(messages) = &request.messages else {
+            return Ok(());
+        };
+        let messages: Vec<&ChatMessage> = messages
+            .iter()
+            .filter(|message| message.role != Role::System)
+            .collect();
+        if payload.get("anthropic_version").is_none()
+            && messages.iter().any(|message| message.content.has_images())
+        {
+            return Err(BedrockError::RequestError(format!(
+                "Image input is only supported by Anthropic Claude 3 and newer, not {}",
+                model_id
+            )));
+        }
+        if payload.get("messages").is_none() {
+            let key = if payload.get("inputText").is_some() {
+                "inputText"
+            } else {
+                "prompt"
+            };
+            payload[key] = json!(Self::transcript(&messages));
+            return Ok(());
+        }
+
+        payload["messages"] = if payload.get("anthropic_version").is_some() {
+            serde_json::Value::Array(
+                messages
+                    .iter()
+                    .map(|message| message.to_anthropic())
+                    .collect::<Result<_>>()?,
+            )
+        } else {
+            json!(messages
+                .iter()
+                .map(|message| {
+                    json!({ "role": message.role.as_str(), "content": message.content.text() })
+                })
+                .collect::<Vec<_>>())
+        };
+        Ok(())
+    }
+
+    // Ends on an `Assistant:` cue unless the last turn is already the
+    // assistant's, in which case the model continues that turn.
+    fn transcript(messages: &[&ChatMessage]) -> String {
+        let mut turns: Vec<String> = messages
+            .iter()
+            .map(|message| {
+                let speaker = match message.role {
+                    Role::Assistant => "Assistant",
+                    _ => "User",
+                };
+                format!("{}: {}", speaker, message.content.text())
+            })
+            .collect();
+        if messages.last().map(|message| message.role) != Some(Role::Assistant) {
+            turns.push("Assistant:".to_string());
+        }
+        turns.join("\n\n")
+    }
+
+    fn apply_prompt_framing(
+        &self,
+        payload: &mut serde_json::Value,
+        request: &TextGenerationRequest,
+    ) {
+        let system = self.system_parts(request).join("\n\n");
+        let suffix = self.prompt_suffix.as_deref().filter(|s| !s.is_empty());
+        // Anthropic takes the system prompt as a top-level field rather than
+        // as a message.
+        let top_level_system = payload.get("anthropic_version").is_some();
+
+        if let Some(messages) = payload
+            .get_mut("messages")
+            .and_then(|messages| messages.as_array_mut())
+        {
+            if let Some(suffix) = suffix {
+                if let Some(content) = messages
+                    .last_mut()
+                    .and_then(|message| message.get_mut("content"))
+                {
+                    if let Some(text) = content.as_str() {
+                        *content = json!(format!("{}\n\n{}", text, suffix));
+                    } else if let Some(blocks) = content.as_array_mut() {
+                        blocks.push(json!({ "type": "text", "text": suffix }));
+                    }
+                }
+            }
+            if system.is_empty() {
+                return;
+            }
+            if top_level_system {
+                payload["system"] = json!(system);
+            } else {
+                messages.insert(0, json!({ "role": "system", "content": system }));
+            }
+            return;
+        }
+
+        let key = if payload.get("inputText").is_some() {
+            "inputText"
+        } else {
+            "prompt"
+        };
+        if let Some(prompt) = payload.get(key).and_then(|p| p.as_str()) {
+            let framed = [Some(system.as_str()), Some(prompt), suffix]
+                .into_iter()
+                .flatten()
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            payload[key] = json!(framed);
+        }
+    }
+
+    fn apply_sampling(
+        &self,
+        payload: &mut serde_json::Value,
+        provider: &ModelProvider,
+        model_id: &str,
+        request: &TextGenerationRequest,
+    ) {
+        let defaults = self
+            .provider_defaults
+            .get(provider)
+            .cloned()
+            .unwrap_or_else(|| SamplingDefaults::for_provider(provider));
+        let sampling = SamplingDefaults {
+            top_p: request.top_p.or(defaults.top_p),
+            top_k: request.top_k.or(defaults.top_k),
+        };
+
+        let (top_p_key, top_k_key) = match provider {
+            ModelProvider::Amazon => {
+                if let Some(top_p) = sampling.top_p {
+                    payload["textGenerationConfig"]["topP"] = json!(top_p);
+                }
+                return;
+            }
+            ModelProvider::Anthropic => ("top_p", Some("top_k")),
+            ModelProvider::Cohere => ("p", Some("k")),
+            ModelProvider::AI21 if Self::is_jamba(model_id) => ("top_p", None),
+            ModelProvider::AI21 => ("topP", None),
+            ModelProvider::Meta => ("top_p", None),
+            ModelProvider::Mistral if Self::is_mistral_chat(model_id) => ("top_p", None),
+            ModelProvider::Mistral => ("top_p", Some("top_k")),
+        };
+
+        if let Some(top_p) = sampling.top_p {
+            payload[top_p_key] = json!(top_p);
+        }
+        if let (Some(top_k_key), Some(top_k)) = (top_k_key, sampling.top_k) {
+            payload[top_k_key] = json!(top_k);
+        }
+    }
+
+    // Event payloads aren't guaranteed to hold exactly one JSON object, so
+    // bytes are buffered and only complete objects are parsed.
+    fn parse_stream_bytes(
+        buffer: &mut JsonChunkBuffer,
+        bytes: &[u8],
+        model_id: &str,
+    ) -> Vec<Result<StreamChunk>> {
+        buffer
+            .push(bytes)
+            .iter()
+            .map(|chunk_str| Self::parse_stream_chunk_static(chunk_str, model_id))
+            .collect()
+    }
+
+    fn parse_stream_chunk_static(chunk_str: &str, model_id: &str) -> Result<StreamChunk> {
+        let json: serde_json::Value = serde_json::from_str(chunk_str)
+            .map_err(|e| BedrockError::ResponseError(e.to_string()))?;
+
+        let token_count = |value: &serde_json::Value| value.as_i64().map(|n| n as i32);
+
+        let mut stream_chunk = match model_id {
+            id if id.starts_with("amazon.titan") => StreamChunk {
+                chunk: json["outputText"].as_str().unwrap_or("").to_string(),
+                done: json["completionReason"].is_string(),
+                finish_reason: json["completionReason"].as_str().map(String::from),
+                tokens_prompt: token_count(&json["inputTextTokenCount"]),
+                tokens_generated: token_count(&json["totalOutputTextTokenCount"]),
+            },
+            id if id.starts_with("meta.llama") => StreamChunk {
                 chunk: json["generation"].as_str().unwrap_or("").to_string(),
                 done: json["stop_reason"].is_string(),
                 finish_reason: json["stop_reason"].as_str().map(String::from),
+                tokens_prompt: token_count(&json["prompt_token_count"]),
+                tokens_generated: token_count(&json["generation_token_count"]),
             },
+            id if Self::is_mistral_chat(id) => {
+                let choice = &json["choices"][0];
+                StreamChunk {
+                    chunk: choice["message"]["content"]
+                        .as_str()
+                        .or_else(|| choice["delta"]["content"].as_str())
+                        .unwrap_or("")
+                        .to_string(),
+                    done: choice["stop_reason"].is_string(),
+                    finish_reason: choice["stop_reason"].as_str().map(String::from),
+                    tokens_prompt: None,
+                    tokens_generated: None,
+                }
+            }
             id if id.starts_with("mistral.mistral") => StreamChunk {
                 chunk: json["outputs"][0]["text"]
                     .as_str()
@@ -283,6 +1602,29 @@ impl TextClient {
                     .to_string(),
                 done: json["outputs"][0]["stop_reason"].is_string(),
                 finish_reason: json["outputs"][0]["stop_reason"].as_str().map(String::from),
+                tokens_prompt: None,
+                tokens_generated: None,
+            },
+            id if Self::is_jamba(id) => StreamChunk {
+                chunk: json["choices"][0]["delta"]["content"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string(),
+                done: json["choices"][0]["finish_reason"].is_string(),
+                finish_reason: json["choices"][0]["finish_reason"]
+                    .as_str()
+                    .map(String::from),
+                tokens_prompt: token_count(&json["usage"]["prompt_tokens"]),
+                tokens_generated: token_count(&json["usage"]["completion_tokens"]),
+            },
+            // Command streams `text` pieces, then a chunk with `is_finished`
+            // and the finish reason.
+            id if id.starts_with("cohere.command") => StreamChunk {
+                chunk: json["text"].as_str().unwrap_or("").to_string(),
+                done: json["is_finished"].as_bool().unwrap_or(false),
+                finish_reason: json["finish_reason"].as_str().map(String::from),
+                tokens_prompt: None,
+                tokens_generated: None,
             },
             id if id.starts_with("anthropic.claude") => {
                 let delta = &json["delta"];
@@ -290,6 +1632,9 @@ impl TextClient {
                     chunk: delta["text"].as_str().unwrap_or("").to_string(),
                     done: json["type"].as_str() == Some("message_stop"),
                     finish_reason: json["delta"]["stop_reason"].as_str().map(String::from),
+                    // message_start carries the input count, message_delta the output count.
+                    tokens_prompt: token_count(&json["message"]["usage"]["input_tokens"]),
+                    tokens_generated: token_count(&json["usage"]["output_tokens"]),
                 }
             }
             _ => {
@@ -299,6 +1644,690 @@ impl TextClient {
             }
         };
 
+        // Bedrock appends invocation metrics to the final chunk of every model.
+        let metrics = &json["amazon-bedrock-invocationMetrics"];
+        stream_chunk.tokens_prompt = stream_chunk
+            .tokens_prompt
+            .or_else(|| token_count(&metrics["inputTokenCount"]));
+        stream_chunk.tokens_generated = stream_chunk
+            .tokens_generated
+            .or_else(|| token_count(&metrics["outputTokenCount"]));
+
         Ok(stream_chunk)
     }
 }
+
+// Stream backed by a spawned producer task. The task is aborted when the
+// stream is dropped, so abandoning a stream early stops the generation
+// instead of reading the rest of the response in the background.
+struct AbortOnDrop<S> {
+    inner: S,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl<S> AbortOnDrop<S> {
+    fn new(inner: S, task: tokio::task::JoinHandle<()>) -> Self {
+        Self { inner, task }
+    }
+}
+
+impl<S: Stream + Unpin> Stream for AbortOnDrop<S> {
+    type Item = S::Item;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+impl<S> Drop for AbortOnDrop<S> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// Multi-byte characters can be split across stream chunks, so incomplete
+// trailing bytes are held back until the next chunk completes them.
+#[derive(Default)]
+struct Utf8ChunkDecoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8ChunkDecoder {
+    fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+
+        let mut decoded = String::new();
+        loop {
+            match std::str::from_utf8(&self.pending) {
+                Ok(valid) => {
+                    decoded.push_str(valid);
+                    self.pending.clear();
+                    break;
+                }
+                Err(e) => {
+                    let valid_up_to = e.valid_up_to();
+                    decoded.push_str(
+                        std::str::from_utf8(&self.pending[..valid_up_to]).unwrap_or_default(),
+                    );
+                    match e.error_len() {
+                        // Genuinely invalid bytes can never complete, so replace them.
+                        Some(invalid_len) => {
+                            decoded.push(char::REPLACEMENT_CHARACTER);
+                            self.pending.drain(..valid_up_to + invalid_len);
+                        }
+                        None => {
+                            self.pending.drain(..valid_up_to);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        decoded
+    }
+}
+
+// Splits decoded stream text into complete JSON objects, holding back a
+// trailing partial object until later bytes finish it.
+#[derive(Default)]
+struct JsonChunkBuffer {
+    decoder: Utf8ChunkDecoder,
+    pending: String,
+}
+
+impl JsonChunkBuffer {
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.push_str(&self.decoder.decode(bytes));
+
+        let mut complete = Vec::new();
+        let mut consumed = 0;
+        let mut values =
+            serde_json::Deserializer::from_str(&self.pending).into_iter::<serde::de::IgnoredAny>();
+        loop {
+            match values.next() {
+                Some(Ok(_)) => {
+                    let end = values.byte_offset();
+                    complete.push(self.pending[consumed..end].trim().to_string());
+                    consumed = end;
+                }
+                Some(Err(e)) if e.is_eof() => break,
+                // Malformed input won't become valid with more bytes, so hand
+                // it on and let the parser report it.
+                Some(Err(_)) => {
+                    complete.push(self.pending[consumed..].trim().to_string());
+                    consumed = self.pending.len();
+                    break;
+                }
+                None => break,
+            }
+        }
+
+        if self.pending[consumed..].trim().is_empty() {
+            self.pending.clear();
+        } else {
+            self.pending.drain(..consumed);
+        }
+        complete
+    }
+
+    fn take_remainder(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.pending);
+        (!rest.trim().is_empty()).then_some(rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_client() -> TextClient {
+        let config = aws_sdk_bedrockruntime::Config::builder()
+            .behavior_version(aws_sdk_bedrockruntime::config::BehaviorVersion::latest())
+            .region(aws_sdk_bedrockruntime::config::Region::new("us-east-1"))
+            .build();
+        TextClient::new(Client::from_conf(config))
+    }
+
+    #[test]
+    fn test_utf8_decoder_carries_split_characters() {
+        let text = "héllo 世界 🎉";
+        let bytes = text.as_bytes();
+        let mut decoder = Utf8ChunkDecoder::default();
+
+        let mut output = String::new();
+        for chunk in bytes.chunks(3) {
+            output.push_str(&decoder.decode(chunk));
+        }
+
+        assert_eq!(output, text);
+        assert!(decoder.pending.is_empty());
+    }
+
+    #[test]
+    fn test_utf8_decoder_replaces_invalid_bytes() {
+        let mut decoder = Utf8ChunkDecoder::default();
+        assert_eq!(decoder.decode(b"ab\xffcd"), "ab\u{FFFD}cd");
+    }
+
+    #[test]
+    fn test_stream_summary_collects_claude_usage() {
+        let model = "anthropic.claude-3-haiku-20240307-v1:0";
+        let events = [
+            r#"{"type":"message_start","message":{"usage":{"input_tokens":12,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_delta","delta":{"type":"text_delta","text":"Hello"}}"#,
+            r#"{"type":"content_block_delta","delta":{"type":"text_delta","text":" world"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":7}}"#,
+            r#"{"type":"message_stop"}"#,
+        ];
+
+        let mut summary = StreamSummary::default();
+        for event in events {
+            summary.record(&TextClient::parse_stream_chunk_static(event, model).unwrap());
+        }
+
+        assert_eq!(summary.text, "Hello world");
+        assert_eq!(summary.chunk_count, 2);
+        assert_eq!(summary.finish_reason.as_deref(), Some("end_turn"));
+        assert_eq!(summary.tokens_prompt, Some(12));
+        assert_eq!(summary.tokens_generated, Some(7));
+    }
+
+    #[test]
+    fn test_mistral_large_uses_chat_payload_with_safe_prompt() {
+        let request = TextGenerationRequest {
+            prompt: "Hello".to_string(),
+            safe_prompt: Some(true),
+            ..Default::default()
+        };
+        let payload = test_client()
+            .text_payload(
+                &request,
+                "mistral.mistral-large-2402-v1:0",
+                &ModelProvider::Mistral,
+            )
+            .unwrap();
+
+        assert_eq!(payload["messages"][0]["content"], "Hello");
+        assert_eq!(payload["safe_prompt"], true);
+        assert!(payload.get("prompt").is_none());
+        assert!(payload.get("top_k").is_none());
+    }
+
+    #[test]
+    fn test_mistral_instruct_keeps_legacy_payload() {
+        let request = TextGenerationRequest {
+            prompt: "Hello".to_string(),
+            safe_prompt: Some(true),
+            ..Default::default()
+        };
+        let payload = test_client()
+            .text_payload(
+                &request,
+                "mistral.mistral-7b-instruct-v0:2",
+                &ModelProvider::Mistral,
+            )
+            .unwrap();
+
+        assert_eq!(payload["prompt"], "Hello");
+        assert!(payload.get("messages").is_none());
+        assert!(payload.get("safe_prompt").is_none());
+    }
+
+    #[test]
+    fn test_prompt_wrapper_frames_chat_and_prompt_payloads() {
+        let client =
+            test_client().with_prompt_wrapper(Some("House rules".into()), Some("Be brief.".into()));
+        let request = TextGenerationRequest {
+            prompt: "Hello".to_string(),
+            system_prompt: Some("You are helpful".to_string()),
+            ..Default::default()
+        };
+
+        let claude = client
+            .text_payload(
+                &request,
+                "anthropic.claude-3-haiku-20240307-v1:0",
+                &ModelProvider::Anthropic,
+            )
+            .unwrap();
+        assert_eq!(claude["system"], "House rules\n\nYou are helpful");
+        assert_eq!(claude["messages"][0]["content"], "Hello\n\nBe brief.");
+
+        let titan = client
+            .text_payload(
+                &request,
+                "amazon.titan-text-express-v1",
+                &ModelProvider::Amazon,
+            )
+            .unwrap();
+        assert_eq!(
+            titan["inputText"],
+            "House rules\n\nYou are helpful\n\nHello\n\nBe brief."
+        );
+    }
+
+    #[test]
+    fn test_preset_fills_unset_fields_only() {
+        let client = test_client();
+        client.register_preset(
+            "terse",
+            GenerationPreset {
+                temperature: Some(0.1),
+                top_p: Some(0.3),
+                top_k: None,
+                max_tokens: Some(64),
+            },
+        );
+        let request = TextGenerationRequest {
+            prompt: "Hello".to_string(),
+            temperature: Some(0.9),
+            preset: Some("terse".to_string()),
+            ..Default::default()
+        };
+
+        let payload = client
+            .text_payload(
+                &request,
+                "anthropic.claude-3-haiku-20240307-v1:0",
+                &ModelProvider::Anthropic,
+            )
+            .unwrap();
+        assert_eq!(payload["temperature"].as_f64().unwrap() as f32, 0.9);
+        assert_eq!(payload["max_tokens"], 64);
+        assert_eq!(payload["top_p"].as_f64().unwrap() as f32, 0.3);
+
+        let unknown = TextGenerationRequest {
+            preset: Some("missing".to_string()),
+            ..request
+        };
+        assert!(client
+            .text_payload(
+                &unknown,
+                "amazon.titan-text-express-v1",
+                &ModelProvider::Amazon
+            )
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dropping_stream_aborts_producer_task() {
+        let (_tx, rx) = tokio::sync::mpsc::channel::<()>(1);
+        let task = tokio::spawn(std::future::pending::<()>());
+        let abort = task.abort_handle();
+
+        drop(AbortOnDrop::new(ReceiverStream::new(rx), task));
+        tokio::task::yield_now().await;
+        assert!(abort.is_finished());
+    }
+
+    #[test]
+    fn test_image_messages_become_anthropic_content_blocks() {
+        let client = test_client();
+        let mut request = TextGenerationRequest {
+            messages: Some(vec![ChatMessage::user(vec![
+                ContentBlock::Image {
+                    base64: "aGVsbG8=".to_string(),
+                    media_type: "image/png".to_string(),
+                },
+                ContentBlock::Text("What is in this image?".to_string()),
+            ])]),
+            ..Default::default()
+        };
+
+        let payload = client
+            .text_payload(
+                &request,
+                "anthropic.claude-3-haiku-20240307-v1:0",
+                &ModelProvider::Anthropic,
+            )
+            .unwrap();
+        let content = &payload["messages"][0]["content"];
+        assert_eq!(content[0]["source"]["media_type"], "image/png");
+        assert_eq!(content[1]["text"], "What is in this image?");
+
+        assert!(client
+            .text_payload(&request, "ai21.jamba-1-5-mini-v1:0", &ModelProvider::AI21)
+            .is_err());
+
+        request.messages = Some(vec![ChatMessage::user(vec![ContentBlock::Image {
+            base64: "aGVsbG8=".to_string(),
+            media_type: "image/tiff".to_string(),
+        }])]);
+        assert!(client
+            .text_payload(
+                &request,
+                "anthropic.claude-3-haiku-20240307-v1:0",
+                &ModelProvider::Anthropic,
+            )
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_usage_events_follow_changed_token_counts() {
+        let chunk = |text: &str, prompt: Option<i32>, generated: Option<i32>| {
+            Ok(StreamChunk {
+                chunk: text.to_string(),
+                done: false,
+                finish_reason: None,
+                tokens_prompt: prompt,
+                tokens_generated: generated,
+            })
+        };
+        let events: Vec<_> = TextClient::with_usage_events(futures::stream::iter(vec![
+            chunk("", Some(12), Some(1)),
+            chunk("Hello", None, None),
+            chunk("", None, Some(1)),
+            chunk("", None, Some(5)),
+        ]))
+        .collect()
+        .await;
+
+        let usage: Vec<(Option<i32>, i32)> = events
+            .into_iter()
+            .filter_map(|event| match event.unwrap() {
+                StreamEvent::Usage {
+                    input_tokens,
+                    output_tokens_so_far,
+                } => Some((input_tokens, output_tokens_so_far)),
+                StreamEvent::Text(_) => None,
+            })
+            .collect();
+        assert_eq!(usage, vec![(Some(12), 1), (Some(12), 5)]);
+    }
+
+    #[test]
+    fn test_continuation_request_extends_chat_with_assistant_turn() {
+        let request = TextGenerationRequest {
+            messages: Some(vec![ChatMessage::user("Write a long story")]),
+            num_completions: Some(2),
+            ..Default::default()
+        };
+        let (next, partial) =
+            TextClient::continuation_request(&request, "Once upon a time ", 0).unwrap();
+
+        assert_eq!(partial, "Once upon a time");
+        assert_eq!(next.num_completions, None);
+        let messages = next.messages.unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].role, Role::Assistant);
+        assert_eq!(messages[1].content.text(), "Once upon a time");
+
+        let request = TextGenerationRequest {
+            prompt: "Write a long story".to_string(),
+            ..Default::default()
+        };
+        let (next, partial) =
+            TextClient::continuation_request(&request, "Once upon a time ", 0).unwrap();
+        assert_eq!(partial, "Once upon a time ");
+        assert!(next.prompt.starts_with("Write a long story"));
+        assert!(next.prompt.contains("Once upon a time"));
+    }
+
+    #[test]
+    fn test_stream_payloads_per_provider() {
+        let client = test_client();
+        let request = TextGenerationRequest {
+            prompt: "Hello".to_string(),
+            ..Default::default()
+        };
+        let streamed = |model_id: &str| {
+            let provider = TextClient::provider_for_model(model_id);
+            let mut payload = client.text_payload(&request, model_id, &provider).unwrap();
+            TextClient::enable_streaming(&mut payload, model_id);
+            payload
+        };
+
+        assert_eq!(
+            streamed("amazon.titan-text-express-v1")["textGenerationConfig"]["stream"],
+            true
+        );
+        assert_eq!(
+            streamed("anthropic.claude-3-haiku-20240307-v1:0")["stream"],
+            true
+        );
+        assert_eq!(streamed("cohere.command-text-v14")["stream"], true);
+        for model_id in [
+            "meta.llama3-8b-instruct-v1:0",
+            "mistral.mistral-7b-instruct-v0:2",
+            "mistral.mistral-large-2402-v1:0",
+            "ai21.jamba-instruct-v1:0",
+        ] {
+            assert!(streamed(model_id).get("stream").is_none(), "{}", model_id);
+        }
+
+        let chunk = TextClient::parse_stream_chunk_static(
+            r#"{"text":" Hi","is_finished":false}"#,
+            "cohere.command-text-v14",
+        )
+        .unwrap();
+        assert_eq!(chunk.chunk, " Hi");
+        let last = TextClient::parse_stream_chunk_static(
+            r#"{"is_finished":true,"finish_reason":"COMPLETE"}"#,
+            "cohere.command-text-v14",
+        )
+        .unwrap();
+        assert!(last.done);
+        assert_eq!(last.finish_reason.as_deref(), Some("COMPLETE"));
+    }
+
+    #[test]
+    fn test_converse_messages_carry_images_and_framing() {
+        let client = test_client().with_prompt_wrapper(
+            Some("Be brief.".to_string()),
+            Some("Answer in English.".to_string()),
+        );
+        let request = TextGenerationRequest {
+            messages: Some(vec![ChatMessage::user(vec![
+                ContentBlock::Text("What is this?".to_string()),
+                ContentBlock::Image {
+                    base64: STANDARD.encode(b"png bytes"),
+                    media_type: "image/png".to_string(),
+                },
+            ])]),
+            system_prompt: Some("You describe images.".to_string()),
+            ..Default::default()
+        };
+
+        let messages = client.converse_messages(&request).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].role(), &ConversationRole::User);
+        let content = messages[0].content();
+        assert_eq!(content.len(), 3);
+        let image = content[1].as_image().unwrap();
+        assert_eq!(image.format(), &ImageFormat::Png);
+        assert_eq!(content[2].as_text().unwrap(), "Answer in English.");
+
+        let system = client.converse_system(&request).unwrap();
+        assert_eq!(system.len(), 2);
+        assert!(client
+            .converse_system(&TextGenerationRequest::default())
+            .is_some());
+        assert!(test_client()
+            .converse_system(&TextGenerationRequest::default())
+            .is_none());
+    }
+
+    #[test]
+    fn test_system_alias_sets_claude_system_field() {
+        let request: TextGenerationRequest =
+            serde_json::from_value(json!({ "prompt": "Hello", "system": "Answer in French" }))
+                .unwrap();
+        let claude = test_client()
+            .text_payload(
+                &request,
+                "anthropic.claude-3-haiku-20240307-v1:0",
+                &ModelProvider::Anthropic,
+            )
+            .unwrap();
+        assert_eq!(claude["system"], "Answer in French");
+        assert_eq!(claude["messages"][0]["content"], "Hello");
+    }
+
+    #[test]
+    fn test_conversation_maps_roles_per_provider() {
+        let request = TextGenerationRequest {
+            messages: Some(vec![
+                ChatMessage::system("Answer in French"),
+                ChatMessage::user("Hi"),
+                ChatMessage::assistant("Bonjour"),
+                ChatMessage::user("How are you?"),
+            ]),
+            ..Default::default()
+        };
+        let client = test_client();
+
+        let claude = client
+            .text_payload(
+                &request,
+                "anthropic.claude-3-haiku-20240307-v1:0",
+                &ModelProvider::Anthropic,
+            )
+            .unwrap();
+        assert_eq!(claude["system"], "Answer in French");
+        assert_eq!(claude["messages"].as_array().unwrap().len(), 3);
+        assert_eq!(claude["messages"][1]["role"], "assistant");
+
+        let titan = client
+            .text_payload(
+                &request,
+                "amazon.titan-text-express-v1",
+                &ModelProvider::Amazon,
+            )
+            .unwrap();
+        assert_eq!(
+            titan["inputText"],
+            "Answer in French\n\nUser: Hi\n\nAssistant: Bonjour\n\nUser: How are you?\n\nAssistant:"
+        );
+    }
+
+    #[test]
+    fn test_stop_sequences_map_to_provider_fields() {
+        let client = test_client();
+        let request = |stops: Vec<&str>| TextGenerationRequest {
+            prompt: "List three colors".to_string(),
+            stop_sequences: Some(stops.into_iter().map(String::from).collect()),
+            ..Default::default()
+        };
+        let payload = |request: &TextGenerationRequest, model_id: &str| {
+            let provider = ModelProvider::from_model_id(model_id).unwrap();
+            client.text_payload(request, model_id, &provider)
+        };
+
+        let titan = payload(&request(vec!["User:"]), "amazon.titan-text-express-v1").unwrap();
+        assert_eq!(
+            titan["textGenerationConfig"]["stopSequences"],
+            json!(["User:"])
+        );
+        let claude = payload(
+            &request(vec!["\n\nHuman:"]),
+            "anthropic.claude-3-haiku-20240307-v1:0",
+        )
+        .unwrap();
+        assert_eq!(claude["stop_sequences"], json!(["\n\nHuman:"]));
+        let mistral = payload(&request(vec!["###"]), "mistral.mistral-large-2402-v1:0").unwrap();
+        assert_eq!(mistral["stop"], json!(["###"]));
+
+        let too_many = request(vec!["a", "b", "c", "d", "e"]);
+        assert!(matches!(
+            payload(&too_many, "cohere.command-text-v14"),
+            Err(BedrockError::RequestError(_))
+        ));
+        assert!(payload(&request(vec![]), "amazon.titan-text-express-v1").is_err());
+        assert!(payload(&request(vec!["END"]), "meta.llama3-8b-instruct-v1:0").is_err());
+    }
+
+    #[test]
+    fn test_sampling_values_are_validated() {
+        let client = test_client();
+        let payload = |top_p: Option<f32>, top_k: Option<i32>| {
+            let request = TextGenerationRequest {
+                prompt: "Hello".to_string(),
+                top_p,
+                top_k,
+                ..Default::default()
+            };
+            client.text_payload(&request, "cohere.command-text-v14", &ModelProvider::Cohere)
+        };
+
+        let cohere = payload(Some(0.5), Some(40)).unwrap();
+        assert_eq!(cohere["p"].as_f64().unwrap() as f32, 0.5);
+        assert_eq!(cohere["k"], 40);
+        assert_eq!(
+            payload(None, None).unwrap()["p"].as_f64().unwrap() as f32,
+            0.9
+        );
+        assert!(matches!(
+            payload(Some(1.5), None),
+            Err(BedrockError::RequestError(msg)) if msg.contains("top_p")
+        ));
+        assert!(payload(Some(-0.1), None).is_err());
+        assert!(payload(None, Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_stream_parser_waits_for_split_json_chunks() {
+        let model = "anthropic.claude-3-haiku-20240307-v1:0";
+        let event =
+            r#"{"type":"content_block_delta","delta":{"type":"text_delta","text":"Grüße"}}"#;
+        let bytes = event.as_bytes();
+        let split = event.find('ü').unwrap() + 1;
+        let mut buffer = JsonChunkBuffer::default();
+
+        assert!(TextClient::parse_stream_bytes(&mut buffer, &bytes[..split], model).is_empty());
+        let chunks = TextClient::parse_stream_bytes(&mut buffer, &bytes[split..], model);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].as_ref().unwrap().chunk, "Grüße");
+
+        let two = format!("{}\n{}", event, event);
+        let chunks = TextClient::parse_stream_bytes(&mut buffer, two.as_bytes(), model);
+        assert_eq!(chunks.len(), 2);
+        assert!(buffer.take_remainder().is_none());
+
+        TextClient::parse_stream_bytes(&mut buffer, &bytes[..10], model);
+        assert_eq!(buffer.take_remainder().as_deref(), Some(&event[..10]));
+    }
+
+    #[test]
+    fn test_meta_payload_uses_max_gen_len() {
+        let client = test_client();
+        let request = TextGenerationRequest {
+            prompt: "Hello".to_string(),
+            max_tokens: Some(64),
+            ..Default::default()
+        };
+        let payload = client
+            .text_payload(
+                &request,
+                "meta.llama3-8b-instruct-v1:0",
+                &ModelProvider::Meta,
+            )
+            .unwrap();
+
+        assert_eq!(payload["max_gen_len"], 64);
+        assert!(payload.get("max_tokens").is_none());
+    }
+
+    #[test]
+    fn test_continuation_request_spends_the_max_tokens_budget() {
+        let request = TextGenerationRequest {
+            prompt: "Write a long story".to_string(),
+            max_tokens: Some(100),
+            ..Default::default()
+        };
+
+        let (next, _) = TextClient::continuation_request(&request, "Once", 60).unwrap();
+        assert_eq!(next.max_tokens, Some(40));
+        assert!(TextClient::continuation_request(&request, "Once", 100).is_none());
+
+        let unbounded = TextGenerationRequest {
+            max_tokens: None,
+            ..request
+        };
+        let (next, _) = TextClient::continuation_request(&unbounded, "Once", 5000).unwrap();
+        assert_eq!(next.max_tokens, None);
+    }
+}
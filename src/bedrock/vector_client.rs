@@ -1,46 +1,293 @@
 use crate::{
+    bedrock::{
+        circuit_breaker::{guarded, CircuitBreaker},
+        log_request_id, map_aws_error,
+        payload_log::log_payload,
+        region_check::{check_region, RegionCheck},
+    },
     error::{BedrockError, Result},
-    models::EmbeddingRequest,
+    loaders::chunk_text,
+    models::{
+        embedding::estimate_tokens, embedding_input_limit, truncate_to_model_limit,
+        EmbeddingInputType, EmbeddingRequest, EmbeddingResponse, LongInputStrategy,
+    },
 };
 use aws_sdk_bedrockruntime::{primitives::Blob, Client};
+use futures::{StreamExt, TryStreamExt};
 use serde_json::json;
 
+pub const DEFAULT_EMBEDDING_MODEL: &str = "amazon.titan-embed-text-v1";
+
+// Embedding calls in flight at once for one batch; more than this mostly
+// buys ThrottlingException retries.
+pub(crate) const MAX_CONCURRENT_EMBEDDINGS: usize = 4;
+
 #[derive(Clone)]
 pub struct VectorClient {
     client: Client,
+    circuit_breaker: Option<CircuitBreaker>,
+    log_payloads: bool,
+    region_check: Option<RegionCheck>,
+    long_input: LongInputStrategy,
 }
 
 impl VectorClient {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            circuit_breaker: None,
+            log_payloads: false,
+            region_check: None,
+            long_input: LongInputStrategy::default(),
+        }
+    }
+
+    pub fn with_long_input_strategy(mut self, strategy: LongInputStrategy) -> Self {
+        self.long_input = strategy;
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(breaker);
+        self
+    }
+
+    pub fn with_payload_logging(mut self, enabled: bool) -> Self {
+        self.log_payloads = enabled;
+        self
+    }
+
+    pub(crate) fn with_region_check(mut self, check: RegionCheck) -> Self {
+        self.region_check = Some(check);
+        self
+    }
+
+    pub async fn generate_embedding(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse> {
+        let model_id = request
+            .model_id
+            .as_deref()
+            .unwrap_or(DEFAULT_EMBEDDING_MODEL);
+        let model = model_id.to_string();
+        let Some(limit) = Self::exceeded_limit(&request.text, model_id) else {
+            let embedding = self
+                .invoke(&request.text, model_id, request.input_type)
+                .await?;
+            return Ok(EmbeddingResponse { embedding, model });
+        };
+
+        let embedding = match self.long_input {
+            LongInputStrategy::Error => Err(BedrockError::RequestError(format!(
+                "Input is an estimated {} tokens (characters / 4), over the {}-token limit of {}; shorten it or set a LongInputStrategy",
+                estimate_tokens(&request.text),
+                limit,
+                model_id
+            ))),
+            LongInputStrategy::Truncate => {
+                let (truncated, _) = truncate_to_model_limit(&request.text, model_id);
+                log::warn!(
+                    "Truncated embedding input from about {} to {} tokens for {}",
+                    estimate_tokens(&request.text),
+                    limit,
+                    model_id
+                );
+                self.invoke(truncated, model_id, request.input_type).await
+            }
+            LongInputStrategy::ChunkAndAverage => {
+                let chunks = self.generate_chunked_embeddings(request).await?;
+                Ok(average_embeddings(
+                    chunks.iter().map(|(_, vector)| vector.as_slice()),
+                ))
+            }
+        }?;
+        Ok(EmbeddingResponse { embedding, model })
     }
 
-    pub async fn generate_embedding(&self, request: EmbeddingRequest) -> Result<String> {
+    // Splits the input into chunks that fit the model's limit and embeds each
+    // one, returning every chunk with its vector in input order. Short input
+    // is one chunk.
+    pub async fn generate_chunked_embeddings(
+        &self,
+        request: EmbeddingRequest,
+    ) -> Result<Vec<(String, Vec<f32>)>> {
         let model_id = request
             .model_id
             .as_deref()
-            .unwrap_or("amazon.titan-embed-text-v1");
-        let request_payload = json!({
-            "inputText": request.text
-        });
+            .unwrap_or(DEFAULT_EMBEDDING_MODEL);
+        let input_type = request.input_type;
+        let chunks = match embedding_input_limit(model_id) {
+            Some(limit) => chunk_text(&request.text, limit * 4, 0),
+            None => vec![request.text.clone()],
+        };
+
+        futures::stream::iter(chunks)
+            .map(|chunk| async move {
+                let vector = self.invoke(&chunk, model_id, input_type).await?;
+                Ok((chunk, vector))
+            })
+            .buffered(MAX_CONCURRENT_EMBEDDINGS)
+            .try_collect()
+            .await
+    }
+
+    fn exceeded_limit(text: &str, model_id: &str) -> Option<usize> {
+        embedding_input_limit(model_id).filter(|limit| estimate_tokens(text) > *limit)
+    }
+
+    async fn invoke(
+        &self,
+        text: &str,
+        model_id: &str,
+        input_type: EmbeddingInputType,
+    ) -> Result<Vec<f32>> {
+        check_region(self.region_check.as_ref(), model_id);
+        let request_payload = embedding_payload(text, model_id, input_type);
         let request_json = serde_json::to_string(&request_payload)
             .map_err(|e| BedrockError::SerializationError(e.to_string()))?;
 
         log::info!("Generating embedding with model: {}", model_id);
-        log::debug!("Embedding request payload: {}", request_json);
-
-        let response = self
-            .client
-            .invoke_model()
-            .model_id(model_id)
-            .content_type("application/json")
-            .accept("application/json")
-            .body(Blob::new(request_json.into_bytes()))
-            .send()
-            .await
-            .map_err(|e| BedrockError::AwsError(e.to_string()))?;
+        log_payload(
+            self.log_payloads,
+            "request",
+            model_id,
+            request_json.as_bytes(),
+        );
+
+        let response = guarded(self.circuit_breaker.as_ref(), async {
+            self.client
+                .invoke_model()
+                .model_id(model_id)
+                .content_type("application/json")
+                .accept("application/json")
+                .body(Blob::new(request_json.into_bytes()))
+                .send()
+                .await
+                .map_err(map_aws_error)
+        })
+        .await?;
+        log_request_id(model_id, &response);
 
         let response_bytes = response.body.into_inner();
-        String::from_utf8(response_bytes).map_err(|e| BedrockError::ResponseError(e.to_string()))
+        log_payload(self.log_payloads, "response", model_id, &response_bytes);
+        let body = String::from_utf8(response_bytes)
+            .map_err(|e| BedrockError::ResponseError(e.to_string()))?;
+        parse_embedding(&body)
+    }
+}
+
+fn embedding_payload(
+    text: &str,
+    model_id: &str,
+    input_type: EmbeddingInputType,
+) -> serde_json::Value {
+    if model_id.starts_with("cohere.") {
+        json!({
+            "texts": [text],
+            "input_type": input_type.as_str()
+        })
+    } else {
+        json!({
+            "inputText": text
+        })
+    }
+}
+
+// Titan (v1 and v2) answers with `embedding`, Cohere with `embeddings`, which
+// is keyed by type when `embedding_types` was requested.
+pub(crate) fn parse_embedding(body: &str) -> Result<Vec<f32>> {
+    let response_json: serde_json::Value =
+        serde_json::from_str(body).map_err(|e| BedrockError::ResponseError(e.to_string()))?;
+
+    let embeddings = &response_json["embeddings"];
+    let vector = response_json["embedding"]
+        .as_array()
+        .or_else(|| embeddings[0].as_array())
+        .or_else(|| embeddings["float"][0].as_array())
+        .ok_or_else(|| BedrockError::ResponseError("No embedding found in response".into()))?;
+
+    Ok(vector
+        .iter()
+        .filter_map(|v| v.as_f64().map(|f| f as f32))
+        .collect())
+}
+
+// Mean of the chunk vectors, scaled back to unit length: averaging unit
+// vectors shrinks the result, which would skew dot-product scores.
+fn average_embeddings<'a>(vectors: impl ExactSizeIterator<Item = &'a [f32]>) -> Vec<f32> {
+    let count = vectors.len() as f32;
+    let mut average: Vec<f32> = Vec::new();
+    for vector in vectors {
+        average.resize(average.len().max(vector.len()), 0.0);
+        for (sum, value) in average.iter_mut().zip(vector) {
+            *sum += value / count;
+        }
+    }
+
+    let norm = average.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        average.iter_mut().for_each(|v| *v /= norm);
+    }
+    average
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_over_limit_input_is_rejected_by_default() {
+        let config = aws_sdk_bedrockruntime::Config::builder()
+            .behavior_version(aws_sdk_bedrockruntime::config::BehaviorVersion::latest())
+            .region(aws_sdk_bedrockruntime::config::Region::new("us-east-1"))
+            .build();
+        let client = VectorClient::new(Client::from_conf(config));
+
+        let err = client
+            .generate_embedding(EmbeddingRequest {
+                text: "word ".repeat(1000),
+                model_id: Some("cohere.embed-english-v3".to_string()),
+                input_type: EmbeddingInputType::SearchDocument,
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BedrockError::RequestError(msg)
+            if msg.contains("512-token limit") && msg.contains("estimated")));
+    }
+
+    #[test]
+    fn test_averaged_embedding_is_unit_length() {
+        let chunks = [vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]];
+        let average = average_embeddings(chunks.iter().map(|v| v.as_slice()));
+
+        let norm = average.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-6);
+        assert!((average[0] - average[1]).abs() < 1e-6);
+        assert_eq!(average[2], 0.0);
+    }
+
+    #[test]
+    fn test_parse_embedding_response_shapes() {
+        let titan = r#"{"embedding":[0.125,-0.5,0.75,0.0],"inputTextTokenCount":3}"#;
+        assert_eq!(parse_embedding(titan).unwrap().len(), 4);
+
+        let cohere = r#"{"id":"a1","embeddings":[[0.1,0.2,0.3]],"texts":["hi"],"response_type":"embeddings_floats"}"#;
+        assert_eq!(parse_embedding(cohere).unwrap(), vec![0.1, 0.2, 0.3]);
+
+        let cohere_typed =
+            r#"{"embeddings":{"float":[[0.5,0.5]]},"response_type":"embeddings_by_type"}"#;
+        assert_eq!(parse_embedding(cohere_typed).unwrap().len(), 2);
+
+        assert!(parse_embedding(r#"{"message":"bad"}"#).is_err());
+
+        let cohere_model = "cohere.embed-english-v3";
+        let document = embedding_payload("hi", cohere_model, EmbeddingInputType::SearchDocument);
+        assert_eq!(document["input_type"], "search_document");
+        let query = embedding_payload("hi", cohere_model, EmbeddingInputType::SearchQuery);
+        assert_eq!(query["input_type"], "search_query");
+        let titan = embedding_payload(
+            "hi",
+            "amazon.titan-embed-text-v2:0",
+            EmbeddingInputType::SearchQuery,
+        );
+        assert!(titan.get("input_type").is_none());
     }
 }
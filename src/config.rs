@@ -1,28 +1,118 @@
-use std::env;
+use crate::{
+    error::{BedrockError, Result},
+    models::{
+        common::{ModelProvider, SamplingDefaults},
+        DuplicateIdPolicy, InputGuard, LongInputStrategy,
+    },
+};
+use std::{collections::HashMap, env, fmt, path::PathBuf, time::Duration};
 
-#[derive(Debug, Clone)]
+pub const DEFAULT_MAX_DIMENSIONS: usize = 4096;
+
+#[derive(Clone, Default)]
 pub struct PostgresConfig {
     pub host: Option<String>,
     pub port: Option<u16>,
     pub username: Option<String>,
     pub password: Option<String>,
     pub database: Option<String>,
+    // Number of ivfflat lists built into the vector index (defaults to 100).
+    pub index_lists: Option<i32>,
+    // Number of ivfflat lists scanned per search. Higher values improve recall
+    // at the cost of query latency; pgvector defaults to 1 when unset.
+    pub search_probes: Option<i32>,
+    // Creates the column as VECTOR(n) so pgvector rejects other sizes and the
+    // ivfflat index can be built. Unset keeps a dimension-less column that
+    // accepts mixed sizes but gets no vector index.
+    pub dimensions: Option<usize>,
+    // Applies pending schema migrations on startup (defaults to true). Turn
+    // off when the schema is managed elsewhere; pending steps are then only
+    // logged.
+    pub auto_migrate: Option<bool>,
 }
 
-#[derive(Debug, Clone)]
+// Metadata keys Pinecone and Upstash store record fields under, since both
+// only keep a vector and its metadata. Rename them when your own metadata
+// already uses one of the defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataKeys {
+    pub content: String,
+    pub namespace: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Default for MetadataKeys {
+    fn default() -> Self {
+        Self {
+            content: "content".to_string(),
+            namespace: "namespace".to_string(),
+            created_at: "created_at".to_string(),
+            updated_at: "updated_at".to_string(),
+        }
+    }
+}
+
+impl MetadataKeys {
+    pub fn validate(&self) -> Result<()> {
+        let keys = [
+            &self.content,
+            &self.namespace,
+            &self.created_at,
+            &self.updated_at,
+        ];
+        if keys.iter().any(|key| key.is_empty()) {
+            return Err(BedrockError::ConfigError(
+                "Metadata key names must not be empty".into(),
+            ));
+        }
+        for (i, key) in keys.iter().enumerate() {
+            if keys[i + 1..].contains(key) {
+                return Err(BedrockError::ConfigError(format!(
+                    "Metadata key name {:?} is used for more than one field",
+                    key
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Default)]
 pub struct PineconeConfig {
     pub api_key: Option<String>,
     pub environment: Option<String>,
     pub index_name: Option<String>,
+    // Vectors per upsert request; Pinecone recommends at most 100.
+    pub batch_size: Option<usize>,
+    // Gzip batch upsert bodies.
+    pub compress_requests: bool,
+    pub metadata_keys: MetadataKeys,
+    pub metric: PineconeMetric,
 }
 
-#[derive(Debug, Clone)]
+// Similarity metric the Pinecone index was created with. Only cosine scores
+// map onto the shared [0, 1] scale; hybrid (sparse-dense) search needs a
+// dotproduct index, whose scores are reported as Pinecone returns them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PineconeMetric {
+    #[default]
+    Cosine,
+    DotProduct,
+}
+
+#[derive(Clone, Default)]
 pub struct UpstashConfig {
     pub url: Option<String>,
     pub token: Option<String>,
+    // Vectors per upsert request; Upstash accepts up to 1000.
+    pub batch_size: Option<usize>,
+    // Gzip batch upsert bodies.
+    pub compress_requests: bool,
+    pub metadata_keys: MetadataKeys,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Config {
     pub port: Option<u16>,
     pub use_psql: bool,
@@ -33,18 +123,10 @@ pub struct Config {
     pub pinecone: Option<PineconeConfig>,
     pub upstash: Option<UpstashConfig>,
     pub secret_key: Option<String>,
-}
-
-impl Default for PostgresConfig {
-    fn default() -> Self {
-        PostgresConfig {
-            host: None,
-            port: None,
-            username: None,
-            password: None,
-            database: None,
-        }
-    }
+    pub operation_timeout: Option<Duration>,
+    pub required_metadata_keys: Vec<String>,
+    pub max_dimensions: Option<usize>,
+    pub duplicate_ids: DuplicateIdPolicy,
 }
 
 impl PostgresConfig {
@@ -58,6 +140,18 @@ impl PostgresConfig {
         let username = env::var("POSTGRES_USERNAME").ok();
         let password = env::var("POSTGRES_PASSWORD").ok();
         let database = env::var("POSTGRES_DATABASE").ok();
+        let index_lists = env::var("POSTGRES_INDEX_LISTS")
+            .ok()
+            .and_then(|s| s.parse().ok());
+        let search_probes = env::var("POSTGRES_SEARCH_PROBES")
+            .ok()
+            .and_then(|s| s.parse().ok());
+        let dimensions = env::var("POSTGRES_DIMENSIONS")
+            .ok()
+            .and_then(|s| s.parse().ok());
+        let auto_migrate = env::var("POSTGRES_AUTO_MIGRATE")
+            .ok()
+            .map(|s| s.to_lowercase() == "true");
 
         PostgresConfig {
             host,
@@ -65,6 +159,10 @@ impl PostgresConfig {
             username,
             password,
             database,
+            index_lists,
+            search_probes,
+            dimensions,
+            auto_migrate,
         }
     }
 
@@ -89,15 +187,25 @@ impl PostgresConfig {
         self.database = Some(database.into());
         self
     }
-}
 
-impl Default for PineconeConfig {
-    fn default() -> Self {
-        PineconeConfig {
-            api_key: None,
-            environment: None,
-            index_name: None,
-        }
+    pub fn with_index_lists(mut self, lists: i32) -> Self {
+        self.index_lists = Some(lists);
+        self
+    }
+
+    pub fn with_search_probes(mut self, probes: i32) -> Self {
+        self.search_probes = Some(probes);
+        self
+    }
+
+    pub fn with_dimensions(mut self, dimensions: usize) -> Self {
+        self.dimensions = Some(dimensions);
+        self
+    }
+
+    pub fn with_auto_migrate(mut self, enabled: bool) -> Self {
+        self.auto_migrate = Some(enabled);
+        self
     }
 }
 
@@ -109,11 +217,28 @@ impl PineconeConfig {
         let api_key = env::var("PINECONE_API_KEY").ok();
         let environment = env::var("PINECONE_ENVIRONMENT").ok();
         let index_name = env::var("PINECONE_INDEX_NAME").ok();
+        let batch_size = env::var("PINECONE_BATCH_SIZE")
+            .ok()
+            .and_then(|s| s.parse().ok());
+        let compress_requests = env::var("PINECONE_COMPRESS_REQUESTS")
+            .map(|s| s.to_lowercase() == "true")
+            .unwrap_or(false);
+        let metric = match env::var("PINECONE_METRIC")
+            .map(|s| s.to_lowercase())
+            .as_deref()
+        {
+            Ok("dotproduct") => PineconeMetric::DotProduct,
+            _ => PineconeMetric::Cosine,
+        };
 
         PineconeConfig {
             api_key,
             environment,
             index_name,
+            batch_size,
+            compress_requests,
+            metadata_keys: MetadataKeys::default(),
+            metric,
         }
     }
 
@@ -131,14 +256,25 @@ impl PineconeConfig {
         self.index_name = Some(index_name.into());
         self
     }
-}
 
-impl Default for UpstashConfig {
-    fn default() -> Self {
-        UpstashConfig {
-            url: None,
-            token: None,
-        }
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    pub fn with_request_compression(mut self, enabled: bool) -> Self {
+        self.compress_requests = enabled;
+        self
+    }
+
+    pub fn with_metadata_keys(mut self, keys: MetadataKeys) -> Self {
+        self.metadata_keys = keys;
+        self
+    }
+
+    pub fn with_metric(mut self, metric: PineconeMetric) -> Self {
+        self.metric = metric;
+        self
     }
 }
 
@@ -155,8 +291,35 @@ impl UpstashConfig {
     pub fn from_env() -> Self {
         let url = env::var("UPSTASH_URL").ok();
         let token = env::var("UPSTASH_TOKEN").ok();
+        let batch_size = env::var("UPSTASH_BATCH_SIZE")
+            .ok()
+            .and_then(|s| s.parse().ok());
+        let compress_requests = env::var("UPSTASH_COMPRESS_REQUESTS")
+            .map(|s| s.to_lowercase() == "true")
+            .unwrap_or(false);
 
-        UpstashConfig { url, token }
+        UpstashConfig {
+            url,
+            token,
+            batch_size,
+            compress_requests,
+            metadata_keys: MetadataKeys::default(),
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    pub fn with_request_compression(mut self, enabled: bool) -> Self {
+        self.compress_requests = enabled;
+        self
+    }
+
+    pub fn with_metadata_keys(mut self, keys: MetadataKeys) -> Self {
+        self.metadata_keys = keys;
+        self
     }
 }
 
@@ -172,6 +335,10 @@ impl Default for Config {
             pinecone: None,
             upstash: None,
             secret_key: Some("".to_string()),
+            operation_timeout: None,
+            required_metadata_keys: Vec::new(),
+            max_dimensions: Some(DEFAULT_MAX_DIMENSIONS),
+            duplicate_ids: DuplicateIdPolicy::default(),
         }
     }
 }
@@ -187,13 +354,35 @@ impl Config {
     }
     pub fn from_env() -> Self {
         let port = env::var("PORT").ok().and_then(|port| port.parse().ok());
-        let use_psql = env::var("USE_PSQL").ok().map_or(false, |val| val == "true");
+        let use_psql = env::var("USE_PSQL").ok().is_some_and(|val| val == "true");
         let use_pinecone = env::var("USE_PINECONE")
             .ok()
-            .map_or(false, |val| val == "true");
+            .is_some_and(|val| val == "true");
         let use_upstash = env::var("USE_UPSTASH")
             .ok()
-            .map_or(false, |val| val == "true");
+            .is_some_and(|val| val == "true");
+        let operation_timeout = env::var("STORAGE_TIMEOUT_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .map(Duration::from_secs);
+        let required_metadata_keys = env::var("STORAGE_REQUIRED_METADATA_KEYS")
+            .map(|keys| {
+                keys.split(',')
+                    .map(str::trim)
+                    .filter(|key| !key.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let max_dimensions = match env::var("STORAGE_MAX_DIMENSIONS") {
+            Ok(val) if val == "none" => None,
+            Ok(val) => val.parse().ok().or(Some(DEFAULT_MAX_DIMENSIONS)),
+            Err(_) => Some(DEFAULT_MAX_DIMENSIONS),
+        };
+        let duplicate_ids = match env::var("STORAGE_DUPLICATE_IDS").as_deref() {
+            Ok("last_wins") => DuplicateIdPolicy::LastWins,
+            _ => DuplicateIdPolicy::Error,
+        };
 
         Config {
             port,
@@ -205,6 +394,10 @@ impl Config {
             pinecone: None,
             upstash: None,
             secret_key: Some("".to_string()),
+            operation_timeout,
+            required_metadata_keys,
+            max_dimensions,
+            duplicate_ids,
         }
     }
     pub fn with_bedrock(mut self, config: BedrockConfig) -> Self {
@@ -212,40 +405,111 @@ impl Config {
         self
     }
 
+    // Only one storage backend can be active, so each of these switches the
+    // others off; the last call wins.
     pub fn with_postgres(mut self, config: PostgresConfig) -> Self {
         self.postgres = Some(config);
-        self.use_psql = true;
+        self.select_backend(true, false, false);
         self
     }
 
     pub fn with_pinecone(mut self, config: PineconeConfig) -> Self {
         self.pinecone = Some(config);
-        self.use_pinecone = true;
+        self.select_backend(false, true, false);
         self
     }
 
     pub fn with_upstash(mut self, config: UpstashConfig) -> Self {
         self.upstash = Some(config);
-        self.use_upstash = true;
+        self.select_backend(false, false, true);
+        self
+    }
+
+    fn select_backend(&mut self, psql: bool, pinecone: bool, upstash: bool) {
+        self.use_psql = psql;
+        self.use_pinecone = pinecone;
+        self.use_upstash = upstash;
+    }
+
+    pub fn enabled_backend_count(&self) -> usize {
+        [self.use_psql, self.use_pinecone, self.use_upstash]
+            .into_iter()
+            .filter(|enabled| *enabled)
+            .count()
+    }
+
+    pub fn with_operation_timeout(mut self, timeout: Duration) -> Self {
+        self.operation_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_required_metadata_keys(mut self, keys: Vec<String>) -> Self {
+        self.required_metadata_keys = keys;
+        self
+    }
+
+    pub fn with_max_dimensions(mut self, max_dimensions: Option<usize>) -> Self {
+        self.max_dimensions = max_dimensions;
+        self
+    }
+
+    pub fn with_duplicate_ids(mut self, policy: DuplicateIdPolicy) -> Self {
+        self.duplicate_ids = policy;
         self
     }
 }
-#[derive(Debug, Clone)]
+#[derive(Clone, Default)]
 pub struct BedrockConfig {
     pub region: Option<String>,
     pub access_key: Option<String>,
     pub secret_key: Option<String>,
+    // Shared credentials file to read instead of ~/.aws/credentials, and the
+    // profile to use from it. Ignored when explicit keys are set.
+    pub credentials_file: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub assume_role_arn: Option<String>,
+    pub external_id: Option<String>,
+    // Overrides for the built-in per-provider sampling values.
+    pub provider_defaults: HashMap<ModelProvider, SamplingDefaults>,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    pub log_payloads: bool,
+    // Wrapped around every text generation prompt; see `TextClient::with_prompt_wrapper`.
+    pub prompt_prefix: Option<String>,
+    pub prompt_suffix: Option<String>,
+    // Resumes allowed per stream after a transient mid-stream failure.
+    pub max_stream_resumes: u32,
+    pub fallback_to_non_streaming: bool,
+    // Embedding input over the model's token limit errors unless chunked.
+    pub long_embedding_input: LongInputStrategy,
+    // Embedding model `embed_and_store` and `semantic_search` use unless a
+    // call names another, so documents and queries are embedded alike.
+    pub embedding_model: Option<String>,
+    // Length and denylist limits applied to every text generation request.
+    pub input_guard: Option<InputGuard>,
 }
 
-impl Default for BedrockConfig {
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    // Consecutive transient failures, all within `window`, that open the circuit.
+    pub failure_threshold: u32,
+    pub window: Duration,
+    // How long calls are rejected before a single probe is let through.
+    pub cooldown: Duration,
+    // Each failed probe doubles the cooldown, up to this limit.
+    pub max_cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
     fn default() -> Self {
-        BedrockConfig {
-            region: None,
-            access_key: None,
-            secret_key: None,
+        Self {
+            failure_threshold: 5,
+            window: Duration::from_secs(60),
+            cooldown: Duration::from_secs(30),
+            max_cooldown: Duration::from_secs(300),
         }
     }
 }
+
 impl BedrockConfig {
     pub fn new() -> Self {
         Self::default()
@@ -265,4 +529,222 @@ impl BedrockConfig {
         self.secret_key = Some(secret_key.into());
         self
     }
+
+    pub fn with_credentials_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.credentials_file = Some(path.into());
+        self
+    }
+
+    pub fn with_profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    pub fn with_assume_role(mut self, role_arn: impl Into<String>) -> Self {
+        self.assume_role_arn = Some(role_arn.into());
+        self
+    }
+
+    pub fn with_external_id(mut self, external_id: impl Into<String>) -> Self {
+        self.external_id = Some(external_id.into());
+        self
+    }
+
+    pub fn with_provider_defaults(
+        mut self,
+        provider: ModelProvider,
+        defaults: SamplingDefaults,
+    ) -> Self {
+        self.provider_defaults.insert(provider, defaults);
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit_breaker = Some(config);
+        self
+    }
+
+    pub fn with_prompt_wrapper(mut self, prefix: Option<String>, suffix: Option<String>) -> Self {
+        self.prompt_prefix = prefix;
+        self.prompt_suffix = suffix;
+        self
+    }
+
+    pub fn with_stream_resumes(mut self, max_resumes: u32) -> Self {
+        self.max_stream_resumes = max_resumes;
+        self
+    }
+
+    pub fn with_stream_fallback(mut self, enabled: bool) -> Self {
+        self.fallback_to_non_streaming = enabled;
+        self
+    }
+
+    pub fn with_long_embedding_input(mut self, strategy: LongInputStrategy) -> Self {
+        self.long_embedding_input = strategy;
+        self
+    }
+
+    pub fn with_embedding_model(mut self, model_id: impl Into<String>) -> Self {
+        self.embedding_model = Some(model_id.into());
+        self
+    }
+
+    pub fn with_input_guard(mut self, guard: InputGuard) -> Self {
+        self.input_guard = Some(guard);
+        self
+    }
+
+    pub fn with_payload_logging(mut self, enabled: bool) -> Self {
+        self.log_payloads = enabled;
+        self
+    }
+}
+
+fn redact(value: &Option<String>) -> Option<&'static str> {
+    value.as_ref().map(|_| "***")
+}
+
+impl fmt::Debug for PostgresConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PostgresConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &redact(&self.password))
+            .field("database", &self.database)
+            .field("index_lists", &self.index_lists)
+            .field("search_probes", &self.search_probes)
+            .field("dimensions", &self.dimensions)
+            .field("auto_migrate", &self.auto_migrate)
+            .finish()
+    }
+}
+
+impl fmt::Debug for PineconeConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PineconeConfig")
+            .field("api_key", &redact(&self.api_key))
+            .field("environment", &self.environment)
+            .field("index_name", &self.index_name)
+            .field("batch_size", &self.batch_size)
+            .field("compress_requests", &self.compress_requests)
+            .field("metadata_keys", &self.metadata_keys)
+            .field("metric", &self.metric)
+            .finish()
+    }
+}
+
+impl fmt::Debug for UpstashConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UpstashConfig")
+            .field("url", &self.url)
+            .field("token", &redact(&self.token))
+            .field("batch_size", &self.batch_size)
+            .field("compress_requests", &self.compress_requests)
+            .field("metadata_keys", &self.metadata_keys)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("port", &self.port)
+            .field("use_psql", &self.use_psql)
+            .field("use_pinecone", &self.use_pinecone)
+            .field("use_upstash", &self.use_upstash)
+            .field("bedrock", &self.bedrock)
+            .field("postgres", &self.postgres)
+            .field("pinecone", &self.pinecone)
+            .field("upstash", &self.upstash)
+            .field("secret_key", &redact(&self.secret_key))
+            .field("operation_timeout", &self.operation_timeout)
+            .field("required_metadata_keys", &self.required_metadata_keys)
+            .field("max_dimensions", &self.max_dimensions)
+            .field("duplicate_ids", &self.duplicate_ids)
+            .finish()
+    }
+}
+
+impl fmt::Debug for BedrockConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BedrockConfig")
+            .field("region", &self.region)
+            .field("access_key", &self.access_key)
+            .field("secret_key", &redact(&self.secret_key))
+            .field("credentials_file", &self.credentials_file)
+            .field("profile", &self.profile)
+            .field("assume_role_arn", &self.assume_role_arn)
+            .field("external_id", &redact(&self.external_id))
+            .field("provider_defaults", &self.provider_defaults)
+            .field("circuit_breaker", &self.circuit_breaker)
+            .field("prompt_prefix", &self.prompt_prefix)
+            .field("prompt_suffix", &self.prompt_suffix)
+            .field("max_stream_resumes", &self.max_stream_resumes)
+            .field("fallback_to_non_streaming", &self.fallback_to_non_streaming)
+            .field("long_embedding_input", &self.long_embedding_input)
+            .field("embedding_model", &self.embedding_model)
+            .field("input_guard", &self.input_guard)
+            .field("log_payloads", &self.log_payloads)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let config = Config::new()
+            .with_bedrock(BedrockConfig::new().with_credentials("AKIA", "bedrock-secret"))
+            .with_postgres(PostgresConfig::new().with_credentials("user", "pg-password"))
+            .with_pinecone(PineconeConfig::new().with_credentials("pinecone-key"))
+            .with_upstash(
+                UpstashConfig::new().with_credentials("https://upstash", "upstash-token"),
+            );
+
+        let output = format!("{:?}", config);
+        for secret in [
+            "bedrock-secret",
+            "pg-password",
+            "pinecone-key",
+            "upstash-token",
+        ] {
+            assert!(!output.contains(secret), "{} leaked in {}", secret, output);
+        }
+        assert!(output.contains("https://upstash"));
+        assert!(output.contains("\"***\""));
+    }
+
+    #[test]
+    fn test_backend_builders_are_mutually_exclusive() {
+        let config = Config::new()
+            .with_postgres(PostgresConfig::new())
+            .with_upstash(UpstashConfig::new());
+
+        assert!(config.use_upstash);
+        assert!(!config.use_psql && !config.use_pinecone);
+        assert_eq!(config.enabled_backend_count(), 1);
+    }
+
+    #[test]
+    fn test_metadata_keys_must_be_distinct() {
+        assert!(MetadataKeys::default().validate().is_ok());
+
+        let keys = MetadataKeys {
+            content: "body".to_string(),
+            ..Default::default()
+        };
+        assert!(keys.validate().is_ok());
+
+        let keys = MetadataKeys {
+            content: "namespace".to_string(),
+            ..Default::default()
+        };
+        assert!(
+            matches!(keys.validate(), Err(BedrockError::ConfigError(msg)) if msg.contains("\"namespace\""))
+        );
+    }
 }
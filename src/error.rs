@@ -1,5 +1,7 @@
 use std::fmt;
 
+pub(crate) const REQUEST_ID_MARKER: &str = "(request id: ";
+
 #[derive(Debug)]
 pub enum BedrockError {
     ConfigError(String),
@@ -10,6 +12,9 @@ pub enum BedrockError {
     InternalError(String),
     AwsError(String),
     AwsServiceError(String),
+    Timeout(String),
+    ContentFiltered(String),
+    CircuitOpen(String),
 }
 
 impl fmt::Display for BedrockError {
@@ -23,10 +28,59 @@ impl fmt::Display for BedrockError {
             BedrockError::InternalError(msg) => write!(f, "Internal error: {}", msg),
             BedrockError::AwsError(msg) => write!(f, "AWS error: {}", msg),
             BedrockError::AwsServiceError(msg) => write!(f, "AWS service error: {}", msg),
+            BedrockError::Timeout(msg) => write!(f, "Timeout: {}", msg),
+            BedrockError::ContentFiltered(msg) => write!(f, "Content filtered: {}", msg),
+            BedrockError::CircuitOpen(msg) => write!(f, "Circuit open: {}", msg),
         }
     }
 }
 
+impl BedrockError {
+    // Failures that reflect the health of the service rather than a bad request.
+    pub fn is_transient(&self) -> bool {
+        const TRANSIENT_CODES: [&str; 5] = [
+            "ThrottlingException",
+            "ServiceUnavailableException",
+            "InternalServerException",
+            "ModelTimeoutException",
+            "ModelNotReadyException",
+        ];
+
+        match self {
+            BedrockError::AwsError(_) | BedrockError::Timeout(_) => true,
+            BedrockError::AwsServiceError(msg) => {
+                TRANSIENT_CODES.iter().any(|code| msg.contains(code))
+            }
+            _ => false,
+        }
+    }
+
+    pub fn request_id(&self) -> Option<&str> {
+        let msg = match self {
+            BedrockError::AwsError(msg) | BedrockError::AwsServiceError(msg) => msg,
+            _ => return None,
+        };
+        let start = msg.rfind(REQUEST_ID_MARKER)? + REQUEST_ID_MARKER.len();
+        msg[start..].strip_suffix(')')
+    }
+}
+
 impl std::error::Error for BedrockError {}
 
 pub type Result<T> = std::result::Result<T, BedrockError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_id_is_read_back_from_aws_errors() {
+        let err = BedrockError::AwsServiceError(format!(
+            "Bedrock service error: ThrottlingException - slow down {}abc-123)",
+            REQUEST_ID_MARKER
+        ));
+        assert_eq!(err.request_id(), Some("abc-123"));
+        assert!(err.is_transient());
+        assert_eq!(BedrockError::RequestError("bad".into()).request_id(), None);
+    }
+}
@@ -1,12 +1,24 @@
+pub mod backoff;
 pub mod bedrock;
 pub mod config;
 pub mod error;
+pub mod loaders;
 pub mod logger;
 pub mod models;
 pub mod storage;
+pub use backoff::{Backoff, Jitter};
 pub use bedrock::{BedrockClient, ImageClient, TextClient, VectorClient};
-pub use config::{BedrockConfig, Config, PineconeConfig, PostgresConfig, UpstashConfig};
+pub use config::{
+    BedrockConfig, CircuitBreakerConfig, Config, MetadataKeys, PineconeConfig, PineconeMetric,
+    PostgresConfig, UpstashConfig,
+};
 pub use error::{BedrockError, Result};
-pub use logger::{init, init_with_config, log_config_info, log_startup_info, timer, Timer};
+pub use loaders::DocumentLoader;
+pub use logger::{
+    init, init_from_env, init_with_config, log_config_info, log_startup_info, timer, Timer,
+};
 pub use models::*;
-pub use storage::{StorageStats, VectorStorageManager, VectorStorageTrait};
+pub use storage::{
+    AggregatedStats, BackendHealth, CachedVectorStorage, InMemoryVectorStorage,
+    StorageCapabilities, StorageStats, VectorStorageManager, VectorStorageTrait,
+};
@@ -0,0 +1,86 @@
+use crate::{
+    error::Result,
+    loaders::{base_metadata, has_extension, read_to_string, DocumentLoader},
+    models::document::LoadedDocument,
+};
+use serde_json::json;
+use std::path::Path;
+
+pub struct HtmlLoader;
+
+impl HtmlLoader {
+    pub fn extract_text(html: &str) -> String {
+        let mut text = String::with_capacity(html.len());
+        let mut rest = html;
+
+        while let Some(start) = rest.find('<') {
+            text.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let lower = rest.get(..7).unwrap_or(rest).to_ascii_lowercase();
+            // Script and style bodies are not document text.
+            let skip_until = if lower.starts_with("<script") {
+                Some("</script>")
+            } else if lower.starts_with("<style") {
+                Some("</style>")
+            } else {
+                None
+            };
+
+            let end = match skip_until {
+                Some(closing) => rest
+                    .to_ascii_lowercase()
+                    .find(closing)
+                    .map(|i| i + closing.len()),
+                None => rest.find('>').map(|i| i + 1),
+            };
+            match end {
+                Some(end) => {
+                    text.push(' ');
+                    rest = &rest[end..];
+                }
+                None => {
+                    rest = "";
+                }
+            }
+        }
+        text.push_str(rest);
+
+        let decoded = text
+            .replace("&nbsp;", " ")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&amp;", "&");
+
+        decoded.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn extract_title(html: &str) -> Option<String> {
+        let lower = html.to_ascii_lowercase();
+        let start = lower.find("<title")?;
+        let content_start = start + lower[start..].find('>')? + 1;
+        let content_end = content_start + lower[content_start..].find("</title>")?;
+        Some(html[content_start..content_end].trim().to_string())
+    }
+}
+
+impl DocumentLoader for HtmlLoader {
+    fn supports(&self, path: &Path) -> bool {
+        has_extension(path, &["html", "htm"])
+    }
+
+    fn load(&self, path: &Path) -> Result<Vec<LoadedDocument>> {
+        let html = read_to_string(path)?;
+        let mut metadata = base_metadata(path, "html");
+        if let Some(title) = Self::extract_title(&html) {
+            metadata.insert("title".to_string(), json!(title));
+        }
+
+        Ok(vec![LoadedDocument {
+            text: Self::extract_text(&html),
+            metadata,
+        }])
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{
+    error::Result,
+    loaders::{base_metadata, has_extension, read_to_string, DocumentLoader},
+    models::document::LoadedDocument,
+};
+use serde_json::json;
+use std::path::Path;
+
+pub struct MarkdownLoader;
+
+impl MarkdownLoader {
+    pub fn strip_formatting(markdown: &str) -> String {
+        let mut lines = Vec::new();
+        let mut in_code_block = false;
+
+        for line in markdown.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code_block = !in_code_block;
+                continue;
+            }
+            if in_code_block {
+                lines.push(line.to_string());
+                continue;
+            }
+
+            let line = trimmed
+                .trim_start_matches('#')
+                .trim_start_matches('>')
+                .trim_start();
+            let line = line
+                .strip_prefix("- ")
+                .or_else(|| line.strip_prefix("* "))
+                .or_else(|| line.strip_prefix("+ "))
+                .unwrap_or(line);
+
+            lines.push(Self::strip_inline(line));
+        }
+
+        lines.join("\n")
+    }
+
+    fn strip_inline(line: &str) -> String {
+        let chars: Vec<char> = line.chars().collect();
+        let mut output = String::with_capacity(line.len());
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                '*' | '_' | '`' | '~' => {
+                    let run = chars[i..].iter().take_while(|&&d| d == c).count();
+                    match Self::closing_delimiter(&chars, i, run) {
+                        Some(close) => {
+                            let inner: String = chars[i + run..close].iter().collect();
+                            if c == '`' {
+                                output.push_str(&inner);
+                            } else {
+                                output.push_str(&Self::strip_inline(&inner));
+                            }
+                            i = close + run;
+                        }
+                        None => {
+                            output.extend(&chars[i..i + run]);
+                            i += run;
+                        }
+                    }
+                }
+                '!' if chars.get(i + 1) == Some(&'[') => i += 1,
+                '[' => {
+                    let end = chars[i..]
+                        .iter()
+                        .position(|&c| c == ']')
+                        .map_or(chars.len(), |offset| i + offset);
+                    output.extend(&chars[i + 1..end]);
+                    i = end + 1;
+                    // Drop the link target, keeping only its label.
+                    if chars.get(i) == Some(&'(') {
+                        i = chars[i..]
+                            .iter()
+                            .position(|&c| c == ')')
+                            .map_or(chars.len(), |offset| i + offset + 1);
+                    }
+                }
+                _ => {
+                    output.push(c);
+                    i += 1;
+                }
+            }
+        }
+
+        output
+    }
+
+    // Emphasis and code delimiters only count when they pair up: the opening
+    // run must be followed, and the closing run preceded, by non-space, and
+    // `_` never opens or closes inside a word, so `snake_case` and `2 * 3`
+    // are left alone.
+    fn closing_delimiter(chars: &[char], open: usize, run: usize) -> Option<usize> {
+        let c = chars[open];
+        let in_word = |ch: Option<&char>| c == '_' && ch.is_some_and(|ch| ch.is_alphanumeric());
+        if chars.get(open + run)?.is_whitespace()
+            || in_word(open.checked_sub(1).and_then(|p| chars.get(p)))
+        {
+            return None;
+        }
+
+        let mut j = open + run;
+        while j < chars.len() {
+            if chars[j] != c {
+                j += 1;
+                continue;
+            }
+            let len = chars[j..].iter().take_while(|&&d| d == c).count();
+            if len == run && !chars[j - 1].is_whitespace() && !in_word(chars.get(j + len)) {
+                return Some(j);
+            }
+            j += len;
+        }
+        None
+    }
+}
+
+impl DocumentLoader for MarkdownLoader {
+    fn supports(&self, path: &Path) -> bool {
+        has_extension(path, &["md", "markdown"])
+    }
+
+    fn load(&self, path: &Path) -> Result<Vec<LoadedDocument>> {
+        let markdown = read_to_string(path)?;
+        let mut metadata = base_metadata(path, "markdown");
+        if let Some(title) = markdown
+            .lines()
+            .find_map(|line| line.trim_start().strip_prefix("# "))
+        {
+            metadata.insert("title".to_string(), json!(title.trim()));
+        }
+
+        Ok(vec![LoadedDocument {
+            text: Self::strip_formatting(&markdown),
+            metadata,
+        }])
+    }
+}
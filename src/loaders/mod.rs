@@ -0,0 +1,137 @@
+pub mod html;
+pub mod markdown;
+pub mod text;
+
+use crate::{
+    error::{BedrockError, Result},
+    models::document::LoadedDocument,
+};
+use serde_json::json;
+use std::{collections::HashMap, path::Path};
+
+pub use html::HtmlLoader;
+pub use markdown::MarkdownLoader;
+pub use text::TextLoader;
+
+pub trait DocumentLoader: Send + Sync {
+    fn supports(&self, path: &Path) -> bool;
+    fn load(&self, path: &Path) -> Result<Vec<LoadedDocument>>;
+}
+
+pub fn loader_for_path(path: &Path) -> Result<Box<dyn DocumentLoader>> {
+    let loaders: Vec<Box<dyn DocumentLoader>> = vec![
+        Box::new(MarkdownLoader),
+        Box::new(HtmlLoader),
+        Box::new(TextLoader),
+    ];
+
+    loaders
+        .into_iter()
+        .find(|loader| loader.supports(path))
+        .ok_or_else(|| {
+            BedrockError::ConfigError(format!(
+                "No document loader available for {}",
+                path.display()
+            ))
+        })
+}
+
+pub fn chunk_text(text: &str, chunk_size: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let chunk_size = chunk_size.max(1);
+    let overlap = overlap.min(chunk_size - 1);
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = (start + chunk_size).min(chars.len());
+
+        // Prefer to break on whitespace so words aren't split between chunks.
+        if end < chars.len() {
+            if let Some(offset) = chars[start..end].iter().rposition(|c| c.is_whitespace()) {
+                if offset > overlap {
+                    end = start + offset;
+                }
+            }
+        }
+
+        let chunk: String = chars[start..end].iter().collect();
+        let chunk = chunk.trim();
+        if !chunk.is_empty() {
+            chunks.push(chunk.to_string());
+        }
+
+        if end == chars.len() {
+            break;
+        }
+        start = end - overlap.min(end - start - 1);
+    }
+
+    chunks
+}
+
+pub(crate) fn read_to_string(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).map_err(|e| {
+        BedrockError::InternalError(format!("Failed to read {}: {}", path.display(), e))
+    })
+}
+
+pub(crate) fn base_metadata(path: &Path, format: &str) -> HashMap<String, serde_json::Value> {
+    HashMap::from([
+        ("source".to_string(), json!(path.display().to_string())),
+        ("format".to_string(), json!(format)),
+    ])
+}
+
+pub(crate) fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_text_respects_size_and_overlap() {
+        let text = "one two three four five six seven eight nine ten";
+        let chunks = chunk_text(text, 15, 4);
+
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 15));
+        assert_eq!(chunks.first().map(String::as_str), Some("one two three"));
+        assert!(chunks.last().unwrap().ends_with("ten"));
+    }
+
+    #[test]
+    fn test_strip_markdown_and_html() {
+        let markdown = "# Title\n\nSome **bold** and [a link](https://example.com).\n- item";
+        assert_eq!(
+            MarkdownLoader::strip_formatting(markdown),
+            "Title\n\nSome bold and a link.\nitem"
+        );
+
+        let html =
+            "<html><head><style>p {}</style></head><body><p>Fish &amp; chips</p></body></html>";
+        assert_eq!(HtmlLoader::extract_text(html), "Fish & chips");
+    }
+
+    #[test]
+    fn test_loader_for_path() {
+        assert!(loader_for_path(Path::new("notes.md")).is_ok());
+        assert!(loader_for_path(Path::new("page.HTML")).is_ok());
+        assert!(loader_for_path(Path::new("readme.txt")).is_ok());
+        assert!(loader_for_path(Path::new("image.png")).is_err());
+    }
+
+    #[test]
+    fn test_strip_markdown_keeps_unpaired_delimiters() {
+        let markdown =
+            "Call `snake_case_name` with snake_case_name, _emphasis_ and 2 * 3 = 6 ~~old~~";
+        assert_eq!(
+            MarkdownLoader::strip_formatting(markdown),
+            "Call snake_case_name with snake_case_name, emphasis and 2 * 3 = 6 old"
+        );
+        assert_eq!(MarkdownLoader::strip_formatting("**a** * b *c*"), "a * b c");
+    }
+}
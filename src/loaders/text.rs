@@ -0,0 +1,21 @@
+use crate::{
+    error::Result,
+    loaders::{base_metadata, has_extension, read_to_string, DocumentLoader},
+    models::document::LoadedDocument,
+};
+use std::path::Path;
+
+pub struct TextLoader;
+
+impl DocumentLoader for TextLoader {
+    fn supports(&self, path: &Path) -> bool {
+        has_extension(path, &["txt", "text", "log", "csv"])
+    }
+
+    fn load(&self, path: &Path) -> Result<Vec<LoadedDocument>> {
+        Ok(vec![LoadedDocument {
+            text: read_to_string(path)?,
+            metadata: base_metadata(path, "text"),
+        }])
+    }
+}
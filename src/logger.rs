@@ -3,19 +3,25 @@ use colored::*;
 use log::{Level, Metadata, Record};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs::{File, OpenOptions};
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fs::{self, File, OpenOptions};
 use std::io::{self, Write};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-static BEAUTIFUL_LOGGER: Lazy<BeautifulLogger> = Lazy::new(|| BeautifulLogger::new());
+static BEAUTIFUL_LOGGER: Lazy<BeautifulLogger> = Lazy::new(BeautifulLogger::new);
 
 pub fn init() -> Result<(), String> {
     init_with_config(LoggerConfig::default())
 }
 
+pub fn init_from_env() -> Result<(), String> {
+    init_with_config(LoggerConfig::from_env())
+}
+
 pub fn init_with_config(config: LoggerConfig) -> Result<(), String> {
     BEAUTIFUL_LOGGER.update_config(config.clone());
 
@@ -23,7 +29,7 @@ pub fn init_with_config(config: LoggerConfig) -> Result<(), String> {
         return Err(format!("Failed to set logger: {:?}", e));
     }
 
-    log::set_max_level(config.min_level.to_log_level_filter());
+    log::set_max_level(config.max_level_filter());
     Ok(())
 }
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -34,6 +40,7 @@ pub enum LogLevel {
     Warn = 3,
     Error = 4,
     Fatal = 5,
+    Off = 6,
 }
 
 impl LogLevel {
@@ -45,6 +52,7 @@ impl LogLevel {
             LogLevel::Warn => Color::Yellow,
             LogLevel::Error => Color::Red,
             LogLevel::Fatal => Color::Magenta,
+            LogLevel::Off => Color::White,
         }
     }
     pub fn emoji(&self) -> &'static str {
@@ -55,6 +63,7 @@ impl LogLevel {
             LogLevel::Warn => "⚠️",
             LogLevel::Error => "❌",
             LogLevel::Fatal => "💀",
+            LogLevel::Off => "🔇",
         }
     }
     pub fn as_str(&self) -> &'static str {
@@ -65,6 +74,7 @@ impl LogLevel {
             LogLevel::Warn => "WARN",
             LogLevel::Error => "ERROR",
             LogLevel::Fatal => "FATAL",
+            LogLevel::Off => "OFF",
         }
     }
     pub fn to_log_level(&self) -> Level {
@@ -74,7 +84,7 @@ impl LogLevel {
             LogLevel::Info => Level::Info,
             LogLevel::Warn => Level::Warn,
             LogLevel::Error => Level::Error,
-            LogLevel::Fatal => Level::Error,
+            LogLevel::Fatal | LogLevel::Off => Level::Error,
         }
     }
     pub fn to_log_level_filter(&self) -> log::LevelFilter {
@@ -85,6 +95,20 @@ impl LogLevel {
             LogLevel::Warn => log::LevelFilter::Warn,
             LogLevel::Error => log::LevelFilter::Error,
             LogLevel::Fatal => log::LevelFilter::Error,
+            LogLevel::Off => log::LevelFilter::Off,
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "trace" => Some(LogLevel::Trace),
+            "debug" => Some(LogLevel::Debug),
+            "info" => Some(LogLevel::Info),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "error" => Some(LogLevel::Error),
+            "fatal" => Some(LogLevel::Fatal),
+            "off" | "none" => Some(LogLevel::Off),
+            _ => None,
         }
     }
 
@@ -165,6 +189,11 @@ pub struct LoggerConfig {
     pub max_file_size_mb: u64,
     pub enable_performance_tracking: bool,
     pub custom_prefix: Option<String>,
+    // Per-module overrides keyed by target prefix, e.g. "rgenai::storage".
+    pub module_levels: HashMap<String, LogLevel>,
+    pub log_to_console: bool,
+    // Extra destinations that receive every entry after the console and file output.
+    pub sinks: Vec<Arc<dyn LogSink>>,
 }
 
 impl Default for LoggerConfig {
@@ -184,6 +213,9 @@ impl Default for LoggerConfig {
             max_file_size_mb: 100,
             enable_performance_tracking: true,
             custom_prefix: None,
+            module_levels: HashMap::new(),
+            log_to_console: true,
+            sinks: Vec::new(),
         }
     }
 }
@@ -214,6 +246,86 @@ impl LoggerConfig {
         self
     }
 
+    pub fn with_module_level(mut self, module: &str, level: LogLevel) -> Self {
+        self.module_levels.insert(module.to_string(), level);
+        self
+    }
+
+    pub fn with_console_output(mut self, enabled: bool) -> Self {
+        self.log_to_console = enabled;
+        self
+    }
+
+    pub fn with_sink(mut self, sink: impl LogSink + 'static) -> Self {
+        self.sinks.push(Arc::new(sink));
+        self
+    }
+
+    // Reads RGEN_LOG (falling back to RUST_LOG) as `level,module=level,...`,
+    // plus RGEN_LOG_JSON, RGEN_LOG_FILE, RGEN_LOG_COLORS and RGEN_LOG_EMOJIS.
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+
+        if let Ok(spec) = env::var("RGEN_LOG").or_else(|_| env::var("RUST_LOG")) {
+            config.apply_filter_spec(&spec);
+        }
+        if let Some(enabled) = env_flag("RGEN_LOG_JSON") {
+            config.output_json = enabled;
+        }
+        if let Some(enabled) = env_flag("RGEN_LOG_COLORS") {
+            config.show_colors = enabled;
+        }
+        if let Some(enabled) = env_flag("RGEN_LOG_EMOJIS") {
+            config.show_emojis = enabled;
+        }
+        if let Ok(path) = env::var("RGEN_LOG_FILE") {
+            if !path.is_empty() {
+                config = config.with_file_output(&path);
+            }
+        }
+
+        config
+    }
+
+    pub fn apply_filter_spec(&mut self, spec: &str) {
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    if let Some(level) = LogLevel::parse(level) {
+                        self.module_levels.insert(module.trim().to_string(), level);
+                    }
+                }
+                None => {
+                    if let Some(level) = LogLevel::parse(directive) {
+                        self.min_level = level;
+                    }
+                }
+            }
+        }
+    }
+
+    // The most specific module prefix wins over the global level.
+    pub fn level_for(&self, target: &str) -> LogLevel {
+        self.module_levels
+            .iter()
+            .filter(|(module, _)| {
+                target == module.as_str()
+                    || target
+                        .strip_prefix(module.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.min_level)
+    }
+
+    pub fn max_level_filter(&self) -> log::LevelFilter {
+        self.module_levels
+            .values()
+            .map(LogLevel::to_log_level_filter)
+            .fold(self.min_level.to_log_level_filter(), std::cmp::max)
+    }
+
     pub fn production() -> Self {
         Self {
             min_level: LogLevel::Info,
@@ -237,131 +349,253 @@ impl LoggerConfig {
     }
 }
 
-pub struct BeautifulLogger {
-    config: Arc<Mutex<LoggerConfig>>,
-    log_file: Arc<Mutex<Option<File>>>,
+fn env_flag(name: &str) -> Option<bool> {
+    env::var(name)
+        .ok()
+        .map(|val| matches!(val.to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
 }
 
-impl BeautifulLogger {
-    pub fn new() -> Self {
+pub trait LogSink: Send + Sync + std::fmt::Debug {
+    fn write(&self, entry: &LogEntry);
+
+    fn flush(&self) {}
+}
+
+// One JSON object per line. Once the file would exceed `max_bytes` it is
+// renamed to `<path>.1` (older files shift up) and a fresh file is started.
+#[derive(Debug)]
+pub struct JsonlFileSink {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: Mutex<Option<(File, u64)>>,
+}
+
+impl JsonlFileSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
         Self {
-            config: Arc::new(Mutex::new(LoggerConfig::default())),
-            log_file: Arc::new(Mutex::new(None)),
+            path: path.into(),
+            max_bytes: 100 * 1024 * 1024,
+            max_files: 5,
+            file: Mutex::new(None),
         }
     }
 
-    pub fn update_config(&self, new_config: LoggerConfig) {
-        let mut config = self.config.lock().unwrap();
-        *config = new_config.clone();
-        if new_config.log_to_file {
-            if let Ok(file) = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&new_config.log_file_path)
-            {
-                let mut log_file = self.log_file.lock().unwrap();
-                *log_file = Some(file);
-            }
-        }
+    pub fn with_rotation(mut self, max_bytes: u64, max_files: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self.max_files = max_files;
+        self
     }
 
-    fn format_console_output(&self, entry: &LogEntry, config: &LoggerConfig) -> String {
-        let mut output = String::new();
-        if let Some(prefix) = &config.custom_prefix {
-            output.push_str(&format!("[{}] ", prefix.bright_white().bold()));
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        if self.max_files == 0 {
+            return fs::remove_file(&self.path);
         }
-        if config.include_timestamp {
-            let timestamp = entry.timestamp.format(&config.timestamp_format);
-            if config.show_colors {
-                output.push_str(&format!("{} ", timestamp.to_string().bright_black()));
-            } else {
-                output.push_str(&format!("{} ", timestamp));
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(index + 1))?;
             }
         }
-        let level_str = if config.show_emojis {
-            format!("{} {}", entry.level.emoji(), entry.level.as_str())
-        } else {
-            entry.level.as_str().to_string()
+        fs::rename(&self.path, self.rotated_path(1))
+    }
+
+    fn open(&self) -> io::Result<(File, u64)> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let size = file.metadata()?.len();
+        Ok((file, size))
+    }
+}
+
+impl LogSink for JsonlFileSink {
+    fn write(&self, entry: &LogEntry) {
+        let Ok(mut line) = serde_json::to_string(entry) else {
+            return;
         };
+        line.push('\n');
 
-        if config.show_colors {
-            output.push_str(&format!(
-                "[{}] ",
-                level_str.color(entry.level.color()).bold()
-            ));
-        } else {
-            output.push_str(&format!("[{}] ", level_str));
+        let mut guard = self.file.lock().unwrap();
+        if guard.is_none() {
+            *guard = self.open().ok();
         }
-        if config.show_module && !entry.module.is_empty() {
-            if config.show_colors {
-                output.push_str(&format!("{}::", entry.module.bright_blue()));
-            } else {
-                output.push_str(&format!("{}::", entry.module));
+        let needs_rotation = guard
+            .as_ref()
+            .is_some_and(|(_, size)| *size > 0 && size + line.len() as u64 > self.max_bytes);
+        if needs_rotation {
+            *guard = None;
+            if self.rotate().is_ok() {
+                *guard = self.open().ok();
             }
         }
-        if config.show_colors {
-            output.push_str(&entry.message.white().bold().to_string());
-        } else {
-            output.push_str(&entry.message);
-        }
-        if !entry.context.is_empty() {
-            output.push_str(" ");
-            if config.show_colors {
-                output.push_str(&format!(
-                    "{}",
-                    serde_json::to_string(&entry.context)
-                        .unwrap_or_default()
-                        .bright_cyan()
-                ));
-            } else {
-                output.push_str(&serde_json::to_string(&entry.context).unwrap_or_default());
+
+        if let Some((file, size)) = guard.as_mut() {
+            if file.write_all(line.as_bytes()).is_ok() {
+                *size += line.len() as u64;
             }
         }
-        if let Some(request_id) = &entry.request_id {
-            if config.show_colors {
-                output.push_str(&format!(" [req:{}]", request_id.bright_yellow()));
-            } else {
-                output.push_str(&format!(" [req:{}]", request_id));
-            }
+    }
+
+    fn flush(&self) {
+        if let Some((file, _)) = self.file.lock().unwrap().as_mut() {
+            let _ = file.flush();
         }
-        if let Some(duration) = entry.duration_ms {
-            if config.show_colors {
-                output.push_str(&format!(" [{}ms]", duration.to_string().bright_magenta()));
-            } else {
-                output.push_str(&format!(" [{}ms]", duration));
-            }
+    }
+}
+
+// Keeps the most recent `capacity` entries, e.g. for an admin UI.
+#[derive(Debug)]
+pub struct MemorySink {
+    capacity: usize,
+    entries: Mutex<VecDeque<LogEntry>>,
+}
+
+impl MemorySink {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
         }
-        if config.show_thread_id {
-            if config.show_colors {
-                output.push_str(&format!(" [thread:{}]", entry.thread_id.bright_black()));
-            } else {
-                output.push_str(&format!(" [thread:{}]", entry.thread_id));
-            }
+    }
+
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl LogSink for MemorySink {
+    fn write(&self, entry: &LogEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.capacity {
+            entries.pop_front();
         }
-        if config.show_file_location {
-            let location = format!("{}:{}", entry.file, entry.line);
-            if config.show_colors {
-                output.push_str(&format!(" ({})", location.bright_black()));
-            } else {
-                output.push_str(&format!(" ({})", location));
-            }
+        if self.capacity > 0 {
+            entries.push_back(entry.clone());
         }
+    }
+}
 
-        output
+// Lets a sink be registered while the caller keeps a handle to read it back.
+impl<T: LogSink> LogSink for Arc<T> {
+    fn write(&self, entry: &LogEntry) {
+        (**self).write(entry);
     }
 
-    fn write_to_file(&self, entry: &LogEntry, config: &LoggerConfig) {
-        if let Ok(mut log_file_guard) = self.log_file.lock() {
-            if let Some(ref mut file) = *log_file_guard {
-                let content = if config.output_json {
-                    serde_json::to_string(entry).unwrap_or_default() + "\n"
-                } else {
-                    self.format_console_output(entry, config) + "\n"
-                };
-                let _ = file.write_all(content.as_bytes());
-                let _ = file.flush();
+    fn flush(&self) {
+        (**self).flush();
+    }
+}
+
+// Console and plain-file output. Built from the config flags so they go
+// through the same path as registered sinks.
+#[derive(Debug)]
+struct ConsoleSink {
+    format: LoggerConfig,
+}
+
+impl LogSink for ConsoleSink {
+    fn write(&self, entry: &LogEntry) {
+        println!("{}", render_entry(entry, &self.format));
+    }
+
+    fn flush(&self) {
+        let _ = io::stdout().flush();
+    }
+}
+
+#[derive(Debug)]
+struct FileSink {
+    format: LoggerConfig,
+    file: Mutex<File>,
+}
+
+impl LogSink for FileSink {
+    fn write(&self, entry: &LogEntry) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_all((render_entry(entry, &self.format) + "\n").as_bytes());
+            let _ = file.flush();
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+fn render_entry(entry: &LogEntry, config: &LoggerConfig) -> String {
+    if config.output_json {
+        serde_json::to_string(entry).unwrap_or_default()
+    } else {
+        format_console_output(entry, config)
+    }
+}
+
+#[derive(Debug)]
+pub struct BeautifulLogger {
+    config: Arc<Mutex<LoggerConfig>>,
+    // Console and file first, then `LoggerConfig::sinks`. Cloned out before
+    // writing so a sink that logs does not re-enter a held lock.
+    sinks: Arc<Mutex<Vec<Arc<dyn LogSink>>>>,
+}
+
+impl Default for BeautifulLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BeautifulLogger {
+    pub fn new() -> Self {
+        Self {
+            config: Arc::new(Mutex::new(LoggerConfig::default())),
+            sinks: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn update_config(&self, new_config: LoggerConfig) {
+        let format = LoggerConfig {
+            sinks: Vec::new(),
+            ..new_config.clone()
+        };
+        let mut sinks: Vec<Arc<dyn LogSink>> = Vec::new();
+        if new_config.log_to_console {
+            sinks.push(Arc::new(ConsoleSink {
+                format: format.clone(),
+            }));
+        }
+        if new_config.log_to_file {
+            if let Ok(file) = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&new_config.log_file_path)
+            {
+                sinks.push(Arc::new(FileSink {
+                    format,
+                    file: Mutex::new(file),
+                }));
             }
         }
+        sinks.extend(new_config.sinks.iter().cloned());
+
+        *self.config.lock().unwrap() = new_config;
+        *self.sinks.lock().unwrap() = sinks;
+    }
+
+    fn current_sinks(&self) -> Vec<Arc<dyn LogSink>> {
+        self.sinks
+            .lock()
+            .map(|sinks| sinks.clone())
+            .unwrap_or_default()
     }
 
     fn create_log_entry(&self, record: &Record) -> LogEntry {
@@ -375,10 +609,95 @@ impl BeautifulLogger {
     }
 }
 
+fn format_console_output(entry: &LogEntry, config: &LoggerConfig) -> String {
+    let mut output = String::new();
+    if let Some(prefix) = &config.custom_prefix {
+        output.push_str(&format!("[{}] ", prefix.bright_white().bold()));
+    }
+    if config.include_timestamp {
+        let timestamp = entry.timestamp.format(&config.timestamp_format);
+        if config.show_colors {
+            output.push_str(&format!("{} ", timestamp.to_string().bright_black()));
+        } else {
+            output.push_str(&format!("{} ", timestamp));
+        }
+    }
+    let level_str = if config.show_emojis {
+        format!("{} {}", entry.level.emoji(), entry.level.as_str())
+    } else {
+        entry.level.as_str().to_string()
+    };
+
+    if config.show_colors {
+        output.push_str(&format!(
+            "[{}] ",
+            level_str.color(entry.level.color()).bold()
+        ));
+    } else {
+        output.push_str(&format!("[{}] ", level_str));
+    }
+    if config.show_module && !entry.module.is_empty() {
+        if config.show_colors {
+            output.push_str(&format!("{}::", entry.module.bright_blue()));
+        } else {
+            output.push_str(&format!("{}::", entry.module));
+        }
+    }
+    if config.show_colors {
+        output.push_str(&entry.message.white().bold().to_string());
+    } else {
+        output.push_str(&entry.message);
+    }
+    if !entry.context.is_empty() {
+        output.push(' ');
+        if config.show_colors {
+            output.push_str(&format!(
+                "{}",
+                serde_json::to_string(&entry.context)
+                    .unwrap_or_default()
+                    .bright_cyan()
+            ));
+        } else {
+            output.push_str(&serde_json::to_string(&entry.context).unwrap_or_default());
+        }
+    }
+    if let Some(request_id) = &entry.request_id {
+        if config.show_colors {
+            output.push_str(&format!(" [req:{}]", request_id.bright_yellow()));
+        } else {
+            output.push_str(&format!(" [req:{}]", request_id));
+        }
+    }
+    if let Some(duration) = entry.duration_ms {
+        if config.show_colors {
+            output.push_str(&format!(" [{}ms]", duration.to_string().bright_magenta()));
+        } else {
+            output.push_str(&format!(" [{}ms]", duration));
+        }
+    }
+    if config.show_thread_id {
+        if config.show_colors {
+            output.push_str(&format!(" [thread:{}]", entry.thread_id.bright_black()));
+        } else {
+            output.push_str(&format!(" [thread:{}]", entry.thread_id));
+        }
+    }
+    if config.show_file_location {
+        let location = format!("{}:{}", entry.file, entry.line);
+        if config.show_colors {
+            output.push_str(&format!(" ({})", location.bright_black()));
+        } else {
+            output.push_str(&format!(" ({})", location));
+        }
+    }
+
+    output
+}
+
 impl log::Log for BeautifulLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
         if let Ok(config) = self.config.lock() {
-            metadata.level() <= config.min_level.to_log_level()
+            metadata.level() <= config.level_for(metadata.target()).to_log_level_filter()
         } else {
             true
         }
@@ -388,26 +707,14 @@ impl log::Log for BeautifulLogger {
         if self.enabled(record.metadata()) {
             let entry = self.create_log_entry(record);
 
-            if let Ok(config) = self.config.lock() {
-                if config.output_json {
-                    println!("{}", serde_json::to_string(&entry).unwrap_or_default());
-                } else {
-                    println!("{}", self.format_console_output(&entry, &config));
-                }
-                if config.log_to_file {
-                    self.write_to_file(&entry, &config);
-                }
+            for sink in self.current_sinks() {
+                sink.write(&entry);
             }
         }
     }
 
     fn flush(&self) {
-        let _ = io::stdout().flush();
-        if let Ok(mut log_file_guard) = self.log_file.lock() {
-            if let Some(ref mut file) = *log_file_guard {
-                let _ = file.flush();
-            }
-        }
+        self.current_sinks().iter().for_each(|sink| sink.flush());
     }
 }
 unsafe impl Sync for BeautifulLogger {}
@@ -526,9 +833,127 @@ mod tests {
         assert!(prod_config.output_json);
     }
 
+    #[test]
+    fn test_off_level_disables_everything() {
+        use log::Log;
+
+        let logger = BeautifulLogger::new();
+        logger.update_config(LoggerConfig::new().with_level(LogLevel::Off));
+        let metadata = Metadata::builder().level(Level::Error).build();
+        assert!(!logger.enabled(&metadata));
+        assert_eq!(LogLevel::Off.to_log_level_filter(), log::LevelFilter::Off);
+    }
+
+    #[test]
+    fn test_filter_spec_sets_global_and_module_levels() {
+        let mut config = LoggerConfig::new();
+        config.apply_filter_spec("warn, rgenai::storage=debug,rgenai::storage::pinecone=off");
+
+        assert_eq!(config.min_level, LogLevel::Warn);
+        assert_eq!(config.level_for("rgenai::bedrock"), LogLevel::Warn);
+        assert_eq!(
+            config.level_for("rgenai::storage::upstash"),
+            LogLevel::Debug
+        );
+        assert_eq!(config.level_for("rgenai::storage::pinecone"), LogLevel::Off);
+        assert_eq!(config.level_for("rgenai::storagex"), LogLevel::Warn);
+        assert_eq!(config.max_level_filter(), log::LevelFilter::Debug);
+    }
+
+    #[test]
+    fn test_sinks_receive_entries() {
+        use log::Log;
+
+        let memory = Arc::new(MemorySink::new(2));
+        let path = env::temp_dir().join(format!("rgenai-sink-{}.jsonl", Uuid::new_v4()));
+        let logger = BeautifulLogger::new();
+        logger.update_config(
+            LoggerConfig::new()
+                .with_console_output(false)
+                .with_sink(memory.clone())
+                .with_sink(JsonlFileSink::new(&path).with_rotation(1, 1)),
+        );
+
+        for message in ["first", "second", "third"] {
+            logger.log(
+                &Record::builder()
+                    .level(Level::Info)
+                    .args(format_args!("{}", message))
+                    .build(),
+            );
+        }
+        logger.flush();
+
+        let messages: Vec<String> = memory.entries().into_iter().map(|e| e.message).collect();
+        assert_eq!(messages, vec!["second", "third"]);
+
+        let current: LogEntry =
+            serde_json::from_str(fs::read_to_string(&path).unwrap().trim()).unwrap();
+        assert_eq!(current.message, "third");
+        let rotated = PathBuf::from(format!("{}.1", path.display()));
+        assert!(fs::read_to_string(&rotated).unwrap().contains("second"));
+
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&rotated);
+    }
+
     #[test]
     fn test_logger_initialization() {
         let config = LoggerConfig::development();
         assert!(init_with_config(config).is_ok());
     }
+
+    #[test]
+    fn test_sink_can_log_while_writing() {
+        use log::Log;
+        use std::sync::OnceLock;
+
+        #[derive(Debug, Default)]
+        struct Reentrant {
+            logger: OnceLock<Arc<BeautifulLogger>>,
+            seen: Mutex<Vec<String>>,
+        }
+
+        impl LogSink for Reentrant {
+            fn write(&self, entry: &LogEntry) {
+                let first = {
+                    let mut seen = self.seen.lock().unwrap();
+                    seen.push(entry.message.clone());
+                    seen.len() == 1
+                };
+                if first {
+                    self.logger.get().unwrap().log(
+                        &Record::builder()
+                            .level(Level::Info)
+                            .args(format_args!("from sink"))
+                            .build(),
+                    );
+                }
+            }
+        }
+
+        let sink = Arc::new(Reentrant::default());
+        let logger = Arc::new(BeautifulLogger::new());
+        sink.logger.set(logger.clone()).unwrap();
+        logger.update_config(
+            LoggerConfig::new()
+                .with_console_output(false)
+                .with_sink(sink.clone()),
+        );
+
+        let (done, finished) = std::sync::mpsc::channel();
+        let writer = logger.clone();
+        std::thread::spawn(move || {
+            writer.log(
+                &Record::builder()
+                    .level(Level::Info)
+                    .args(format_args!("outer"))
+                    .build(),
+            );
+            let _ = done.send(());
+        });
+
+        assert!(finished.recv_timeout(Duration::from_secs(5)).is_ok());
+        assert_eq!(*sink.seen.lock().unwrap(), vec!["outer", "from sink"]);
+    }
 }
@@ -1,4 +1,7 @@
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use super::text::TextGenerationResponse;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelInfo {
@@ -17,7 +20,7 @@ pub enum ModelCategory {
     Image,
     Embedding,
 }
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum ModelProvider {
     Amazon,
@@ -27,9 +30,222 @@ pub enum ModelProvider {
     Meta,
     Mistral,
 }
+
+impl ModelProvider {
+    // Reads the provider from a model id, a cross-region inference profile id
+    // such as `us.anthropic.claude-3-haiku-20240307-v1:0`, or a foundation
+    // model ARN. None for ids it doesn't recognise, including application
+    // inference profile ARNs, which don't name their model.
+    pub fn from_model_id(model_id: &str) -> Option<Self> {
+        let id = model_id.rsplit('/').next().unwrap_or(model_id);
+        let id = match id.split_once('.') {
+            Some(("us" | "eu" | "apac" | "us-gov", rest)) => rest,
+            _ => id,
+        };
+        let provider = match id.split('.').next()? {
+            "amazon" => Self::Amazon,
+            "anthropic" => Self::Anthropic,
+            "cohere" => Self::Cohere,
+            "ai21" => Self::AI21,
+            "meta" => Self::Meta,
+            "mistral" => Self::Mistral,
+            _ => return None,
+        };
+        Some(provider)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SamplingDefaults {
+    pub top_p: Option<f32>,
+    pub top_k: Option<i32>,
+}
+
+impl SamplingDefaults {
+    pub fn for_provider(provider: &ModelProvider) -> Self {
+        match provider {
+            ModelProvider::Anthropic => Self::default(),
+            _ => Self {
+                top_p: Some(0.9),
+                top_k: None,
+            },
+        }
+    }
+}
+// Finish reason of the empty marker chunk emitted when a stream is resumed
+// after a transient failure.
+pub const STREAM_RESUMED_REASON: &str = "resumed";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamChunk {
     pub chunk: String,
     pub done: bool,
     pub finish_reason: Option<String>,
+    #[serde(default)]
+    pub tokens_prompt: Option<i32>,
+    #[serde(default)]
+    pub tokens_generated: Option<i32>,
+}
+
+// Item of `TextClient::generate_stream_events`. A `Usage` event follows each
+// chunk that moved the running output token count, e.g. Claude's
+// `message_delta`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamEvent {
+    Text(StreamChunk),
+    Usage {
+        input_tokens: Option<i32>,
+        output_tokens_so_far: i32,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StreamSummary {
+    pub text: String,
+    pub finish_reason: Option<String>,
+    pub tokens_prompt: Option<i32>,
+    pub tokens_generated: Option<i32>,
+    pub chunk_count: usize,
+    pub duration: Duration,
+}
+
+impl StreamSummary {
+    pub(crate) fn record(&mut self, chunk: &StreamChunk) {
+        if !chunk.chunk.is_empty() {
+            self.text.push_str(&chunk.chunk);
+            self.chunk_count += 1;
+        }
+        if self.finish_reason.is_none()
+            && chunk.finish_reason.as_deref() != Some(STREAM_RESUMED_REASON)
+        {
+            self.finish_reason = chunk.finish_reason.clone();
+        }
+        // Providers report counts cumulatively, so the latest value wins.
+        if chunk.tokens_prompt.is_some() {
+            self.tokens_prompt = chunk.tokens_prompt;
+        }
+        if chunk.tokens_generated.is_some() {
+            self.tokens_generated = chunk.tokens_generated;
+        }
+    }
+}
+
+// Assembles a stream's chunks into the response a non-streaming call would
+// have returned. Token counts are zero when the provider reported none.
+#[derive(Debug, Clone)]
+pub struct StreamAccumulator {
+    model: String,
+    summary: StreamSummary,
+    done: bool,
+}
+
+impl StreamAccumulator {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            summary: StreamSummary::default(),
+            done: false,
+        }
+    }
+
+    pub fn push(&mut self, chunk: &StreamChunk) {
+        self.summary.record(chunk);
+        self.done |= chunk.done;
+    }
+
+    pub fn text(&self) -> &str {
+        &self.summary.text
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.summary.chunk_count
+    }
+
+    pub fn finish_reason(&self) -> Option<&str> {
+        self.summary.finish_reason.as_deref()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn into_response(self) -> TextGenerationResponse {
+        TextGenerationResponse {
+            text: self.summary.text,
+            model: self.model,
+            tokens_generated: self.summary.tokens_generated.unwrap_or(0),
+            tokens_prompt: self.summary.tokens_prompt.unwrap_or(0),
+            finish_reason: self.summary.finish_reason,
+            candidates: None,
+            request_id: None,
+            trace: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_accumulator_assembles_response() {
+        let chunk = |text: &str, finish_reason: Option<&str>, tokens: Option<i32>| StreamChunk {
+            chunk: text.to_string(),
+            done: finish_reason.is_some_and(|reason| reason != STREAM_RESUMED_REASON),
+            finish_reason: finish_reason.map(String::from),
+            tokens_prompt: tokens.map(|_| 5),
+            tokens_generated: tokens,
+        };
+        let mut accumulator = StreamAccumulator::new("amazon.titan-text-express-v1");
+        accumulator.push(&chunk("Hello", None, None));
+        accumulator.push(&chunk("", Some(STREAM_RESUMED_REASON), None));
+        assert!(!accumulator.is_done());
+        accumulator.push(&chunk(" world", Some("FINISH"), Some(3)));
+
+        assert!(accumulator.is_done());
+        assert_eq!(accumulator.chunk_count(), 2);
+        let response = accumulator.into_response();
+        assert_eq!(response.text, "Hello world");
+        assert_eq!(response.finish_reason.as_deref(), Some("FINISH"));
+        assert_eq!((response.tokens_prompt, response.tokens_generated), (5, 3));
+        assert_eq!(response.model, "amazon.titan-text-express-v1");
+    }
+
+    #[test]
+    fn test_provider_from_model_id() {
+        let cases = [
+            ("amazon.titan-text-express-v1", Some(ModelProvider::Amazon)),
+            ("anthropic.claude-v2", Some(ModelProvider::Anthropic)),
+            (
+                "us.anthropic.claude-3-haiku-20240307-v1:0",
+                Some(ModelProvider::Anthropic),
+            ),
+            (
+                "eu.meta.llama3-2-1b-instruct-v1:0",
+                Some(ModelProvider::Meta),
+            ),
+            (
+                "mistral.mistral-large-2402-v1:0",
+                Some(ModelProvider::Mistral),
+            ),
+            (
+                "arn:aws:bedrock:us-east-1::foundation-model/cohere.command-text-v14",
+                Some(ModelProvider::Cohere),
+            ),
+            (
+                "arn:aws:bedrock:us-east-1:123456789012:application-inference-profile/abc123",
+                None,
+            ),
+            ("gpt-4", None),
+        ];
+        for (model_id, expected) in cases {
+            assert_eq!(
+                ModelProvider::from_model_id(model_id),
+                expected,
+                "{}",
+                model_id
+            );
+        }
+    }
 }
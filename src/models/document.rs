@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadedDocument {
+    pub text: String,
+    pub metadata: HashMap<String, serde_json::Value>,
+}
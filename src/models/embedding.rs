@@ -1,9 +1,76 @@
 use serde::{Deserialize, Serialize};
 
+use crate::models::{ModelCategory, ModelRegistry};
+
+// What `VectorClient::generate_embedding` does with input over the model's
+// token limit. Left alone, the service would truncate it or reject it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LongInputStrategy {
+    #[default]
+    Error,
+    // Embed each chunk separately and average the vectors.
+    ChunkAndAverage,
+    // Cut the input to the limit client-side with `truncate_to_model_limit`.
+    Truncate,
+}
+
+// Rough token count, about four characters per token for English text.
+pub(crate) fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+// Cuts `text` to the model's input limit as counted by `estimate_tokens`,
+// preferring a word boundary, and reports whether anything was removed.
+// Unknown models have no limit and are returned whole.
+pub fn truncate_to_model_limit<'a>(text: &'a str, model_id: &str) -> (&'a str, bool) {
+    let Some(limit) = embedding_input_limit(model_id) else {
+        return (text, false);
+    };
+    let Some((cut, _)) = text.char_indices().nth(limit * 4) else {
+        return (text, false);
+    };
+
+    let head = &text[..cut];
+    let end = head
+        .rfind(char::is_whitespace)
+        .filter(|&end| end > cut / 2)
+        .unwrap_or(cut);
+    (head[..end].trim_end(), true)
+}
+
+// Input token limit of a known embedding model.
+pub fn embedding_input_limit(model_id: &str) -> Option<usize> {
+    ModelRegistry::find(model_id)
+        .filter(|model| model.category == ModelCategory::Embedding && model.max_tokens > 0)
+        .map(|model| model.max_tokens)
+}
+
+// Cohere embeds documents and queries differently, so search quality
+// depends on tagging each side. Titan ignores it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingInputType {
+    #[default]
+    SearchDocument,
+    SearchQuery,
+}
+
+impl EmbeddingInputType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmbeddingInputType::SearchDocument => "search_document",
+            EmbeddingInputType::SearchQuery => "search_query",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct EmbeddingRequest {
     pub text: String,
     pub model_id: Option<String>,
+    #[serde(default)]
+    pub input_type: EmbeddingInputType,
 }
 
 #[derive(Debug, Serialize)]
@@ -27,3 +94,22 @@ pub struct CohereEmbeddingRequest {
 pub struct CohereEmbeddingResponse {
     pub embeddings: Vec<Vec<f32>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncation_fits_the_model_limit() {
+        let text = "token ".repeat(1000);
+        let (truncated, cut) = truncate_to_model_limit(&text, "cohere.embed-english-v3");
+        assert!(cut);
+        assert!(estimate_tokens(truncated) <= 512);
+        assert!(truncated.ends_with("token"));
+
+        assert_eq!(
+            truncate_to_model_limit("short", "cohere.embed-english-v3"),
+            ("short", false)
+        );
+    }
+}
@@ -1,21 +1,198 @@
+use crate::error::{BedrockError, Result};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct ImageGenerationRequest {
     pub prompt: String,
     pub model_id: Option<String>,
     pub width: Option<u32>,
     pub height: Option<u32>,
     pub num_images: Option<u32>,
+    // Base64 encoded reference image for Titan v2 conditioned generation.
+    #[serde(default)]
+    pub condition_image: Option<String>,
+    #[serde(default)]
+    pub control_mode: Option<ImageControlMode>,
+    #[serde(default)]
+    pub control_strength: Option<f32>,
+    // Prompt adherence; both Titan and Stability default to 8.0.
+    #[serde(default)]
+    pub cfg_scale: Option<f32>,
+    // Diffusion steps, Stability models only.
+    #[serde(default)]
+    pub steps: Option<u32>,
+    // Fixed seed for reproducible output. Titan accepts 0 to 2147483646,
+    // Stability 0 to 4294967295; unset picks a random seed per call.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub output_format: ImageFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+    // Base64 images wrapped in the model's JSON response.
+    #[default]
+    Json,
+    // Raw PNG bytes, for models that can return them directly.
+    Png,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImageControlMode {
+    CannyEdge,
+    Segmentation,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedImage {
+    // Base64 encoded.
+    pub data: String,
+    // Seed that produced this image; Stability reports one per artifact, so
+    // passing it back as the request seed reproduces this candidate.
+    pub seed: Option<u64>,
+    pub finish_reason: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ImageGenerationResponse {
-    pub image_data: String, // Base64 encoded
+    // One per requested image.
+    pub images: Vec<GeneratedImage>,
     pub model: String,
 }
 
+impl ImageGenerationResponse {
+    pub fn image_data(&self) -> &str {
+        &self.images[0].data
+    }
+
+    // Titan answers with `images`, Stability with `artifacts[].base64`. Both
+    // can report failures in an `error` field rather than an error status,
+    // and Titan sometimes with a bare `message` body.
+    pub fn from_body(model_id: &str, body: &serde_json::Value) -> Result<Self> {
+        let error = body["error"].as_str().or_else(|| {
+            let has_images = body.get("images").is_some() || body.get("artifacts").is_some();
+            (!has_images)
+                .then(|| {
+                    body["message"]
+                        .as_str()
+                        .or_else(|| body["Message"].as_str())
+                })
+                .flatten()
+        });
+        if let Some(error) = error.filter(|error| !error.is_empty()) {
+            return Err(BedrockError::ResponseError(format!(
+                "{} failed to generate an image: {}",
+                model_id, error
+            )));
+        }
+
+        let images: Vec<GeneratedImage> = match body["images"].as_array() {
+            Some(images) => images
+                .iter()
+                .filter_map(|image| image.as_str())
+                .map(|data| GeneratedImage {
+                    data: data.to_string(),
+                    seed: None,
+                    finish_reason: None,
+                })
+                .collect(),
+            None => body["artifacts"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|artifact| {
+                    Some(GeneratedImage {
+                        data: artifact["base64"].as_str()?.to_string(),
+                        seed: artifact["seed"].as_u64(),
+                        finish_reason: artifact["finishReason"].as_str().map(String::from),
+                    })
+                })
+                .collect(),
+        };
+        if images.is_empty() {
+            return Err(BedrockError::ResponseError(format!(
+                "No image found in {} response",
+                model_id
+            )));
+        }
+
+        Ok(Self {
+            images,
+            model: model_id.to_string(),
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct TitanImageResponse {
     pub images: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_body_reads_every_provider_image() {
+        let titan = json!({ "images": ["aGVsbG8=", "d29ybGQ="], "error": null });
+        let response =
+            ImageGenerationResponse::from_body("amazon.titan-image-generator-v1", &titan).unwrap();
+        assert_eq!(response.images.len(), 2);
+        assert_eq!(response.image_data(), "aGVsbG8=");
+
+        let stability = json!({
+            "result": "success",
+            "artifacts": [{ "seed": 1, "base64": "c2RYTA==", "finishReason": "SUCCESS" }]
+        });
+        let response =
+            ImageGenerationResponse::from_body("stability.stable-diffusion-xl-v1", &stability)
+                .unwrap();
+        assert_eq!(response.images.len(), 1);
+        assert_eq!(response.images[0].data, "c2RYTA==");
+
+        let failed = json!({ "images": [], "error": "prompt blocked by content filters" });
+        let err = ImageGenerationResponse::from_body("amazon.titan-image-generator-v1", &failed)
+            .unwrap_err();
+        assert!(err.to_string().contains("content filters"));
+    }
+
+    #[test]
+    fn test_from_body_keeps_stability_seed_per_image() {
+        let body = json!({
+            "result": "success",
+            "artifacts": [
+                { "seed": 42, "base64": "Zmlyc3Q=", "finishReason": "SUCCESS" },
+                { "seed": 4294967295u64, "base64": "c2Vjb25k", "finishReason": "CONTENT_FILTERED" }
+            ]
+        });
+        let response =
+            ImageGenerationResponse::from_body("stability.stable-diffusion-xl-v1", &body).unwrap();
+        assert_eq!(response.images[0].seed, Some(42));
+        assert_eq!(response.images[1].seed, Some(4_294_967_295));
+        assert_eq!(
+            response.images[1].finish_reason.as_deref(),
+            Some("CONTENT_FILTERED")
+        );
+
+        let titan = json!({ "images": ["aGVsbG8="] });
+        let response =
+            ImageGenerationResponse::from_body("amazon.titan-image-generator-v1", &titan).unwrap();
+        assert_eq!(response.images[0].seed, None);
+    }
+
+    #[test]
+    fn test_from_body_surfaces_titan_error_message() {
+        let body = json!({
+            "message": "This request has been blocked by our content filters. Our filters automatically flagged this prompt because it may conflict our AUP or AWS Responsible AI Policy."
+        });
+        let err = ImageGenerationResponse::from_body("amazon.titan-image-generator-v1", &body)
+            .unwrap_err();
+        assert!(
+            matches!(err, BedrockError::ResponseError(msg) if msg.contains("blocked by our content filters"))
+        );
+    }
+}
@@ -1,11 +1,19 @@
 pub mod common;
+pub mod document;
 pub mod embedding;
 pub mod image;
+pub mod moderation;
+pub mod rag;
+pub mod registry;
 pub mod storage;
 pub mod text;
 
 pub use common::*;
+pub use document::*;
 pub use embedding::*;
 pub use image::*;
+pub use moderation::*;
+pub use rag::*;
+pub use registry::*;
 pub use storage::*;
 pub use text::*;
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_GUARDRAIL_VERSION: &str = "DRAFT";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationResult {
+    pub flagged: bool,
+    pub action: String,
+    pub reason: Option<String>,
+    pub outputs: Vec<String>,
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagResponse {
+    pub answer: String,
+    // The exact prompt sent to the generation model.
+    pub enhanced_prompt: String,
+    pub context_text: String,
+    pub context: Vec<String>,
+    // Standalone query used for retrieval when it was rewritten from a conversation.
+    #[serde(default)]
+    pub search_query: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationTurn {
+    pub role: String,
+    pub content: String,
+}
+
+impl ConversationTurn {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+pub(crate) fn conversation_transcript(history: &[ConversationTurn]) -> String {
+    history
+        .iter()
+        .map(|turn| format!("{}: {}", turn.role, turn.content))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// The generation prompt for `query`. Earlier turns, if any, come first so
+// the model answers the user's actual message in context.
+pub(crate) fn build_context_prompt(
+    template: Option<&str>,
+    history: &[ConversationTurn],
+    query: &str,
+    context_text: &str,
+) -> String {
+    let prompt = if context_text.is_empty() {
+        format!("Question: {}\n\nAnswer:", query)
+    } else {
+        match template {
+            Some(template) => fill_prompt_template(template, context_text, query),
+            None => format!(
+                "Context:\n{}\n\nQuestion: {}\n\nAnswer based on the provided context:",
+                context_text, query
+            ),
+        }
+    };
+
+    if history.is_empty() {
+        prompt
+    } else {
+        format!(
+            "Conversation so far:\n{}\n\n{}",
+            conversation_transcript(history),
+            prompt
+        )
+    }
+}
+
+// Substitutes `{context}` and `{query}` in one pass, so placeholder-like
+// text inside the retrieved context or the query is left as it is.
+fn fill_prompt_template(template: &str, context: &str, query: &str) -> String {
+    let mut filled = String::with_capacity(template.len() + context.len() + query.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if let Some(after) = tail.strip_prefix("{context}") {
+            filled.push_str(context);
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("{query}") {
+            filled.push_str(query);
+            rest = after;
+        } else {
+            filled.push('{');
+            rest = &tail[1..];
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_placeholders_are_filled_once() {
+        let prompt = build_context_prompt(
+            Some("Docs: {context}\nQ: {query} {other}"),
+            &[],
+            "what is {context}?",
+            "use {query} here",
+        );
+        assert_eq!(
+            prompt,
+            "Docs: use {query} here\nQ: what is {context}? {other}"
+        );
+    }
+
+    #[test]
+    fn test_conversation_prompt_asks_the_original_message() {
+        let history = [
+            ConversationTurn::user("Tell me about the X200 phone"),
+            ConversationTurn::assistant("It has a 6.1 inch screen."),
+        ];
+        let prompt = build_context_prompt(None, &history, "what about its battery?", "4000 mAh");
+
+        assert!(prompt.starts_with(
+            "Conversation so far:\nuser: Tell me about the X200 phone\nassistant: It has a 6.1 inch screen."
+        ));
+        assert!(prompt.contains("Question: what about its battery?"));
+        assert!(prompt.contains("4000 mAh"));
+    }
+}
@@ -0,0 +1,251 @@
+use crate::models::common::{ModelCategory, ModelInfo};
+
+pub struct ModelRegistry;
+
+// On-demand availability by model family, most specific prefix first.
+// Families that are not listed, inference profiles and cross-region ids are
+// never reported as unavailable. AWS adds regions faster than this table is
+// updated, so it only drives a warning; ListFoundationModels is the source
+// of truth.
+const REGION_AVAILABILITY: &[(&str, &[&str])] = &[
+    (
+        "anthropic.claude-3-5-sonnet",
+        &[
+            "us-east-1",
+            "us-west-2",
+            "eu-central-1",
+            "ap-northeast-1",
+            "ap-southeast-1",
+        ],
+    ),
+    (
+        "anthropic.claude",
+        &[
+            "us-east-1",
+            "us-west-2",
+            "eu-central-1",
+            "eu-west-3",
+            "ap-northeast-1",
+            "ap-southeast-2",
+            "ap-south-1",
+        ],
+    ),
+    ("ai21.", &["us-east-1"]),
+    (
+        "cohere.",
+        &["us-east-1", "us-west-2", "eu-central-1", "ap-northeast-1"],
+    ),
+    ("meta.llama3", &["us-east-1", "us-west-2"]),
+    (
+        "mistral.",
+        &["us-east-1", "us-west-2", "eu-west-3", "ap-south-1"],
+    ),
+    ("amazon.titan-image-generator", &["us-east-1", "us-west-2"]),
+    ("stability.", &["us-west-2"]),
+];
+
+impl ModelRegistry {
+    pub fn supported_models() -> Vec<ModelInfo> {
+        vec![
+            Self::model(
+                "amazon.titan-text-express-v1",
+                "Titan Text G1 - Express",
+                "Amazon",
+                ModelCategory::Text,
+                8192,
+                "General purpose text generation",
+            ),
+            Self::model(
+                "amazon.titan-text-lite-v1",
+                "Titan Text G1 - Lite",
+                "Amazon",
+                ModelCategory::Text,
+                4096,
+                "Lightweight, low-cost text generation",
+            ),
+            Self::model(
+                "anthropic.claude-3-haiku-20240307-v1:0",
+                "Claude 3 Haiku",
+                "Anthropic",
+                ModelCategory::Text,
+                4096,
+                "Fast and compact model for near-instant responses",
+            ),
+            Self::model(
+                "anthropic.claude-3-sonnet-20240229-v1:0",
+                "Claude 3 Sonnet",
+                "Anthropic",
+                ModelCategory::Text,
+                4096,
+                "Balanced intelligence and speed",
+            ),
+            Self::model(
+                "anthropic.claude-3-5-sonnet-20240620-v1:0",
+                "Claude 3.5 Sonnet",
+                "Anthropic",
+                ModelCategory::Text,
+                8192,
+                "High intelligence model for complex tasks",
+            ),
+            Self::model(
+                "cohere.command-text-v14",
+                "Command",
+                "Cohere",
+                ModelCategory::Text,
+                4000,
+                "Instruction-following text generation",
+            ),
+            Self::model(
+                "ai21.j2-ultra-v1",
+                "Jurassic-2 Ultra",
+                "AI21",
+                ModelCategory::Text,
+                8191,
+                "Advanced text generation",
+            ),
+            Self::model(
+                "ai21.jamba-1-5-large-v1:0",
+                "Jamba 1.5 Large",
+                "AI21",
+                ModelCategory::Text,
+                4096,
+                "Long-context chat model",
+            ),
+            Self::model(
+                "meta.llama3-8b-instruct-v1:0",
+                "Llama 3 8B Instruct",
+                "Meta",
+                ModelCategory::Text,
+                2048,
+                "Instruction-tuned Llama 3 model",
+            ),
+            Self::model(
+                "meta.llama3-70b-instruct-v1:0",
+                "Llama 3 70B Instruct",
+                "Meta",
+                ModelCategory::Text,
+                2048,
+                "Large instruction-tuned Llama 3 model",
+            ),
+            Self::model(
+                "mistral.mistral-7b-instruct-v0:2",
+                "Mistral 7B Instruct",
+                "Mistral",
+                ModelCategory::Text,
+                8192,
+                "Efficient instruction-following model",
+            ),
+            Self::model(
+                "mistral.mixtral-8x7b-instruct-v0:1",
+                "Mixtral 8x7B Instruct",
+                "Mistral",
+                ModelCategory::Text,
+                4096,
+                "Sparse mixture-of-experts model",
+            ),
+            Self::model(
+                "amazon.titan-image-generator-v1",
+                "Titan Image Generator G1",
+                "Amazon",
+                ModelCategory::Image,
+                0,
+                "Text-to-image generation",
+            ),
+            Self::model(
+                "amazon.titan-image-generator-v2:0",
+                "Titan Image Generator G1 v2",
+                "Amazon",
+                ModelCategory::Image,
+                0,
+                "Text-to-image generation with image conditioning",
+            ),
+            Self::model(
+                "amazon.titan-embed-text-v1",
+                "Titan Embeddings G1 - Text",
+                "Amazon",
+                ModelCategory::Embedding,
+                8192,
+                "1536-dimension text embeddings",
+            ),
+            Self::model(
+                "amazon.titan-embed-text-v2:0",
+                "Titan Text Embeddings V2",
+                "Amazon",
+                ModelCategory::Embedding,
+                8192,
+                "Configurable-dimension text embeddings",
+            ),
+            Self::model(
+                "cohere.embed-english-v3",
+                "Embed English",
+                "Cohere",
+                ModelCategory::Embedding,
+                512,
+                "English text embeddings",
+            ),
+            Self::model(
+                "cohere.embed-multilingual-v3",
+                "Embed Multilingual",
+                "Cohere",
+                ModelCategory::Embedding,
+                512,
+                "Multilingual text embeddings",
+            ),
+        ]
+    }
+
+    pub fn models_by_category(category: ModelCategory) -> Vec<ModelInfo> {
+        Self::supported_models()
+            .into_iter()
+            .filter(|model| model.category == category)
+            .collect()
+    }
+
+    pub fn text_models() -> Vec<ModelInfo> {
+        Self::models_by_category(ModelCategory::Text)
+    }
+
+    pub fn image_models() -> Vec<ModelInfo> {
+        Self::models_by_category(ModelCategory::Image)
+    }
+
+    pub fn embedding_models() -> Vec<ModelInfo> {
+        Self::models_by_category(ModelCategory::Embedding)
+    }
+
+    pub fn known_regions(model_id: &str) -> Option<&'static [&'static str]> {
+        REGION_AVAILABILITY
+            .iter()
+            .find(|(family, _)| model_id.starts_with(family))
+            .map(|(_, regions)| *regions)
+    }
+
+    // None when the model family has no known region list.
+    pub fn is_available_in(model_id: &str, region: &str) -> Option<bool> {
+        Self::known_regions(model_id).map(|regions| regions.contains(&region))
+    }
+
+    pub fn find(model_id: &str) -> Option<ModelInfo> {
+        Self::supported_models()
+            .into_iter()
+            .find(|model| model.id == model_id)
+    }
+
+    fn model(
+        id: &str,
+        name: &str,
+        provider: &str,
+        category: ModelCategory,
+        max_tokens: usize,
+        description: &str,
+    ) -> ModelInfo {
+        ModelInfo {
+            id: id.to_string(),
+            name: name.to_string(),
+            provider: provider.to_string(),
+            category,
+            max_tokens,
+            description: description.to_string(),
+        }
+    }
+}
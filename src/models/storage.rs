@@ -1,11 +1,74 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::{cmp::Ordering, collections::HashMap};
+
+// Reserved metadata written by `embed_and_store` so stored vectors describe
+// themselves; user metadata must not use these keys. The model key predates
+// the underscore convention and keeps its name so existing data still matches.
+pub const EMBEDDING_MODEL_METADATA_KEY: &str = "embedding_model";
+pub const NORMALIZED_METADATA_KEY: &str = "_normalized";
+pub const DIMENSIONS_METADATA_KEY: &str = "_dim";
+pub const DTYPE_METADATA_KEY: &str = "_dtype";
+pub const RESERVED_METADATA_KEYS: [&str; 4] = [
+    EMBEDDING_MODEL_METADATA_KEY,
+    NORMALIZED_METADATA_KEY,
+    DIMENSIONS_METADATA_KEY,
+    DTYPE_METADATA_KEY,
+];
+
+pub(crate) fn vector_description(
+    model_id: &str,
+    vector: &[f32],
+) -> HashMap<String, serde_json::Value> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    HashMap::from([
+        (
+            EMBEDDING_MODEL_METADATA_KEY.to_string(),
+            serde_json::json!(model_id),
+        ),
+        (
+            NORMALIZED_METADATA_KEY.to_string(),
+            serde_json::json!((norm - 1.0).abs() < 1e-3),
+        ),
+        (
+            DIMENSIONS_METADATA_KEY.to_string(),
+            serde_json::json!(vector.len()),
+        ),
+        (DTYPE_METADATA_KEY.to_string(), serde_json::json!("float32")),
+    ])
+}
+
+pub(crate) fn describe_vector(
+    metadata: &mut HashMap<String, serde_json::Value>,
+    model_id: &str,
+    vector: &[f32],
+) {
+    for (key, value) in vector_description(model_id, vector) {
+        if metadata.insert(key.clone(), value).is_some() {
+            log::warn!("Overwriting reserved metadata key {}", key);
+        }
+    }
+}
+
+// Maps cosine similarity from [-1, 1] onto the [0, 1] scale every backend
+// reports `VectorSearchResult::score` on; 0.5 means orthogonal.
+pub fn normalize_cosine_score(similarity: f32) -> f32 {
+    ((similarity + 1.0) / 2.0).clamp(0.0, 1.0)
+}
+
+// Hex-encoded SHA-256, stable across releases so stored hashes stay valid.
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VectorRecord {
     pub id: String,
     pub vector: Vec<f32>,
+    // Only filled by backends that store sparse values (Pinecone, Upstash).
+    #[serde(default)]
+    pub sparse_vector: Option<SparseVector>,
     pub metadata: HashMap<String, serde_json::Value>,
     pub content: Option<String>,
     pub namespace: Option<String>,
@@ -13,15 +76,92 @@ pub struct VectorRecord {
     pub updated_at: DateTime<Utc>,
 }
 
+impl VectorRecord {
+    pub fn embedding_model(&self) -> Option<&str> {
+        self.metadata
+            .get(EMBEDDING_MODEL_METADATA_KEY)
+            .and_then(|v| v.as_str())
+    }
+
+    pub fn is_normalized(&self) -> Option<bool> {
+        self.metadata
+            .get(NORMALIZED_METADATA_KEY)
+            .and_then(|v| v.as_bool())
+    }
+
+    pub fn dimensions(&self) -> Option<usize> {
+        self.metadata
+            .get(DIMENSIONS_METADATA_KEY)
+            .and_then(|v| v.as_u64())
+            .map(|dim| dim as usize)
+    }
+
+    pub fn dtype(&self) -> Option<&str> {
+        self.metadata
+            .get(DTYPE_METADATA_KEY)
+            .and_then(|v| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SparseVector {
+    pub indices: Vec<u32>,
+    pub values: Vec<f32>,
+}
+
+// How `VectorStorageManager::insert_batch` treats records in one batch that
+// share an id. Backends would otherwise disagree: some keep the last write,
+// Postgres fails the whole statement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateIdPolicy {
+    #[default]
+    Error,
+    // Keep only the last record for each id, in the position it appeared.
+    LastWins,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VectorInsert {
     pub id: Option<String>,
     pub vector: Vec<f32>,
+    #[serde(default)]
+    pub sparse_vector: Option<SparseVector>,
     pub metadata: HashMap<String, serde_json::Value>,
     pub content: Option<String>,
     pub namespace: Option<String>,
 }
 
+// Re-inserting a fetched record keeps its id; the timestamps are dropped and
+// set again by the backend.
+impl From<VectorRecord> for VectorInsert {
+    fn from(record: VectorRecord) -> Self {
+        Self {
+            id: Some(record.id),
+            vector: record.vector,
+            sparse_vector: record.sparse_vector,
+            metadata: record.metadata,
+            content: record.content,
+            namespace: record.namespace,
+        }
+    }
+}
+
+// The vector is empty unless the search set `include_vector`, and results
+// don't carry their namespace.
+impl From<&VectorSearchResult> for VectorInsert {
+    fn from(result: &VectorSearchResult) -> Self {
+        Self {
+            id: Some(result.id.clone()),
+            vector: result.vector.clone().unwrap_or_default(),
+            sparse_vector: None,
+            metadata: result.metadata.clone(),
+            content: result.content.clone(),
+            namespace: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VectorUpdate {
     pub id: String,
@@ -34,20 +174,158 @@ pub struct VectorUpdate {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VectorSearch {
     pub vector: Vec<f32>,
+    #[serde(default)]
+    pub sparse_vector: Option<SparseVector>,
     pub limit: usize,
     pub namespace: Option<String>,
     pub filter: Option<HashMap<String, serde_json::Value>>,
     pub include_metadata: bool,
     pub include_content: bool,
+    #[serde(default)]
+    pub include_vector: bool,
+    #[serde(default)]
+    pub min_score: Option<f32>,
+    // Query text used to pick a `highlight` snippet from each result's
+    // content; needs `include_content`.
+    #[serde(default)]
+    pub highlight_query: Option<String>,
+}
+
+impl VectorSearch {
+    pub fn builder(vector: Vec<f32>) -> VectorSearchBuilder {
+        VectorSearchBuilder {
+            search: VectorSearch {
+                vector,
+                sparse_vector: None,
+                limit: 10,
+                namespace: None,
+                filter: None,
+                include_metadata: true,
+                include_content: false,
+                include_vector: false,
+                min_score: None,
+                highlight_query: None,
+            },
+        }
+    }
+}
+
+pub struct VectorSearchBuilder {
+    search: VectorSearch,
+}
+
+impl VectorSearchBuilder {
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.search.limit = limit;
+        self
+    }
+
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.search.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn filter(mut self, filter: HashMap<String, serde_json::Value>) -> Self {
+        self.search.filter = Some(filter);
+        self
+    }
+
+    pub fn sparse_vector(mut self, sparse_vector: SparseVector) -> Self {
+        self.search.sparse_vector = Some(sparse_vector);
+        self
+    }
+
+    pub fn include_metadata(mut self, include: bool) -> Self {
+        self.search.include_metadata = include;
+        self
+    }
+
+    pub fn include_content(mut self, include: bool) -> Self {
+        self.search.include_content = include;
+        self
+    }
+
+    pub fn include_vector(mut self, include: bool) -> Self {
+        self.search.include_vector = include;
+        self
+    }
+
+    pub fn min_score(mut self, min_score: f32) -> Self {
+        self.search.min_score = Some(min_score);
+        self
+    }
+
+    pub fn highlight(mut self, query: impl Into<String>) -> Self {
+        self.search.highlight_query = Some(query.into());
+        self.search.include_content = true;
+        self
+    }
+
+    pub fn build(self) -> VectorSearch {
+        self.search
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VectorSearchResult {
     pub id: String,
+    // Cosine similarity normalized to [0, 1] by `normalize_cosine_score`, so
+    // `min_score` thresholds mean the same thing on every backend.
     pub score: f32,
+    // The backend's native score before normalization.
+    #[serde(default)]
+    pub raw_score: Option<f32>,
+    // Score assigned by a reranking or boosting stage, if any.
+    #[serde(default)]
+    pub rerank_score: Option<f32>,
     pub vector: Option<Vec<f32>>,
     pub metadata: HashMap<String, serde_json::Value>,
     pub content: Option<String>,
+    // Best-matching snippet of `content`, when the search asked for one.
+    #[serde(default)]
+    pub highlight: Option<String>,
+}
+
+// Sentences per highlight snippet.
+const HIGHLIGHT_WINDOW: usize = 2;
+
+fn highlight_terms(text: &str) -> std::collections::HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| term.chars().count() > 2)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+impl VectorSearchResult {
+    pub fn effective_score(&self) -> f32 {
+        self.rerank_score.unwrap_or(self.score)
+    }
+
+    // The window of consecutive sentences in `content` sharing the most
+    // distinct terms with `query`. None when nothing overlaps.
+    pub fn extract_highlight(&self, query: &str) -> Option<String> {
+        let content = self.content.as_deref()?;
+        let query_terms = highlight_terms(query);
+
+        let sentences: Vec<&str> = content
+            .split_inclusive(['.', '!', '?', '\n'])
+            .map(str::trim)
+            .filter(|sentence| !sentence.is_empty())
+            .collect();
+        let window = HIGHLIGHT_WINDOW.min(sentences.len());
+
+        (0..=sentences.len() - window)
+            .map(|start| {
+                let snippet = sentences[start..start + window].join(" ");
+                let overlap = highlight_terms(&snippet).intersection(&query_terms).count();
+                (overlap, snippet)
+            })
+            .filter(|(overlap, _)| *overlap > 0)
+            // Earliest window wins ties.
+            .rev()
+            .max_by_key(|(overlap, _)| *overlap)
+            .map(|(_, snippet)| snippet)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,6 +334,152 @@ pub struct VectorSearchResponse {
     pub total: usize,
 }
 
+impl VectorSearchResponse {
+    // Orders by `effective_score`, so reranked results sort by their new score.
+    pub fn sort_by_score_desc(&mut self) {
+        self.results
+            .sort_by(|a, b| b.effective_score().total_cmp(&a.effective_score()));
+    }
+
+    pub fn truncate(&mut self, n: usize) {
+        self.results.truncate(n);
+        self.total = self.results.len();
+    }
+
+    // Drops results tagged with an embedding model other than `model`.
+    // Untagged results, stored before tagging existed, are kept. Returns how
+    // many results were removed.
+    pub fn retain_embedding_model(&mut self, model: &str) -> usize {
+        let before = self.results.len();
+        self.results.retain(|result| {
+            result
+                .metadata
+                .get(EMBEDDING_MODEL_METADATA_KEY)
+                .and_then(|v| v.as_str())
+                .is_none_or(|tagged| tagged == model)
+        });
+        self.total = self.results.len();
+        before - self.results.len()
+    }
+
+    pub fn vectors(&self) -> Vec<&Vec<f32>> {
+        self.results
+            .iter()
+            .filter_map(|result| result.vector.as_ref())
+            .collect()
+    }
+
+    // One flat row per result. Metadata keys become columns, but never
+    // shadow the id/score/content columns.
+    pub fn to_records(&self) -> Vec<HashMap<String, serde_json::Value>> {
+        self.results
+            .iter()
+            .map(|result| {
+                let mut row = result.metadata.clone();
+                row.insert("id".to_string(), serde_json::json!(result.id));
+                row.insert("score".to_string(), serde_json::json!(result.score));
+                row.insert("raw_score".to_string(), serde_json::json!(result.raw_score));
+                row.insert(
+                    "rerank_score".to_string(),
+                    serde_json::json!(result.rerank_score),
+                );
+                row.insert("content".to_string(), serde_json::json!(result.content));
+                row
+            })
+            .collect()
+    }
+}
+
+// Sort order for `query_by_metadata`. Records without the key come last in
+// either direction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataOrder {
+    pub key: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl MetadataOrder {
+    pub fn asc(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            descending: false,
+        }
+    }
+
+    pub fn desc(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            descending: true,
+        }
+    }
+}
+
+// Exact match on every filter entry, the same semantics as Postgres `@>` on
+// top-level keys.
+pub(crate) fn metadata_matches(
+    metadata: &HashMap<String, serde_json::Value>,
+    filter: &HashMap<String, serde_json::Value>,
+) -> bool {
+    filter
+        .iter()
+        .all(|(key, value)| metadata.get(key) == Some(value))
+}
+
+// Values of different JSON types are ranked the way Postgres orders jsonb,
+// so client-side sorting agrees with the Postgres backend.
+pub(crate) fn sort_by_metadata(records: &mut [VectorRecord], order: &MetadataOrder) {
+    use serde_json::Value;
+
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::String(_) => 1,
+            Value::Number(_) => 2,
+            Value::Bool(_) => 3,
+            Value::Array(_) => 4,
+            Value::Object(_) => 5,
+        }
+    }
+    fn compare(a: &Value, b: &Value) -> Ordering {
+        match (a, b) {
+            (Value::Number(a), Value::Number(b)) => a
+                .as_f64()
+                .partial_cmp(&b.as_f64())
+                .unwrap_or(Ordering::Equal),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            _ => rank(a).cmp(&rank(b)),
+        }
+    }
+
+    records.sort_by(|a, b| {
+        match (a.metadata.get(&order.key), b.metadata.get(&order.key)) {
+            (Some(a), Some(b)) if order.descending => compare(b, a),
+            (Some(a), Some(b)) => compare(a, b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+        .then_with(|| a.id.cmp(&b.id))
+    });
+}
+
+// One page of a full scan; `next_cursor` is None once the scan is complete.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrollPage {
+    pub records: Vec<VectorRecord>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub scanned: usize,
+    pub migrated: usize,
+    pub failed: usize,
+    pub errors: Vec<String>,
+}
+
 // Storage operation results
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InsertResult {
@@ -77,3 +501,146 @@ pub struct DeleteResult {
     pub success: bool,
     pub message: Option<String>,
 }
+
+pub trait OperationOutcome {
+    fn succeeded(&self) -> bool;
+    fn message(&self) -> Option<&str>;
+}
+
+macro_rules! impl_operation_outcome {
+    ($($ty:ty),*) => {
+        $(impl OperationOutcome for $ty {
+            fn succeeded(&self) -> bool {
+                self.success
+            }
+
+            fn message(&self) -> Option<&str> {
+                self.message.as_deref()
+            }
+        })*
+    };
+}
+
+impl_operation_outcome!(InsertResult, UpdateResult, DeleteResult);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResult<T> {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<T>,
+    pub first_error: Option<String>,
+}
+
+impl<T> BatchResult<T> {
+    pub fn is_complete_success(&self) -> bool {
+        self.failed == 0
+    }
+}
+
+impl<T: OperationOutcome> From<Vec<T>> for BatchResult<T> {
+    fn from(results: Vec<T>) -> Self {
+        let succeeded = results.iter().filter(|r| r.succeeded()).count();
+        let first_error = results
+            .iter()
+            .find(|r| !r.succeeded())
+            .map(|r| r.message().unwrap_or("Operation failed").to_string());
+
+        Self {
+            succeeded,
+            failed: results.len() - succeeded,
+            results,
+            first_error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_picks_the_best_overlapping_sentences() {
+        let result = VectorSearchResult {
+            id: "doc".to_string(),
+            score: 0.9,
+            raw_score: None,
+            rerank_score: None,
+            vector: None,
+            metadata: HashMap::new(),
+            content: Some(
+                "Rust has no garbage collector. Ownership rules free memory. The borrow checker enforces them at compile time. Cargo builds crates."
+                    .to_string(),
+            ),
+            highlight: None,
+        };
+
+        assert_eq!(
+            result
+                .extract_highlight("how does the borrow checker free memory")
+                .as_deref(),
+            Some("Ownership rules free memory. The borrow checker enforces them at compile time.")
+        );
+        assert_eq!(result.extract_highlight("python interpreter"), None);
+    }
+
+    #[test]
+    fn test_retain_embedding_model_keeps_untagged_results() {
+        let result = |id: &str, model: Option<&str>| VectorSearchResult {
+            id: id.to_string(),
+            score: 0.9,
+            raw_score: None,
+            rerank_score: None,
+            vector: None,
+            metadata: model
+                .map(|model| {
+                    HashMap::from([(
+                        EMBEDDING_MODEL_METADATA_KEY.to_string(),
+                        serde_json::json!(model),
+                    )])
+                })
+                .unwrap_or_default(),
+            content: None,
+            highlight: None,
+        };
+        let mut response = VectorSearchResponse {
+            results: vec![
+                result("titan", Some("amazon.titan-embed-text-v2:0")),
+                result("legacy", None),
+                result("cohere", Some("cohere.embed-english-v3")),
+                result("titan-2", Some("amazon.titan-embed-text-v2:0")),
+            ],
+            total: 4,
+        };
+
+        assert_eq!(
+            response.retain_embedding_model("amazon.titan-embed-text-v2:0"),
+            1
+        );
+        let ids: Vec<_> = response.results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["titan", "legacy", "titan-2"]);
+        assert_eq!(response.total, 3);
+    }
+
+    #[test]
+    fn test_reinserting_a_record_keeps_its_sparse_values() {
+        let sparse = SparseVector {
+            indices: vec![3, 17],
+            values: vec![0.5, 0.25],
+        };
+        let record: VectorRecord = serde_json::from_value(serde_json::json!({
+            "id": "doc",
+            "vector": [0.1, 0.2],
+            "sparse_vector": sparse,
+            "metadata": {},
+            "content": null,
+            "namespace": null,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z"
+        }))
+        .unwrap();
+
+        let insert = VectorInsert::from(record);
+        assert_eq!(insert.id.as_deref(), Some("doc"));
+        assert_eq!(insert.sparse_vector, Some(sparse));
+    }
+}
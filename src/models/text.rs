@@ -1,8 +1,13 @@
 use serde::{Deserialize, Serialize};
 
-use crate::ModelProvider;
+use crate::{
+    bedrock::TextClient,
+    error::{BedrockError, Result},
+    models::{embedding::estimate_tokens, StreamChunk},
+    ModelProvider,
+};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct TextGenerationRequest {
     pub prompt: String,
     pub max_tokens: Option<i32>,
@@ -10,6 +15,479 @@ pub struct TextGenerationRequest {
     pub model_id: Option<String>,
     pub stream: Option<bool>,
     pub provider: Option<ModelProvider>,
+    #[serde(default)]
+    pub moderation_guardrail_id: Option<String>,
+    #[serde(default)]
+    pub moderation_guardrail_version: Option<String>,
+    #[serde(default)]
+    pub num_completions: Option<i32>,
+    // Mistral's own system-prompt guardrail; only honoured by chat-format models.
+    #[serde(default)]
+    pub safe_prompt: Option<bool>,
+    // Sent after the client's prompt prefix, which always comes first: as
+    // Anthropic's top-level `system`, a leading system message for other
+    // chat-format models, or prepended to the prompt for the rest.
+    #[serde(default, alias = "system")]
+    pub system_prompt: Option<String>,
+    // Overrides the client's per-provider sampling defaults.
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub top_k: Option<i32>,
+    // Named `GenerationPreset`; explicitly set fields take precedence over it.
+    #[serde(default)]
+    pub preset: Option<String>,
+    // Sent instead of `prompt` to messages-format models, and collapsed into
+    // a `User:`/`Assistant:` transcript for prompt-only ones. Image blocks
+    // need an Anthropic Claude 3 or newer model.
+    #[serde(default)]
+    pub messages: Option<Vec<ChatMessage>>,
+    // Asks Bedrock for its `amazon-bedrock-trace` block, returned in
+    // `TextGenerationResponse::trace`. Off unless set.
+    #[serde(default)]
+    pub trace: Option<bool>,
+    // Text that ends generation when produced. Meta Llama models take none;
+    // Titan only accepts `|` and `User:`.
+    #[serde(default)]
+    pub stop_sequences: Option<Vec<String>>,
+}
+
+pub const SUPPORTED_IMAGE_MEDIA_TYPES: [&str; 4] =
+    ["image/jpeg", "image/png", "image/gif", "image/webp"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text(String),
+    Image { base64: String, media_type: String },
+}
+
+impl ContentBlock {
+    fn to_anthropic(&self) -> Result<serde_json::Value> {
+        match self {
+            Self::Text(text) => Ok(serde_json::json!({ "type": "text", "text": text })),
+            Self::Image { base64, media_type } => {
+                if !SUPPORTED_IMAGE_MEDIA_TYPES.contains(&media_type.as_str()) {
+                    return Err(BedrockError::RequestError(format!(
+                        "Unsupported image media type {}, expected one of: {}",
+                        media_type,
+                        SUPPORTED_IMAGE_MEDIA_TYPES.join(", ")
+                    )));
+                }
+                Ok(serde_json::json!({
+                    "type": "image",
+                    "source": {
+                        "type": "base64",
+                        "media_type": media_type,
+                        "data": base64
+                    }
+                }))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl MessageContent {
+    pub fn has_images(&self) -> bool {
+        matches!(self, Self::Blocks(blocks) if blocks.iter().any(|block| matches!(block, ContentBlock::Image { .. })))
+    }
+
+    // The text blocks joined together, for models that only take strings.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text(text) => Some(text.as_str()),
+                    ContentBlock::Image { .. } => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        }
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<Vec<ContentBlock>> for MessageContent {
+    fn from(blocks: Vec<ContentBlock>) -> Self {
+        Self::Blocks(blocks)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+    // Merged into the request's system prompt, after `system_prompt`, since
+    // no provider accepts system turns inside the conversation itself.
+    System,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Assistant => "assistant",
+            Self::System => "system",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: MessageContent,
+}
+
+impl ChatMessage {
+    pub fn user(content: impl Into<MessageContent>) -> Self {
+        Self {
+            role: Role::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<MessageContent>) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: content.into(),
+        }
+    }
+
+    pub fn system(content: impl Into<MessageContent>) -> Self {
+        Self {
+            role: Role::System,
+            content: content.into(),
+        }
+    }
+
+    pub(crate) fn to_anthropic(&self) -> Result<serde_json::Value> {
+        let content = match &self.content {
+            MessageContent::Text(text) => serde_json::json!(text),
+            MessageContent::Blocks(blocks) => serde_json::Value::Array(
+                blocks
+                    .iter()
+                    .map(ContentBlock::to_anthropic)
+                    .collect::<Result<_>>()?,
+            ),
+        };
+        Ok(serde_json::json!({ "role": self.role.as_str(), "content": content }))
+    }
+}
+
+// A reusable bundle of generation parameters, referenced from requests by
+// the name it is registered under.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationPreset {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<i32>,
+    pub max_tokens: Option<i32>,
+}
+
+impl GenerationPreset {
+    pub fn builtin() -> Vec<(&'static str, GenerationPreset)> {
+        vec![
+            (
+                "creative",
+                GenerationPreset {
+                    temperature: Some(1.0),
+                    top_p: Some(0.95),
+                    top_k: None,
+                    max_tokens: Some(1024),
+                },
+            ),
+            (
+                "balanced",
+                GenerationPreset {
+                    temperature: Some(0.7),
+                    top_p: Some(0.9),
+                    top_k: None,
+                    max_tokens: None,
+                },
+            ),
+            (
+                "precise",
+                GenerationPreset {
+                    temperature: Some(0.2),
+                    top_p: Some(0.5),
+                    top_k: None,
+                    max_tokens: None,
+                },
+            ),
+            (
+                "deterministic",
+                GenerationPreset {
+                    temperature: Some(0.0),
+                    top_p: Some(1.0),
+                    top_k: Some(1),
+                    max_tokens: None,
+                },
+            ),
+        ]
+    }
+
+    pub(crate) fn apply_to(&self, request: &TextGenerationRequest) -> TextGenerationRequest {
+        TextGenerationRequest {
+            temperature: request.temperature.or(self.temperature),
+            top_p: request.top_p.or(self.top_p),
+            top_k: request.top_k.or(self.top_k),
+            max_tokens: request.max_tokens.or(self.max_tokens),
+            preset: None,
+            ..request.clone()
+        }
+    }
+}
+
+// Limits on the caller-supplied text of a request, checked before any model
+// or guardrail call: the prompt, the system prompt and every message. The
+// client's own prompt wrapper is trusted and not counted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputGuard {
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+    // Estimated at about four characters per token, as for embeddings.
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    // Phrases that reject the request, e.g. "ignore previous instructions".
+    // Matched case-insensitively with runs of whitespace collapsed.
+    #[serde(default)]
+    pub denylist: Vec<String>,
+}
+
+impl InputGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = Some(max_chars);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_denied_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.denylist.push(pattern.into());
+        self
+    }
+
+    pub fn check(&self, request: &TextGenerationRequest) -> Result<()> {
+        let texts: Vec<String> = [request.prompt.clone()]
+            .into_iter()
+            .chain(request.system_prompt.clone())
+            .chain(
+                request
+                    .messages
+                    .iter()
+                    .flatten()
+                    .map(|message| message.content.text()),
+            )
+            .filter(|text| !text.is_empty())
+            .collect();
+
+        let chars: usize = texts.iter().map(|text| text.chars().count()).sum();
+        if let Some(max_chars) = self.max_chars.filter(|max| chars > *max) {
+            return Err(BedrockError::RequestError(format!(
+                "Input rejected by max_chars guard: {} characters, limit {}",
+                chars, max_chars
+            )));
+        }
+        let tokens: usize = texts.iter().map(|text| estimate_tokens(text)).sum();
+        if let Some(max_tokens) = self.max_tokens.filter(|max| tokens > *max) {
+            return Err(BedrockError::RequestError(format!(
+                "Input rejected by max_tokens guard: about {} tokens, limit {}",
+                tokens, max_tokens
+            )));
+        }
+
+        let normalize = |text: &str| {
+            text.split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase()
+        };
+        let normalized: Vec<String> = texts.iter().map(|text| normalize(text)).collect();
+        for pattern in &self.denylist {
+            let needle = normalize(pattern);
+            if !needle.is_empty() && normalized.iter().any(|text| text.contains(&needle)) {
+                return Err(BedrockError::RequestError(format!(
+                    "Input rejected by denylist guard: matched \"{}\"",
+                    pattern
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn continuation_prompt(prompt: &str, partial: &str) -> String {
+    format!(
+        "{}\n\nPartial response so far:\n{}\n\nContinue the response exactly where it stops, without repeating any of it:",
+        prompt, partial
+    )
+}
+
+// First generated text in a raw invoke_model body, whichever provider
+// format it is in.
+pub(crate) fn generated_text(body: &serde_json::Value) -> Option<&str> {
+    body["results"][0]["outputText"]
+        .as_str()
+        .or_else(|| body["content"][0]["text"].as_str())
+        .or_else(|| body["generation"].as_str())
+        .or_else(|| body["generations"][0]["text"].as_str())
+        .or_else(|| body["choices"][0]["message"]["content"].as_str())
+        .or_else(|| body["outputs"][0]["text"].as_str())
+        .or_else(|| body["completions"][0]["data"]["text"].as_str())
+}
+
+// Parses JSON from model output, tolerating a Markdown code fence or prose
+// around a single top-level object or array.
+pub fn parse_json(text: &str) -> Result<serde_json::Value> {
+    let trimmed = text.trim();
+    let unfenced = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.trim_end().strip_suffix("```"))
+        .unwrap_or(trimmed)
+        .trim();
+
+    serde_json::from_str(unfenced).or_else(|e| {
+        let start = unfenced.find(['{', '[']);
+        let end = unfenced.rfind(['}', ']']);
+        match (start, end) {
+            (Some(start), Some(end)) if start < end => serde_json::from_str(&unfenced[start..=end]),
+            _ => Err(e),
+        }
+        .map_err(|e| BedrockError::ResponseError(format!("Invalid JSON in model output: {}", e)))
+    })
+}
+
+pub(crate) fn json_repair_prompt(text: &str, error: &BedrockError) -> String {
+    format!(
+        "The following output is not valid JSON ({}). Fix it to valid JSON, keeping the same content. Respond with the JSON only.\n\n{}",
+        error, text
+    )
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StructuredResponse {
+    pub value: serde_json::Value,
+    // Model output the value was parsed from, after any repairs.
+    pub raw: String,
+    pub repair_attempts: u32,
+}
+
+// Conversational scaffolding models put around the content they were asked
+// for. A leading line is only removed when it starts with one of `leading`
+// and either introduces the content (ends with ':') or is nothing but an
+// interjection such as "Sure!"; a trailing line only when it starts with one
+// of `trailing`. Patterns are matched case-insensitively.
+#[derive(Debug, Clone)]
+pub struct PreambleTrimmer {
+    pub leading: Vec<String>,
+    pub trailing: Vec<String>,
+}
+
+impl Default for PreambleTrimmer {
+    fn default() -> Self {
+        Self {
+            leading: [
+                "here's",
+                "here is",
+                "here are",
+                "sure",
+                "certainly",
+                "of course",
+                "okay",
+                "absolutely",
+            ]
+            .map(String::from)
+            .to_vec(),
+            trailing: [
+                "i hope",
+                "hope this",
+                "let me know",
+                "feel free",
+                "is there anything",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+impl PreambleTrimmer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_leading(mut self, pattern: impl Into<String>) -> Self {
+        self.leading.push(pattern.into());
+        self
+    }
+
+    pub fn with_trailing(mut self, pattern: impl Into<String>) -> Self {
+        self.trailing.push(pattern.into());
+        self
+    }
+
+    pub fn trim<'a>(&self, text: &'a str) -> &'a str {
+        let mut text = text.trim();
+        loop {
+            let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+            let first = first.trim().to_lowercase();
+            let is_preamble = self.matches(&self.leading, &first)
+                && (first.ends_with(':')
+                    || self.leading.iter().any(|pattern| {
+                        first
+                            .strip_prefix(pattern.to_lowercase().as_str())
+                            .is_some_and(|tail| tail.chars().all(|c| c.is_ascii_punctuation()))
+                    }));
+            if !is_preamble || rest.trim().is_empty() {
+                break;
+            }
+            text = rest.trim();
+        }
+        loop {
+            let (rest, last) = text.rsplit_once('\n').unwrap_or(("", text));
+            if !self.matches(&self.trailing, &last.trim().to_lowercase()) || rest.trim().is_empty()
+            {
+                break;
+            }
+            text = rest.trim();
+        }
+        text
+    }
+
+    fn matches(&self, patterns: &[String], line: &str) -> bool {
+        patterns
+            .iter()
+            .any(|pattern| line.starts_with(pattern.to_lowercase().as_str()))
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -19,6 +497,265 @@ pub struct TextGenerationResponse {
     pub tokens_generated: i32,
     pub tokens_prompt: i32,
     pub finish_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub candidates: Option<Vec<String>>,
+    // AWS request id of the invocation, for support and CloudTrail lookups.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    // Bedrock's explanation of guardrail interventions and inference profile
+    // routing, when the request set `trace`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<serde_json::Value>,
+}
+
+// A whole response as the single, final chunk of a stream, for models that
+// can't stream.
+impl From<TextGenerationResponse> for StreamChunk {
+    fn from(response: TextGenerationResponse) -> Self {
+        StreamChunk {
+            chunk: response.text,
+            done: true,
+            finish_reason: response.finish_reason,
+            tokens_prompt: Some(response.tokens_prompt),
+            tokens_generated: Some(response.tokens_generated),
+        }
+    }
+}
+
+impl TextGenerationResponse {
+    pub fn primary(&self) -> &str {
+        &self.text
+    }
+
+    // The text without a leading "Here's ...:" line or a trailing "I hope
+    // this helps" line; `text` itself keeps the raw output.
+    pub fn trim_preamble(&self) -> &str {
+        self.trim_preamble_with(&PreambleTrimmer::default())
+    }
+
+    pub fn trim_preamble_with(&self, trimmer: &PreambleTrimmer) -> &str {
+        trimmer.trim(&self.text)
+    }
+
+    pub fn all_candidates(&self) -> Vec<&str> {
+        match &self.candidates {
+            Some(candidates) => candidates.iter().map(String::as_str).collect(),
+            None => vec![self.text.as_str()],
+        }
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.finish_reason.as_deref().is_some_and(|reason| {
+            matches!(
+                reason.to_ascii_lowercase().as_str(),
+                "max_tokens" | "length" | "max_length"
+            )
+        })
+    }
+
+    // Claude reports `stop_sequence`, Titan `STOP_CRITERIA_MET`. Other
+    // providers don't tell a stop sequence apart from a natural end.
+    pub fn hit_stop_sequence(&self) -> bool {
+        self.finish_reason.as_deref().is_some_and(|reason| {
+            matches!(
+                reason.to_ascii_lowercase().as_str(),
+                "stop_sequence" | "stop_criteria_met"
+            )
+        })
+    }
+
+    // The output so far extended by one more call, with the token usage
+    // summed. Fails once the request's `max_tokens` is already spent.
+    pub async fn continue_generation(
+        &self,
+        client: &TextClient,
+        request: &TextGenerationRequest,
+    ) -> Result<TextGenerationResponse> {
+        client
+            .continue_response(&self.pinned_request(request), self)
+            .await?
+            .ok_or_else(|| {
+                BedrockError::RequestError(
+                    "max_tokens is already spent; nothing left to continue".into(),
+                )
+            })
+    }
+
+    // Continuations go to the model that answered, if the request named none.
+    fn pinned_request(&self, request: &TextGenerationRequest) -> TextGenerationRequest {
+        TextGenerationRequest {
+            model_id: request
+                .model_id
+                .clone()
+                .or_else(|| Some(self.model.clone())),
+            ..request.clone()
+        }
+    }
+
+    pub fn from_mistral(model_id: &str, body: &serde_json::Value) -> Result<Self> {
+        let count = |value: &serde_json::Value| value.as_i64().unwrap_or(0) as i32;
+
+        // Chat-format models answer with `choices`, the instruct models with `outputs`.
+        let (items, text_of): (_, fn(&serde_json::Value) -> Option<&str>) =
+            if let Some(choices) = body["choices"].as_array() {
+                (choices, |item| item["message"]["content"].as_str())
+            } else if let Some(outputs) = body["outputs"].as_array() {
+                (outputs, |item| item["text"].as_str())
+            } else {
+                return Err(BedrockError::ResponseError(
+                    "No choices or outputs in Mistral response".into(),
+                ));
+            };
+
+        let texts: Vec<String> = items
+            .iter()
+            .map(|item| text_of(item).unwrap_or("").to_string())
+            .collect();
+
+        Ok(Self {
+            text: texts.first().cloned().unwrap_or_default(),
+            model: model_id.to_string(),
+            tokens_generated: count(&body["usage"]["completion_tokens"]),
+            tokens_prompt: count(&body["usage"]["prompt_tokens"]),
+            finish_reason: items
+                .first()
+                .and_then(|item| item["stop_reason"].as_str())
+                .map(String::from),
+            candidates: (texts.len() > 1).then_some(texts),
+            request_id: None,
+            trace: None,
+        })
+    }
+
+    pub fn from_ai21(model_id: &str, body: &serde_json::Value) -> Result<Self> {
+        let count = |value: &serde_json::Value| value.as_i64().unwrap_or(0) as i32;
+
+        let (texts, finish_reason, tokens_prompt, tokens_generated) =
+            if model_id.starts_with("ai21.jamba") {
+                let choices = body["choices"].as_array().ok_or_else(|| {
+                    BedrockError::ResponseError("No choices in Jamba response".into())
+                })?;
+                (
+                    choices
+                        .iter()
+                        .map(|choice| {
+                            choice["message"]["content"]
+                                .as_str()
+                                .unwrap_or("")
+                                .to_string()
+                        })
+                        .collect::<Vec<_>>(),
+                    choices
+                        .first()
+                        .and_then(|choice| choice["finish_reason"].as_str())
+                        .map(String::from),
+                    count(&body["usage"]["prompt_tokens"]),
+                    count(&body["usage"]["completion_tokens"]),
+                )
+            } else {
+                let completions = body["completions"].as_array().ok_or_else(|| {
+                    BedrockError::ResponseError("No completions in Jurassic response".into())
+                })?;
+                let token_len = |value: &serde_json::Value| {
+                    value.as_array().map(|tokens| tokens.len()).unwrap_or(0) as i32
+                };
+                (
+                    completions
+                        .iter()
+                        .map(|completion| {
+                            completion["data"]["text"]
+                                .as_str()
+                                .unwrap_or("")
+                                .to_string()
+                        })
+                        .collect::<Vec<_>>(),
+                    completions
+                        .first()
+                        .and_then(|completion| completion["finishReason"]["reason"].as_str())
+                        .map(String::from),
+                    token_len(&body["prompt"]["tokens"]),
+                    completions
+                        .iter()
+                        .map(|completion| token_len(&completion["data"]["tokens"]))
+                        .sum(),
+                )
+            };
+
+        Ok(Self {
+            text: texts.first().cloned().unwrap_or_default(),
+            model: model_id.to_string(),
+            tokens_generated,
+            tokens_prompt,
+            finish_reason,
+            candidates: (texts.len() > 1).then_some(texts),
+            request_id: None,
+            trace: None,
+        })
+    }
+
+    // Parses a raw invoke_model body from any supported provider.
+    pub fn from_body(model_id: &str, body: &serde_json::Value) -> Result<Self> {
+        if model_id.starts_with("mistral.") {
+            return Self::from_mistral(model_id, body);
+        }
+        if model_id.starts_with("ai21.") {
+            return Self::from_ai21(model_id, body);
+        }
+
+        let count = |value: &serde_json::Value| value.as_i64().unwrap_or(0) as i32;
+        let text = generated_text(body)
+            .ok_or_else(|| {
+                BedrockError::ResponseError(format!("No generated text in {} response", model_id))
+            })?
+            .to_string();
+
+        let (finish_reason, tokens_prompt, tokens_generated) =
+            if let Some(result) = body["results"].get(0) {
+                (
+                    &result["completionReason"],
+                    count(&body["inputTextTokenCount"]),
+                    count(&result["tokenCount"]),
+                )
+            } else if body.get("content").is_some() {
+                (
+                    &body["stop_reason"],
+                    count(&body["usage"]["input_tokens"]),
+                    count(&body["usage"]["output_tokens"]),
+                )
+            } else if body.get("generation").is_some() {
+                (
+                    &body["stop_reason"],
+                    count(&body["prompt_token_count"]),
+                    count(&body["generation_token_count"]),
+                )
+            } else {
+                (&body["generations"][0]["finish_reason"], 0, 0)
+            };
+        if tokens_prompt == 0 && tokens_generated == 0 {
+            log::debug!("{} response reported no token usage", model_id);
+        }
+
+        let candidates = body["generations"]
+            .as_array()
+            .filter(|generations| generations.len() > 1)
+            .map(|generations| {
+                generations
+                    .iter()
+                    .map(|generation| generation["text"].as_str().unwrap_or("").to_string())
+                    .collect()
+            });
+
+        Ok(Self {
+            text,
+            model: model_id.to_string(),
+            tokens_generated,
+            tokens_prompt,
+            finish_reason: finish_reason.as_str().map(String::from),
+            candidates,
+            request_id: None,
+            trace: None,
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -36,3 +773,206 @@ pub struct TitanTextResponse {
     #[serde(rename = "completionReason")]
     pub completion_reason: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_input_guard_names_the_tripped_guard() {
+        let guard = InputGuard::new()
+            .with_max_chars(40)
+            .with_denied_pattern("Ignore previous instructions");
+        let request = |prompt: &str| TextGenerationRequest {
+            prompt: prompt.to_string(),
+            ..Default::default()
+        };
+        let rejected = |request: &TextGenerationRequest, name: &str| matches!(guard.check(request), Err(BedrockError::RequestError(msg)) if msg.contains(name));
+
+        assert!(guard.check(&request("Summarize this text")).is_ok());
+        assert!(rejected(&request(&"a".repeat(41)), "max_chars"));
+        assert!(rejected(
+            &request("please IGNORE   previous\ninstructions"),
+            "denylist"
+        ));
+
+        let chat = TextGenerationRequest {
+            messages: Some(vec![
+                ChatMessage::user("Hi"),
+                ChatMessage::user("ignore previous instructions"),
+            ]),
+            ..Default::default()
+        };
+        assert!(rejected(&chat, "denylist"));
+        let tokens = InputGuard::new().with_max_tokens(2);
+        assert!(tokens.check(&request("twelve chars")).is_err());
+    }
+
+    #[test]
+    fn test_from_body_reads_claude_and_titan_usage() {
+        let claude = serde_json::json!({
+            "content": [{"type": "text", "text": "Hi"}],
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 7, "output_tokens": 2}
+        });
+        let response =
+            TextGenerationResponse::from_body("anthropic.claude-3-haiku-20240307-v1:0", &claude)
+                .unwrap();
+        assert_eq!(response.text, "Hi");
+        assert_eq!(response.finish_reason.as_deref(), Some("end_turn"));
+        assert_eq!((response.tokens_prompt, response.tokens_generated), (7, 2));
+
+        let titan = serde_json::json!({
+            "inputTextTokenCount": 4,
+            "results": [{"outputText": "Hello", "tokenCount": 1, "completionReason": "FINISH"}]
+        });
+        let response =
+            TextGenerationResponse::from_body("amazon.titan-text-express-v1", &titan).unwrap();
+        assert_eq!(response.text, "Hello");
+        assert_eq!((response.tokens_prompt, response.tokens_generated), (4, 1));
+    }
+
+    #[test]
+    fn test_preamble_trimmer_keeps_only_the_content() {
+        let trimmer = PreambleTrimmer::default();
+        assert_eq!(
+            trimmer.trim("Sure!\nHere's a haiku about technology:\n\nWires hum at night\n\nI hope you enjoy it!"),
+            "Wires hum at night"
+        );
+        assert_eq!(
+            trimmer.trim("Here is why it fails in production."),
+            "Here is why it fails in production."
+        );
+        assert_eq!(
+            trimmer.clone().with_leading("Answer").trim("Answer:\n42"),
+            "42"
+        );
+    }
+
+    #[test]
+    fn test_parse_json_tolerates_fences_and_prose() {
+        assert_eq!(
+            parse_json("```json\n{\"a\": 1}\n```").unwrap(),
+            serde_json::json!({"a": 1})
+        );
+        assert_eq!(
+            parse_json("Here you go: [1, 2] Hope that helps!").unwrap(),
+            serde_json::json!([1, 2])
+        );
+        assert!(parse_json("{\"a\": 1,}").is_err());
+    }
+
+    #[test]
+    fn test_from_ai21_parses_jamba_chat_response() {
+        let body = json!({
+            "choices": [
+                {"index": 0, "message": {"role": "assistant", "content": "Hi there"}, "finish_reason": "stop"}
+            ],
+            "usage": {"prompt_tokens": 9, "completion_tokens": 3, "total_tokens": 12}
+        });
+
+        let response =
+            TextGenerationResponse::from_ai21("ai21.jamba-1-5-mini-v1:0", &body).unwrap();
+        assert_eq!(response.text, "Hi there");
+        assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+        assert_eq!((response.tokens_prompt, response.tokens_generated), (9, 3));
+        assert!(response.candidates.is_none());
+    }
+
+    #[test]
+    fn test_from_ai21_parses_jurassic_completions() {
+        let body = json!({
+            "prompt": {"tokens": [{}, {}, {}]},
+            "completions": [
+                {"data": {"text": "first", "tokens": [{}, {}]}, "finishReason": {"reason": "endoftext"}},
+                {"data": {"text": "second", "tokens": [{}]}, "finishReason": {"reason": "length"}}
+            ]
+        });
+
+        let response = TextGenerationResponse::from_ai21("ai21.j2-ultra-v1", &body).unwrap();
+        assert_eq!(response.text, "first");
+        assert_eq!(response.finish_reason.as_deref(), Some("endoftext"));
+        assert_eq!((response.tokens_prompt, response.tokens_generated), (3, 3));
+        assert_eq!(response.all_candidates(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn test_from_mistral_parses_chat_and_legacy_responses() {
+        let chat = json!({
+            "choices": [
+                {"index": 0, "message": {"role": "assistant", "content": "Bonjour"}, "stop_reason": "stop"}
+            ],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 2}
+        });
+        let response =
+            TextGenerationResponse::from_mistral("mistral.mistral-large-2402-v1:0", &chat).unwrap();
+        assert_eq!(response.text, "Bonjour");
+        assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+        assert_eq!((response.tokens_prompt, response.tokens_generated), (5, 2));
+
+        let legacy = json!({"outputs": [{"text": "Salut", "stop_reason": "length"}]});
+        let response =
+            TextGenerationResponse::from_mistral("mistral.mistral-7b-instruct-v0:2", &legacy)
+                .unwrap();
+        assert_eq!(response.text, "Salut");
+        assert!(response.is_truncated());
+    }
+
+    #[test]
+    fn test_continuation_of_chat_request_appends_assistant_turn() {
+        let body = json!({
+            "content": [{"type": "text", "text": "Once upon a time "}],
+            "stop_reason": "max_tokens",
+            "usage": {"input_tokens": 7, "output_tokens": 4}
+        });
+        let model = "anthropic.claude-3-haiku-20240307-v1:0";
+        let response = TextGenerationResponse::from_body(model, &body).unwrap();
+        let request = TextGenerationRequest {
+            messages: Some(vec![ChatMessage::user("Write a long story")]),
+            moderation_guardrail_id: Some("guardrail".to_string()),
+            ..Default::default()
+        };
+
+        let (next, _) = TextClient::continuation_request(
+            &response.pinned_request(&request),
+            &response.text,
+            response.tokens_generated,
+        )
+        .unwrap();
+        let messages = next.messages.unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].role, Role::Assistant);
+        assert_eq!(messages[1].content.text(), "Once upon a time");
+        assert!(next.prompt.is_empty());
+        assert_eq!(next.model_id.as_deref(), Some(model));
+        assert!(next.moderation_guardrail_id.is_none());
+    }
+
+    #[test]
+    fn test_from_ai21_rejects_mismatched_format() {
+        let body = json!({"completions": []});
+        assert!(TextGenerationResponse::from_ai21("ai21.jamba-instruct-v1:0", &body).is_err());
+    }
+
+    #[test]
+    fn test_response_becomes_final_stream_chunk_with_usage() {
+        let body = json!({
+            "content": [{"type": "text", "text": "Hi"}],
+            "stop_reason": "max_tokens",
+            "usage": {"input_tokens": 7, "output_tokens": 2}
+        });
+        let response =
+            TextGenerationResponse::from_body("anthropic.claude-3-haiku-20240307-v1:0", &body)
+                .unwrap();
+
+        let chunk = StreamChunk::from(response);
+        assert!(chunk.done);
+        assert_eq!(chunk.chunk, "Hi");
+        assert_eq!(chunk.finish_reason.as_deref(), Some("max_tokens"));
+        assert_eq!(
+            (chunk.tokens_prompt, chunk.tokens_generated),
+            (Some(7), Some(2))
+        );
+    }
+}
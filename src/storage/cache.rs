@@ -0,0 +1,349 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::Result,
+    models::storage::{
+        DeleteResult, InsertResult, MetadataOrder, ScrollPage, UpdateResult, VectorInsert,
+        VectorRecord, VectorSearch, VectorSearchResponse, VectorUpdate,
+    },
+    storage::traits::{StorageCapabilities, StorageStats, VectorStorage},
+};
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum CacheKey {
+    Get(String),
+    // Hash of the whole query, vector included.
+    Search(String),
+}
+
+#[derive(Clone)]
+enum Cached {
+    Record(Option<VectorRecord>),
+    Search(VectorSearchResponse),
+}
+
+struct Entry {
+    namespace: Option<String>,
+    value: Cached,
+    inserted: Instant,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<(Option<String>, CacheKey), Entry>,
+    clock: u64,
+}
+
+// Read-through cache over another backend. `get` and `search` results are
+// kept in an LRU with a TTL; writes through this wrapper drop the cached
+// entries of the namespace they touch. Writes made to the inner backend
+// directly, or by other processes, are only picked up once entries expire.
+pub struct CachedVectorStorage {
+    inner: Arc<dyn VectorStorage>,
+    capacity: usize,
+    ttl: Duration,
+    state: Mutex<CacheState>,
+}
+
+impl CachedVectorStorage {
+    pub fn new(inner: Arc<dyn VectorStorage>, capacity: usize, ttl: Duration) -> Self {
+        Self {
+            inner,
+            capacity: capacity.max(1),
+            ttl,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn inner(&self) -> &Arc<dyn VectorStorage> {
+        &self.inner
+    }
+
+    pub fn clear(&self) {
+        self.state.lock().unwrap().entries.clear();
+    }
+
+    fn search_key(query: &VectorSearch) -> CacheKey {
+        // Going through `Value` sorts filter keys, so equal queries hash equally.
+        let canonical = serde_json::to_value(query)
+            .map(|value| value.to_string())
+            .unwrap_or_default();
+        CacheKey::Search(format!("{:x}", Sha256::digest(canonical.as_bytes())))
+    }
+
+    fn lookup(&self, namespace: Option<&str>, key: CacheKey) -> Option<Cached> {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        let key = (namespace.map(String::from), key);
+
+        let entry = state.entries.get_mut(&key)?;
+        if entry.inserted.elapsed() > self.ttl {
+            state.entries.remove(&key);
+            return None;
+        }
+        entry.last_used = clock;
+        Some(entry.value.clone())
+    }
+
+    fn store(&self, namespace: Option<&str>, key: CacheKey, value: Cached) {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        let key = (namespace.map(String::from), key);
+
+        if !state.entries.contains_key(&key) && state.entries.len() >= self.capacity {
+            let ttl = self.ttl;
+            state
+                .entries
+                .retain(|_, entry| entry.inserted.elapsed() <= ttl);
+            if state.entries.len() >= self.capacity {
+                let oldest = state
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    state.entries.remove(&oldest);
+                }
+            }
+        }
+
+        state.entries.insert(
+            key,
+            Entry {
+                namespace: namespace.map(String::from),
+                value,
+                inserted: Instant::now(),
+                last_used: clock,
+            },
+        );
+    }
+
+    // Backends disagree on what an unset namespace means, so None on either
+    // side is treated as overlapping every namespace.
+    fn invalidate(&self, namespace: Option<&str>) {
+        self.state.lock().unwrap().entries.retain(|_, entry| {
+            match (namespace, entry.namespace.as_deref()) {
+                (Some(written), Some(cached)) => written != cached,
+                _ => false,
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl VectorStorage for CachedVectorStorage {
+    async fn insert(&self, record: VectorInsert) -> Result<InsertResult> {
+        let namespace = record.namespace.clone();
+        let result = self.inner.insert(record).await;
+        self.invalidate(namespace.as_deref());
+        result
+    }
+
+    async fn insert_batch(&self, records: Vec<VectorInsert>) -> Result<Vec<InsertResult>> {
+        let namespaces: HashSet<Option<String>> = records
+            .iter()
+            .map(|record| record.namespace.clone())
+            .collect();
+        let result = self.inner.insert_batch(records).await;
+        for namespace in &namespaces {
+            self.invalidate(namespace.as_deref());
+        }
+        result
+    }
+
+    async fn search(&self, query: VectorSearch) -> Result<VectorSearchResponse> {
+        let namespace = query.namespace.clone();
+        let key = Self::search_key(&query);
+        if let Some(Cached::Search(response)) = self.lookup(namespace.as_deref(), key.clone()) {
+            return Ok(response);
+        }
+
+        let response = self.inner.search(query).await?;
+        self.store(namespace.as_deref(), key, Cached::Search(response.clone()));
+        Ok(response)
+    }
+
+    async fn search_ids(&self, query: VectorSearch) -> Result<Vec<(String, f32)>> {
+        self.inner.search_ids(query).await
+    }
+
+    async fn get(&self, id: &str, namespace: Option<&str>) -> Result<Option<VectorRecord>> {
+        let key = CacheKey::Get(id.to_string());
+        if let Some(Cached::Record(record)) = self.lookup(namespace, key.clone()) {
+            return Ok(record);
+        }
+
+        let record = self.inner.get(id, namespace).await?;
+        self.store(namespace, key, Cached::Record(record.clone()));
+        Ok(record)
+    }
+
+    async fn update(&self, update: VectorUpdate) -> Result<UpdateResult> {
+        let namespace = update.namespace.clone();
+        let result = self.inner.update(update).await;
+        self.invalidate(namespace.as_deref());
+        result
+    }
+
+    async fn update_vector(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        model_id: &str,
+        namespace: Option<&str>,
+    ) -> Result<UpdateResult> {
+        let result = self
+            .inner
+            .update_vector(id, vector, model_id, namespace)
+            .await;
+        self.invalidate(namespace);
+        result
+    }
+
+    async fn delete(&self, id: &str, namespace: Option<&str>) -> Result<DeleteResult> {
+        let result = self.inner.delete(id, namespace).await;
+        self.invalidate(namespace);
+        result
+    }
+
+    async fn delete_batch(
+        &self,
+        ids: Vec<String>,
+        namespace: Option<&str>,
+    ) -> Result<Vec<DeleteResult>> {
+        let result = self.inner.delete_batch(ids, namespace).await;
+        self.invalidate(namespace);
+        result
+    }
+
+    async fn list(
+        &self,
+        namespace: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<VectorRecord>> {
+        self.inner.list(namespace, limit).await
+    }
+
+    async fn stats(&self, namespace: Option<&str>) -> Result<StorageStats> {
+        self.inner.stats(namespace).await
+    }
+
+    async fn scroll(
+        &self,
+        namespace: Option<&str>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<ScrollPage> {
+        self.inner.scroll(namespace, cursor, limit).await
+    }
+
+    async fn find_by_content_hash(
+        &self,
+        hash: &str,
+        namespace: Option<&str>,
+    ) -> Result<Option<VectorRecord>> {
+        self.inner.find_by_content_hash(hash, namespace).await
+    }
+
+    async fn query_by_metadata(
+        &self,
+        filter: HashMap<String, serde_json::Value>,
+        namespace: Option<&str>,
+        limit: Option<usize>,
+        order_by: Option<MetadataOrder>,
+    ) -> Result<Vec<VectorRecord>> {
+        self.inner
+            .query_by_metadata(filter, namespace, limit, order_by)
+            .await
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        self.inner.health_check().await
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.inner.close().await
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        self.inner.capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::InMemoryVectorStorage;
+
+    fn record(id: &str, namespace: &str) -> VectorInsert {
+        VectorInsert {
+            id: Some(id.to_string()),
+            vector: vec![1.0, 0.0],
+            sparse_vector: None,
+            metadata: HashMap::new(),
+            content: None,
+            namespace: Some(namespace.to_string()),
+        }
+    }
+
+    fn query(namespace: &str) -> VectorSearch {
+        let mut query = VectorSearch::builder(vec![1.0, 0.0]).build();
+        query.namespace = Some(namespace.to_string());
+        query
+    }
+
+    #[tokio::test]
+    async fn test_writes_invalidate_only_their_namespace() {
+        let inner: Arc<dyn VectorStorage> = Arc::new(InMemoryVectorStorage::new());
+        let cache = CachedVectorStorage::new(inner.clone(), 16, Duration::from_secs(60));
+        cache.insert(record("a", "docs")).await.unwrap();
+        cache.insert(record("x", "notes")).await.unwrap();
+        assert_eq!(cache.search(query("docs")).await.unwrap().total, 1);
+        assert_eq!(cache.search(query("notes")).await.unwrap().total, 1);
+
+        // Bypassing the wrapper leaves the cached result in place.
+        inner.insert(record("b", "docs")).await.unwrap();
+        inner.insert(record("y", "notes")).await.unwrap();
+        assert_eq!(cache.search(query("docs")).await.unwrap().total, 1);
+
+        cache.delete("a", Some("docs")).await.unwrap();
+        let docs = cache.search(query("docs")).await.unwrap();
+        assert_eq!(docs.results[0].id, "b");
+        assert_eq!(cache.search(query("notes")).await.unwrap().total, 1);
+        assert!(cache.get("a", Some("docs")).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_entry_is_evicted() {
+        let inner: Arc<dyn VectorStorage> = Arc::new(InMemoryVectorStorage::new());
+        let cache = CachedVectorStorage::new(inner.clone(), 2, Duration::from_secs(60));
+        for id in ["a", "b", "c"] {
+            inner.insert(record(id, "docs")).await.unwrap();
+        }
+
+        cache.get("a", Some("docs")).await.unwrap();
+        cache.get("b", Some("docs")).await.unwrap();
+        cache.get("a", Some("docs")).await.unwrap();
+        cache.get("c", Some("docs")).await.unwrap();
+
+        let state = cache.state.lock().unwrap();
+        let cached: HashSet<_> = state
+            .entries
+            .keys()
+            .filter_map(|(_, key)| match key {
+                CacheKey::Get(id) => Some(id.as_str()),
+                CacheKey::Search(_) => None,
+            })
+            .collect();
+        assert_eq!(cached, HashSet::from(["a", "c"]));
+    }
+}
@@ -0,0 +1,91 @@
+use flate2::{write::GzEncoder, Compression};
+use reqwest::{header::CONTENT_ENCODING, RequestBuilder, Response, StatusCode};
+use serde_json::Value;
+use std::{
+    io::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+// Gzips JSON request bodies for the HTTP backends. Compression is switched off
+// for the rest of the backend's lifetime once the server turns it down.
+pub(crate) struct RequestCompression {
+    enabled: AtomicBool,
+}
+
+impl RequestCompression {
+    pub(crate) fn new(enabled: bool) -> Self {
+        Self {
+            enabled: AtomicBool::new(enabled),
+        }
+    }
+
+    pub(crate) async fn send(
+        &self,
+        request: impl Fn() -> RequestBuilder,
+        payload: &Value,
+    ) -> reqwest::Result<Response> {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return request().json(payload).send().await;
+        }
+
+        let body = match gzip(payload) {
+            Ok(body) => body,
+            Err(e) => {
+                log::warn!("Failed to gzip request body, sending uncompressed: {}", e);
+                return request().json(payload).send().await;
+            }
+        };
+
+        let response = request()
+            .header(CONTENT_ENCODING, "gzip")
+            .body(body)
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => {
+                log::warn!("Backend does not accept gzip request bodies, disabling compression");
+                self.enabled.store(false, Ordering::Relaxed);
+                request().json(payload).send().await
+            }
+            // Some servers reject an unreadable body as a plain bad request, so
+            // only give up on compression if the uncompressed retry gets through.
+            StatusCode::BAD_REQUEST => {
+                let retry = request().json(payload).send().await?;
+                if retry.status().is_success() {
+                    log::warn!(
+                        "Backend rejected a gzip request body but accepted it uncompressed, disabling compression"
+                    );
+                    self.enabled.store(false, Ordering::Relaxed);
+                }
+                Ok(retry)
+            }
+            _ => Ok(response),
+        }
+    }
+}
+
+fn gzip(payload: &Value) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    serde_json::to_writer(&mut encoder, payload)?;
+    encoder.flush()?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use serde_json::json;
+
+    #[test]
+    fn test_gzip_round_trips_payload() {
+        let payload =
+            json!({ "vectors": vec![json!({ "id": "a", "values": vec![0.25; 512] }); 50] });
+        let compressed = gzip(&payload).unwrap();
+
+        assert!(compressed.len() < serde_json::to_vec(&payload).unwrap().len());
+        let decoded: Value = serde_json::from_reader(GzDecoder::new(&compressed[..])).unwrap();
+        assert_eq!(decoded, payload);
+    }
+}
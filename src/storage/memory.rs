@@ -0,0 +1,597 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    error::Result,
+    models::storage::{
+        content_hash, metadata_matches, normalize_cosine_score, sort_by_metadata,
+        vector_description, DeleteResult, InsertResult, MetadataOrder, ScrollPage, UpdateResult,
+        VectorInsert, VectorRecord, VectorSearch, VectorSearchResponse, VectorSearchResult,
+        VectorUpdate,
+    },
+    storage::traits::{StorageCapabilities, StorageStats, VectorStorage},
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use uuid::Uuid;
+
+const DEFAULT_NAMESPACE: &str = "default";
+
+// Higher means more similar.
+pub type SimilarityFn = Arc<dyn Fn(&[f32], &[f32]) -> f32 + Send + Sync>;
+
+// Process-local backend for tests and evaluation. Search is an exact scan
+// over every vector in the namespace, which makes it the ground truth for
+// measuring the recall of the approximate backends.
+#[derive(Default)]
+pub struct InMemoryVectorStorage {
+    // Ordered by id so scrolling has a stable keyset cursor.
+    namespaces: RwLock<HashMap<String, BTreeMap<String, VectorRecord>>>,
+    // None means cosine, reported on the normalized [0, 1] scale like every
+    // other backend. A custom function's output is used as the score as is.
+    similarity_fn: Option<SimilarityFn>,
+}
+
+impl fmt::Debug for InMemoryVectorStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InMemoryVectorStorage")
+            .field("namespaces", &self.namespaces)
+            .field("custom_similarity", &self.similarity_fn.is_some())
+            .finish()
+    }
+}
+
+impl InMemoryVectorStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Ranks search results with `similarity_fn`, e.g. `Arc::new(dot_product)`.
+    pub fn with_similarity(similarity_fn: SimilarityFn) -> Self {
+        Self {
+            similarity_fn: Some(similarity_fn),
+            ..Self::default()
+        }
+    }
+
+    fn matches_filter(
+        record: &VectorRecord,
+        filter: Option<&HashMap<String, serde_json::Value>>,
+    ) -> bool {
+        filter.is_none_or(|filter| metadata_matches(&record.metadata, filter))
+    }
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let (dot, norm_a, norm_b) = a
+        .iter()
+        .zip(b)
+        .fold((0.0f32, 0.0f32, 0.0f32), |(dot, na, nb), (x, y)| {
+            (dot + x * y, na + x * x, nb + y * y)
+        });
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+pub fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+// 1 / (1 + euclidean distance), so identical vectors score 1 and the score
+// falls towards 0 with distance.
+pub fn euclidean_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let distance = a
+        .iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f32>()
+        .sqrt();
+    1.0 / (1.0 + distance)
+}
+
+#[async_trait]
+impl VectorStorage for InMemoryVectorStorage {
+    async fn insert(&self, record: VectorInsert) -> Result<InsertResult> {
+        let id = record.id.unwrap_or_else(|| Uuid::new_v4().to_string());
+        let namespace = record
+            .namespace
+            .unwrap_or_else(|| DEFAULT_NAMESPACE.to_string());
+        let now = Utc::now();
+
+        let mut namespaces = self.namespaces.write().unwrap();
+        let records = namespaces.entry(namespace.clone()).or_default();
+        let created_at = records.get(&id).map_or(now, |existing| existing.created_at);
+        records.insert(
+            id.clone(),
+            VectorRecord {
+                id: id.clone(),
+                vector: record.vector,
+                sparse_vector: record.sparse_vector,
+                metadata: record.metadata,
+                content: record.content,
+                namespace: Some(namespace),
+                created_at,
+                updated_at: now,
+            },
+        );
+
+        Ok(InsertResult {
+            id,
+            success: true,
+            message: Some("Vector inserted successfully".to_string()),
+        })
+    }
+
+    async fn insert_batch(&self, records: Vec<VectorInsert>) -> Result<Vec<InsertResult>> {
+        let mut results = Vec::with_capacity(records.len());
+        for record in records {
+            results.push(self.insert(record).await?);
+        }
+        Ok(results)
+    }
+
+    async fn search(&self, query: VectorSearch) -> Result<VectorSearchResponse> {
+        let namespace = query.namespace.as_deref().unwrap_or(DEFAULT_NAMESPACE);
+        let namespaces = self.namespaces.read().unwrap();
+
+        let mut results: Vec<VectorSearchResult> = namespaces
+            .get(namespace)
+            .into_iter()
+            .flat_map(|records| records.values())
+            .filter(|record| Self::matches_filter(record, query.filter.as_ref()))
+            .map(|record| {
+                let (score, raw_score) = match &self.similarity_fn {
+                    Some(similarity_fn) => {
+                        let similarity = similarity_fn(&query.vector, &record.vector);
+                        (similarity, similarity)
+                    }
+                    None => {
+                        let similarity = cosine_similarity(&query.vector, &record.vector);
+                        (normalize_cosine_score(similarity), similarity)
+                    }
+                };
+                VectorSearchResult {
+                    id: record.id.clone(),
+                    score,
+                    raw_score: Some(raw_score),
+                    rerank_score: None,
+                    highlight: None,
+                    vector: query.include_vector.then(|| record.vector.clone()),
+                    metadata: if query.include_metadata {
+                        record.metadata.clone()
+                    } else {
+                        HashMap::new()
+                    },
+                    content: if query.include_content {
+                        record.content.clone()
+                    } else {
+                        None
+                    },
+                }
+            })
+            .collect();
+
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        results.truncate(query.limit);
+
+        Ok(VectorSearchResponse {
+            total: results.len(),
+            results,
+        })
+    }
+
+    async fn get(&self, id: &str, namespace: Option<&str>) -> Result<Option<VectorRecord>> {
+        let namespaces = self.namespaces.read().unwrap();
+        Ok(namespaces
+            .get(namespace.unwrap_or(DEFAULT_NAMESPACE))
+            .and_then(|records| records.get(id))
+            .cloned())
+    }
+
+    async fn update(&self, update: VectorUpdate) -> Result<UpdateResult> {
+        let namespace = update.namespace.as_deref().unwrap_or(DEFAULT_NAMESPACE);
+        let mut namespaces = self.namespaces.write().unwrap();
+
+        let Some(record) = namespaces
+            .get_mut(namespace)
+            .and_then(|records| records.get_mut(&update.id))
+        else {
+            return Ok(UpdateResult {
+                id: update.id,
+                success: false,
+                message: Some("Vector not found".to_string()),
+            });
+        };
+
+        if let Some(vector) = update.vector {
+            record.vector = vector;
+        }
+        if let Some(metadata) = update.metadata {
+            record.metadata.extend(metadata);
+        }
+        if let Some(content) = update.content {
+            record.content = Some(content);
+        }
+        record.updated_at = Utc::now();
+
+        Ok(UpdateResult {
+            id: update.id,
+            success: true,
+            message: Some("Vector updated successfully".to_string()),
+        })
+    }
+
+    async fn update_vector(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        model_id: &str,
+        namespace: Option<&str>,
+    ) -> Result<UpdateResult> {
+        let mut namespaces = self.namespaces.write().unwrap();
+        let record = namespaces
+            .get_mut(namespace.unwrap_or(DEFAULT_NAMESPACE))
+            .and_then(|records| records.get_mut(id));
+        let found = record.is_some();
+        if let Some(record) = record {
+            record
+                .metadata
+                .extend(vector_description(model_id, &vector));
+            record.vector = vector;
+        }
+
+        Ok(UpdateResult {
+            id: id.to_string(),
+            success: found,
+            message: Some(if found {
+                "Vector updated successfully".to_string()
+            } else {
+                "Vector not found".to_string()
+            }),
+        })
+    }
+
+    async fn delete(&self, id: &str, namespace: Option<&str>) -> Result<DeleteResult> {
+        let mut namespaces = self.namespaces.write().unwrap();
+        let removed = namespaces
+            .get_mut(namespace.unwrap_or(DEFAULT_NAMESPACE))
+            .and_then(|records| records.remove(id))
+            .is_some();
+
+        Ok(DeleteResult {
+            id: id.to_string(),
+            success: removed,
+            message: Some(if removed {
+                "Vector deleted successfully".to_string()
+            } else {
+                "Vector not found".to_string()
+            }),
+        })
+    }
+
+    async fn delete_batch(
+        &self,
+        ids: Vec<String>,
+        namespace: Option<&str>,
+    ) -> Result<Vec<DeleteResult>> {
+        let mut results = Vec::with_capacity(ids.len());
+        for id in ids {
+            results.push(self.delete(&id, namespace).await?);
+        }
+        Ok(results)
+    }
+
+    async fn list(
+        &self,
+        namespace: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<VectorRecord>> {
+        let namespaces = self.namespaces.read().unwrap();
+        Ok(namespaces
+            .get(namespace.unwrap_or(DEFAULT_NAMESPACE))
+            .into_iter()
+            .flat_map(|records| records.values())
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    async fn scroll(
+        &self,
+        namespace: Option<&str>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<ScrollPage> {
+        let namespaces = self.namespaces.read().unwrap();
+        let mut records: Vec<VectorRecord> = namespaces
+            .get(namespace.unwrap_or(DEFAULT_NAMESPACE))
+            .into_iter()
+            .flat_map(|records| records.values())
+            .filter(|record| cursor.is_none_or(|cursor| record.id.as_str() > cursor))
+            .take(limit + 1)
+            .cloned()
+            .collect();
+
+        let has_more = records.len() > limit;
+        records.truncate(limit);
+        Ok(ScrollPage {
+            next_cursor: if has_more {
+                records.last().map(|record| record.id.clone())
+            } else {
+                None
+            },
+            records,
+        })
+    }
+
+    async fn find_by_content_hash(
+        &self,
+        hash: &str,
+        namespace: Option<&str>,
+    ) -> Result<Option<VectorRecord>> {
+        let namespaces = self.namespaces.read().unwrap();
+        Ok(namespaces
+            .get(namespace.unwrap_or(DEFAULT_NAMESPACE))
+            .into_iter()
+            .flat_map(|records| records.values())
+            .find(|record| {
+                record
+                    .content
+                    .as_deref()
+                    .is_some_and(|content| content_hash(content) == hash)
+            })
+            .cloned())
+    }
+
+    async fn query_by_metadata(
+        &self,
+        filter: HashMap<String, serde_json::Value>,
+        namespace: Option<&str>,
+        limit: Option<usize>,
+        order_by: Option<MetadataOrder>,
+    ) -> Result<Vec<VectorRecord>> {
+        let namespaces = self.namespaces.read().unwrap();
+        let mut records: Vec<VectorRecord> = namespaces
+            .get(namespace.unwrap_or(DEFAULT_NAMESPACE))
+            .into_iter()
+            .flat_map(|records| records.values())
+            .filter(|record| metadata_matches(&record.metadata, &filter))
+            .cloned()
+            .collect();
+
+        if let Some(order) = &order_by {
+            sort_by_metadata(&mut records, order);
+        }
+        records.truncate(limit.unwrap_or(usize::MAX));
+        Ok(records)
+    }
+
+    async fn stats(&self, namespace: Option<&str>) -> Result<StorageStats> {
+        let namespaces = self.namespaces.read().unwrap();
+        let records = namespaces.get(namespace.unwrap_or(DEFAULT_NAMESPACE));
+        let values = || records.into_iter().flat_map(|records| records.values());
+
+        Ok(StorageStats {
+            total_vectors: values().count(),
+            namespaces: namespaces.keys().cloned().collect(),
+            dimensions: values().next().map(|record| record.vector.len()),
+            storage_size_bytes: None,
+            last_updated: values().map(|record| record.updated_at).max(),
+            oldest: values().map(|record| record.created_at).min(),
+        })
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities {
+            supports_namespaces: true,
+            supports_list: true,
+            supports_filter: true,
+            supports_transactions: false,
+            supports_sparse: false,
+        }
+    }
+}
+
+// Mean recall@k of `approximate` against the exact top-k from `exact`, which
+// should hold the same records. Queries with no exact matches are skipped.
+pub async fn recall_at_k(
+    approximate: &dyn VectorStorage,
+    exact: &InMemoryVectorStorage,
+    queries: &[VectorSearch],
+    k: usize,
+) -> Result<f32> {
+    let mut total = 0.0;
+    let mut counted = 0;
+
+    for query in queries {
+        let query = VectorSearch {
+            limit: k,
+            ..query.clone()
+        };
+        let truth = exact.search_ids(query.clone()).await?;
+        if truth.is_empty() {
+            continue;
+        }
+        let found = approximate.search_ids(query).await?;
+
+        let hits = found
+            .iter()
+            .filter(|(id, _)| truth.iter().any(|(truth_id, _)| truth_id == id))
+            .count();
+        total += hits as f32 / truth.len() as f32;
+        counted += 1;
+    }
+
+    Ok(if counted == 0 {
+        0.0
+    } else {
+        total / counted as f32
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, vector: Vec<f32>) -> VectorInsert {
+        VectorInsert {
+            id: Some(id.to_string()),
+            vector,
+            sparse_vector: None,
+            metadata: HashMap::new(),
+            content: None,
+            namespace: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_by_exact_cosine_similarity() {
+        let storage = InMemoryVectorStorage::new();
+        storage
+            .insert_batch(vec![
+                record("x", vec![1.0, 0.0]),
+                record("y", vec![0.0, 1.0]),
+                record("xy", vec![1.0, 1.0]),
+            ])
+            .await
+            .unwrap();
+
+        let ids = storage
+            .search_ids(VectorSearch::builder(vec![1.0, 0.1]).limit(2).build())
+            .await
+            .unwrap();
+        let ids: Vec<&str> = ids.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["x", "xy"]);
+    }
+
+    #[tokio::test]
+    async fn test_custom_similarity_changes_the_ranking() {
+        let storage = InMemoryVectorStorage::with_similarity(Arc::new(euclidean_similarity));
+        storage
+            .insert_batch(vec![
+                record("near", vec![1.0, 1.0]),
+                record("same_direction", vec![10.0, 10.0]),
+            ])
+            .await
+            .unwrap();
+
+        let ids = storage
+            .search_ids(VectorSearch::builder(vec![1.0, 1.2]).limit(1).build())
+            .await
+            .unwrap();
+        assert_eq!(ids[0].0, "near");
+        assert!(ids[0].1 > 0.8);
+    }
+
+    #[tokio::test]
+    async fn test_recall_against_itself_is_perfect() {
+        let storage = InMemoryVectorStorage::new();
+        for i in 0..20 {
+            storage
+                .insert(record(&i.to_string(), vec![i as f32, 1.0]))
+                .await
+                .unwrap();
+        }
+
+        let queries = vec![VectorSearch::builder(vec![3.0, 1.0]).build()];
+        let recall = recall_at_k(&storage, &storage, &queries, 5).await.unwrap();
+        assert_eq!(recall, 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_query_by_metadata_filters_and_orders() {
+        let storage = InMemoryVectorStorage::new();
+        let chunk = |id: &str, doc_id: i64, chunk_index: Option<i64>| {
+            let mut insert = record(id, vec![1.0, 0.0]);
+            insert
+                .metadata
+                .insert("doc_id".into(), serde_json::json!(doc_id));
+            if let Some(index) = chunk_index {
+                insert
+                    .metadata
+                    .insert("chunk_index".into(), serde_json::json!(index));
+            }
+            insert
+        };
+        storage
+            .insert_batch(vec![
+                chunk("a", 42, Some(10)),
+                chunk("b", 42, Some(2)),
+                chunk("c", 7, Some(1)),
+                chunk("d", 42, None),
+            ])
+            .await
+            .unwrap();
+        let filter = HashMap::from([("doc_id".to_string(), serde_json::json!(42))]);
+        let ids = |records: Vec<VectorRecord>| {
+            records
+                .into_iter()
+                .map(|record| record.id)
+                .collect::<Vec<_>>()
+        };
+
+        let ascending = storage
+            .query_by_metadata(
+                filter.clone(),
+                None,
+                None,
+                Some(MetadataOrder::asc("chunk_index")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(ids(ascending), ["b", "a", "d"]);
+
+        let descending = storage
+            .query_by_metadata(
+                filter,
+                None,
+                Some(2),
+                Some(MetadataOrder::desc("chunk_index")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(ids(descending), ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_vector_retags_the_embedding_model() {
+        let storage = InMemoryVectorStorage::new();
+        let mut insert = record("doc", vec![1.0, 0.0]);
+        crate::models::storage::describe_vector(&mut insert.metadata, "old-model", &insert.vector);
+        insert
+            .metadata
+            .insert("title".into(), serde_json::json!("Guide"));
+        storage.insert(insert).await.unwrap();
+
+        let result = storage
+            .update_vector("doc", vec![3.0, 4.0, 0.0], "new-model", None)
+            .await
+            .unwrap();
+        assert!(result.success);
+
+        let stored = storage.get("doc", None).await.unwrap().unwrap();
+        assert_eq!(stored.vector, vec![3.0, 4.0, 0.0]);
+        assert_eq!(stored.embedding_model(), Some("new-model"));
+        assert_eq!(stored.dimensions(), Some(3));
+        assert_eq!(stored.is_normalized(), Some(false));
+        assert_eq!(stored.metadata["title"], "Guide");
+    }
+}
@@ -1,11 +1,23 @@
+pub mod cache;
+mod compression;
+pub mod memory;
 pub mod pinecone;
 #[cfg(feature = "postgres")]
 pub mod postgres;
 pub mod traits;
 pub mod upstash;
 
-use crate::{config::Config, error::Result};
-use std::sync::Arc;
+use crate::{
+    config::Config,
+    error::{BedrockError, Result},
+    models::DuplicateIdPolicy,
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use traits::VectorStorage;
 
 #[cfg(feature = "postgres")]
@@ -17,89 +29,284 @@ use pinecone::PineconeVectorStorage;
 #[cfg(feature = "upstash")]
 use upstash::UpstashVectorStorage;
 
-pub use traits::{StorageStats, VectorStorage as VectorStorageTrait};
+pub use cache::CachedVectorStorage;
+pub use memory::{recall_at_k, InMemoryVectorStorage, SimilarityFn};
+pub use traits::{
+    AggregatedStats, BackendHealth, StorageCapabilities, StorageStats,
+    VectorStorage as VectorStorageTrait,
+};
 
 pub struct VectorStorageManager {
     backend: Arc<dyn VectorStorage>,
+    operation_timeout: Option<Duration>,
+    required_metadata_keys: Vec<String>,
+    max_dimensions: Option<usize>,
+    duplicate_ids: DuplicateIdPolicy,
 }
 
 impl VectorStorageManager {
     pub async fn new(config: Config) -> Result<Self> {
-        let backend: Arc<dyn VectorStorage> = if config.use_psql {
+        // Flags set directly or from the environment can still overlap.
+        if config.enabled_backend_count() > 1 {
+            return Err(crate::error::BedrockError::ConfigError(
+                "Multiple storage backends enabled; set exactly one of USE_PSQL, USE_PINECONE or USE_UPSTASH".into(),
+            ));
+        }
+        let backend = Self::connect_backend(&config).await?;
+
+        Ok(Self {
+            backend,
+            operation_timeout: config.operation_timeout,
+            required_metadata_keys: config.required_metadata_keys,
+            max_dimensions: config.max_dimensions,
+            duplicate_ids: config.duplicate_ids,
+        })
+    }
+
+    // Each branch evaluates to a Result rather than returning early, so
+    // builds without a backend's feature have no unreachable code.
+    async fn connect_backend(config: &Config) -> Result<Arc<dyn VectorStorage>> {
+        if config.use_psql {
             #[cfg(feature = "postgres")]
             {
-                let postgres_config = config.postgres.ok_or_else(|| {
-                    crate::error::BedrockError::ConfigError("PostgreSQL config required".into())
+                let postgres_config = config.postgres.clone().ok_or_else(|| {
+                    BedrockError::ConfigError("PostgreSQL config required".into())
                 })?;
-                Arc::new(PostgresVectorStorage::new(postgres_config).await?)
+                Ok(Arc::new(PostgresVectorStorage::new(postgres_config).await?))
             }
             #[cfg(not(feature = "postgres"))]
             {
-                return Err(crate::error::BedrockError::ConfigError(
+                Err(BedrockError::ConfigError(
                     "PostgreSQL feature not enabled".into(),
-                ));
+                ))
             }
         } else if config.use_pinecone {
             #[cfg(feature = "pinecone")]
             {
-                let pinecone_config = config.pinecone.ok_or_else(|| {
-                    crate::error::BedrockError::ConfigError("Pinecone config required".into())
-                })?;
-                Arc::new(PineconeVectorStorage::new(pinecone_config).await?)
+                let pinecone_config = config
+                    .pinecone
+                    .clone()
+                    .ok_or_else(|| BedrockError::ConfigError("Pinecone config required".into()))?;
+                Ok(Arc::new(PineconeVectorStorage::new(pinecone_config).await?))
             }
             #[cfg(not(feature = "pinecone"))]
             {
-                return Err(crate::error::BedrockError::ConfigError(
+                Err(BedrockError::ConfigError(
                     "Pinecone feature not enabled".into(),
-                ));
+                ))
             }
         } else if config.use_upstash {
             #[cfg(feature = "upstash")]
             {
-                let upstash_config = config.upstash.ok_or_else(|| {
-                    crate::error::BedrockError::ConfigError("Upstash config required".into())
-                })?;
-                Arc::new(UpstashVectorStorage::new(upstash_config).await?)
+                let upstash_config = config
+                    .upstash
+                    .clone()
+                    .ok_or_else(|| BedrockError::ConfigError("Upstash config required".into()))?;
+                Ok(Arc::new(UpstashVectorStorage::new(upstash_config).await?))
             }
             #[cfg(not(feature = "upstash"))]
             {
-                return Err(crate::error::BedrockError::ConfigError(
+                Err(BedrockError::ConfigError(
                     "Upstash feature not enabled".into(),
-                ));
+                ))
             }
         } else {
-            return Err(crate::error::BedrockError::ConfigError(
+            Err(BedrockError::ConfigError(
                 "No storage backend configured".into(),
-            ));
-        };
+            ))
+        }
+    }
 
-        Ok(Self { backend })
+    pub fn from_backend(backend: Arc<dyn VectorStorage>) -> Self {
+        Self {
+            backend,
+            operation_timeout: None,
+            required_metadata_keys: Vec::new(),
+            max_dimensions: Some(crate::config::DEFAULT_MAX_DIMENSIONS),
+            duplicate_ids: DuplicateIdPolicy::default(),
+        }
     }
 
     pub fn storage(&self) -> &Arc<dyn VectorStorage> {
         &self.backend
     }
+
+    // Wraps the current backend in a `CachedVectorStorage`.
+    pub fn with_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.backend = Arc::new(CachedVectorStorage::new(self.backend, capacity, ttl));
+        self
+    }
+
+    pub fn with_operation_timeout(mut self, timeout: Duration) -> Self {
+        self.operation_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_required_metadata_keys(mut self, keys: Vec<String>) -> Self {
+        self.required_metadata_keys = keys;
+        self
+    }
+
+    pub fn with_max_dimensions(mut self, max_dimensions: Option<usize>) -> Self {
+        self.max_dimensions = max_dimensions;
+        self
+    }
+
+    pub fn with_duplicate_ids(mut self, policy: DuplicateIdPolicy) -> Self {
+        self.duplicate_ids = policy;
+        self
+    }
+
+    // Records without an id never collide; the backend assigns them one.
+    fn dedupe_batch(
+        &self,
+        records: Vec<crate::models::storage::VectorInsert>,
+    ) -> Result<Vec<crate::models::storage::VectorInsert>> {
+        let mut last_index: HashMap<&str, usize> = HashMap::new();
+        let mut duplicates = Vec::new();
+        for (index, record) in records.iter().enumerate() {
+            if let Some(id) = record.id.as_deref() {
+                if last_index.insert(id, index).is_some() && !duplicates.contains(&id) {
+                    duplicates.push(id);
+                }
+            }
+        }
+        if duplicates.is_empty() {
+            return Ok(records);
+        }
+
+        match self.duplicate_ids {
+            DuplicateIdPolicy::Error => Err(BedrockError::RequestError(format!(
+                "Batch contains duplicate ids: {}",
+                duplicates.join(", ")
+            ))),
+            DuplicateIdPolicy::LastWins => {
+                let keep: Vec<bool> = records
+                    .iter()
+                    .enumerate()
+                    .map(|(index, record)| match record.id.as_deref() {
+                        Some(id) => last_index[id] == index,
+                        None => true,
+                    })
+                    .collect();
+                log::debug!(
+                    "Dropping earlier records for duplicate ids: {}",
+                    duplicates.join(", ")
+                );
+                Ok(records
+                    .into_iter()
+                    .zip(keep)
+                    .filter_map(|(record, keep)| keep.then_some(record))
+                    .collect())
+            }
+        }
+    }
+
+    fn validate_record(&self, record: &crate::models::storage::VectorInsert) -> Result<()> {
+        if let Some(max) = self.max_dimensions {
+            if record.vector.len() > max {
+                return Err(BedrockError::RequestError(format!(
+                    "Record {} has {} dimensions, exceeding the limit of {}",
+                    record.id.as_deref().unwrap_or("<unassigned>"),
+                    record.vector.len(),
+                    max
+                )));
+            }
+        }
+
+        let missing: Vec<&str> = self
+            .required_metadata_keys
+            .iter()
+            .filter(|key| !record.metadata.contains_key(*key))
+            .map(String::as_str)
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(BedrockError::RequestError(format!(
+                "Record {} is missing required metadata keys: {}",
+                record.id.as_deref().unwrap_or("<unassigned>"),
+                missing.join(", ")
+            )))
+        }
+    }
+
+    async fn with_timeout<T>(
+        &self,
+        operation: &str,
+        future: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        match self.operation_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future).await.map_err(|_| {
+                BedrockError::Timeout(format!(
+                    "Storage {} timed out after {}ms",
+                    operation,
+                    timeout.as_millis()
+                ))
+            })?,
+            None => future.await,
+        }
+    }
 }
 impl VectorStorageManager {
     pub async fn insert(
         &self,
         record: crate::models::storage::VectorInsert,
     ) -> Result<crate::models::storage::InsertResult> {
-        self.backend.insert(record).await
+        self.validate_record(&record)?;
+        self.with_timeout("insert", self.backend.insert(record))
+            .await
     }
 
     pub async fn insert_batch(
         &self,
         records: Vec<crate::models::storage::VectorInsert>,
     ) -> Result<Vec<crate::models::storage::InsertResult>> {
-        self.backend.insert_batch(records).await
+        // Reject the whole batch before any of it is written.
+        let records = self.dedupe_batch(records)?;
+        for record in &records {
+            self.validate_record(record)?;
+        }
+        self.with_timeout("insert_batch", self.backend.insert_batch(records))
+            .await
     }
 
     pub async fn search(
         &self,
         query: crate::models::storage::VectorSearch,
     ) -> Result<crate::models::storage::VectorSearchResponse> {
-        self.backend.search(query).await
+        let min_score = query.min_score;
+        let highlight_query = query.highlight_query.clone();
+        let mut response = self
+            .with_timeout("search", self.backend.search(query))
+            .await?;
+
+        if let Some(min_score) = min_score {
+            response.results.retain(|result| result.score >= min_score);
+            response.total = response.results.len();
+        }
+        if let Some(highlight_query) = highlight_query {
+            for result in &mut response.results {
+                result.highlight = result.extract_highlight(&highlight_query);
+            }
+        }
+        Ok(response)
+    }
+
+    pub async fn search_ids(
+        &self,
+        query: crate::models::storage::VectorSearch,
+    ) -> Result<Vec<(String, f32)>> {
+        let min_score = query.min_score;
+        let mut ids = self
+            .with_timeout("search_ids", self.backend.search_ids(query))
+            .await?;
+
+        if let Some(min_score) = min_score {
+            ids.retain(|(_, score)| *score >= min_score);
+        }
+        Ok(ids)
     }
 
     pub async fn get(
@@ -107,14 +314,30 @@ impl VectorStorageManager {
         id: &str,
         namespace: Option<&str>,
     ) -> Result<Option<crate::models::storage::VectorRecord>> {
-        self.backend.get(id, namespace).await
+        self.with_timeout("get", self.backend.get(id, namespace))
+            .await
     }
 
     pub async fn update(
         &self,
         update: crate::models::storage::VectorUpdate,
     ) -> Result<crate::models::storage::UpdateResult> {
-        self.backend.update(update).await
+        self.with_timeout("update", self.backend.update(update))
+            .await
+    }
+
+    pub async fn update_vector(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        model_id: &str,
+        namespace: Option<&str>,
+    ) -> Result<crate::models::storage::UpdateResult> {
+        self.with_timeout(
+            "update_vector",
+            self.backend.update_vector(id, vector, model_id, namespace),
+        )
+        .await
     }
 
     pub async fn delete(
@@ -122,7 +345,8 @@ impl VectorStorageManager {
         id: &str,
         namespace: Option<&str>,
     ) -> Result<crate::models::storage::DeleteResult> {
-        self.backend.delete(id, namespace).await
+        self.with_timeout("delete", self.backend.delete(id, namespace))
+            .await
     }
 
     pub async fn delete_batch(
@@ -130,7 +354,8 @@ impl VectorStorageManager {
         ids: Vec<String>,
         namespace: Option<&str>,
     ) -> Result<Vec<crate::models::storage::DeleteResult>> {
-        self.backend.delete_batch(ids, namespace).await
+        self.with_timeout("delete_batch", self.backend.delete_batch(ids, namespace))
+            .await
     }
 
     pub async fn list(
@@ -138,14 +363,207 @@ impl VectorStorageManager {
         namespace: Option<&str>,
         limit: Option<usize>,
     ) -> Result<Vec<crate::models::storage::VectorRecord>> {
-        self.backend.list(namespace, limit).await
+        self.with_timeout("list", self.backend.list(namespace, limit))
+            .await
+    }
+
+    pub async fn scroll(
+        &self,
+        namespace: Option<&str>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<crate::models::storage::ScrollPage> {
+        self.with_timeout("scroll", self.backend.scroll(namespace, cursor, limit))
+            .await
+    }
+
+    // Copies every record in `namespace` into `target`, page by page. A page
+    // that fails to insert is recorded and the migration carries on.
+    pub async fn migrate_to(
+        &self,
+        target: &VectorStorageManager,
+        namespace: Option<&str>,
+        batch_size: usize,
+    ) -> Result<crate::models::storage::MigrationReport> {
+        let mut report = crate::models::storage::MigrationReport::default();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let page = self
+                .scroll(namespace, cursor.as_deref(), batch_size.max(1))
+                .await?;
+            report.scanned += page.records.len();
+
+            let records: Vec<crate::models::storage::VectorInsert> =
+                page.records.into_iter().map(Into::into).collect();
+            let page_len = records.len();
+
+            if page_len > 0 {
+                match target.insert_batch(records).await {
+                    Ok(results) => {
+                        for result in results {
+                            if result.success {
+                                report.migrated += 1;
+                            } else {
+                                report.failed += 1;
+                                report.errors.push(format!(
+                                    "{}: {}",
+                                    result.id,
+                                    result.message.unwrap_or_default()
+                                ));
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        report.failed += page_len;
+                        report.errors.push(e.to_string());
+                    }
+                }
+            }
+
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        log::info!(
+            "Migration finished: {} scanned, {} migrated, {} failed",
+            report.scanned,
+            report.migrated,
+            report.failed
+        );
+        Ok(report)
+    }
+
+    pub async fn find_by_content_hash(
+        &self,
+        hash: &str,
+        namespace: Option<&str>,
+    ) -> Result<Option<crate::models::storage::VectorRecord>> {
+        self.with_timeout(
+            "find_by_content_hash",
+            self.backend.find_by_content_hash(hash, namespace),
+        )
+        .await
+    }
+
+    pub async fn query_by_metadata(
+        &self,
+        filter: HashMap<String, serde_json::Value>,
+        namespace: Option<&str>,
+        limit: Option<usize>,
+        order_by: Option<crate::models::storage::MetadataOrder>,
+    ) -> Result<Vec<crate::models::storage::VectorRecord>> {
+        self.with_timeout(
+            "query_by_metadata",
+            self.backend
+                .query_by_metadata(filter, namespace, limit, order_by),
+        )
+        .await
     }
 
     pub async fn stats(&self, namespace: Option<&str>) -> Result<StorageStats> {
-        self.backend.stats(namespace).await
+        self.with_timeout("stats", self.backend.stats(namespace))
+            .await
+    }
+
+    // Fetches stats from every manager concurrently and combines them.
+    pub async fn aggregate_stats(
+        managers: &[&VectorStorageManager],
+        namespace: Option<&str>,
+    ) -> Result<AggregatedStats> {
+        let stats =
+            futures::future::try_join_all(managers.iter().map(|m| m.stats(namespace))).await?;
+        Ok(StorageStats::aggregate(stats))
+    }
+
+    pub async fn close(self) -> Result<()> {
+        self.backend.close().await
+    }
+
+    pub fn capabilities(&self) -> StorageCapabilities {
+        self.backend.capabilities()
     }
 
     pub async fn health_check(&self) -> Result<bool> {
-        self.backend.health_check().await
+        self.with_timeout("health_check", self.backend.health_check())
+            .await
+    }
+
+    // Health check for dashboards: never fails, and reports a timeout, an
+    // error or an unhealthy answer as `healthy: false`.
+    pub async fn ping(&self, name: &str, timeout: Duration) -> BackendHealth {
+        let started = Instant::now();
+        let result = tokio::time::timeout(timeout, self.backend.health_check()).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let error = match result {
+            Ok(Ok(true)) => None,
+            Ok(Ok(false)) => Some("Health check failed".to_string()),
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(_) => Some(format!(
+                "Health check timed out after {}ms",
+                timeout.as_millis()
+            )),
+        };
+        BackendHealth {
+            name: name.to_string(),
+            healthy: error.is_none(),
+            latency_ms,
+            error,
+        }
+    }
+
+    // Pings every named manager concurrently, in the given order.
+    pub async fn ping_all(
+        managers: &[(&str, &VectorStorageManager)],
+        timeout: Duration,
+    ) -> Vec<BackendHealth> {
+        futures::future::join_all(
+            managers
+                .iter()
+                .map(|(name, manager)| manager.ping(name, timeout)),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::VectorInsert;
+
+    fn record(id: Option<&str>, content: &str) -> VectorInsert {
+        VectorInsert {
+            id: id.map(String::from),
+            vector: vec![1.0, 0.0],
+            sparse_vector: None,
+            metadata: HashMap::new(),
+            content: Some(content.to_string()),
+            namespace: None,
+        }
+    }
+
+    #[test]
+    fn test_duplicate_ids_in_batch() {
+        let batch = vec![
+            record(Some("a"), "first"),
+            record(None, "unassigned"),
+            record(Some("b"), "only"),
+            record(Some("a"), "second"),
+        ];
+        let manager = VectorStorageManager::from_backend(Arc::new(InMemoryVectorStorage::new()));
+        let err = manager.dedupe_batch(batch.clone()).unwrap_err();
+        assert!(err.to_string().contains("duplicate ids: a"));
+
+        let manager = manager.with_duplicate_ids(DuplicateIdPolicy::LastWins);
+        let contents: Vec<_> = manager
+            .dedupe_batch(batch)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.content.unwrap())
+            .collect();
+        assert_eq!(contents, vec!["unassigned", "only", "second"]);
     }
 }
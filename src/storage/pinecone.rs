@@ -1,13 +1,17 @@
 use std::collections::HashMap;
 
 use crate::{
-    config::PineconeConfig,
+    config::{MetadataKeys, PineconeConfig, PineconeMetric},
     error::{BedrockError, Result},
     models::storage::{
-        DeleteResult, InsertResult, UpdateResult, VectorInsert, VectorRecord, VectorSearch,
+        normalize_cosine_score, sort_by_metadata, vector_description, DeleteResult, InsertResult,
+        MetadataOrder, ScrollPage, UpdateResult, VectorInsert, VectorRecord, VectorSearch,
         VectorSearchResponse, VectorSearchResult, VectorUpdate,
     },
-    storage::traits::{StorageStats, VectorStorage},
+    storage::{
+        compression::RequestCompression,
+        traits::{StorageCapabilities, StorageStats, VectorStorage},
+    },
 };
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
@@ -16,16 +20,24 @@ use serde_json::{json, Value};
 
 use uuid::Uuid;
 
+// Largest `topK` Pinecone accepts for a query.
+const MAX_TOP_K: usize = 10_000;
+
+const DEFAULT_BATCH_SIZE: usize = 100;
+
 pub struct PineconeVectorStorage {
     client: Client,
     api_key: String,
-    environment: String,
-    index_name: String,
     base_url: String,
+    batch_size: usize,
+    compression: RequestCompression,
+    keys: MetadataKeys,
+    metric: PineconeMetric,
 }
 
 impl PineconeVectorStorage {
     pub async fn new(config: PineconeConfig) -> Result<Self> {
+        config.metadata_keys.validate()?;
         let api_key = config
             .api_key
             .ok_or_else(|| BedrockError::ConfigError("Pinecone API key is required".into()))?;
@@ -46,15 +58,26 @@ impl PineconeVectorStorage {
         let storage = Self {
             client: Client::new(),
             api_key,
-            environment,
-            index_name,
             base_url,
+            batch_size: config.batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1),
+            compression: RequestCompression::new(config.compress_requests),
+            keys: config.metadata_keys,
+            metric: config.metric,
         };
         storage.health_check().await?;
 
         Ok(storage)
     }
 
+    // A cosine index returns the raw similarity, which maps onto the shared
+    // [0, 1] scale. Dotproduct scores are unbounded, so they pass through.
+    fn score(&self, raw_score: f32) -> f32 {
+        match self.metric {
+            PineconeMetric::Cosine => normalize_cosine_score(raw_score),
+            PineconeMetric::DotProduct => raw_score,
+        }
+    }
+
     fn build_headers(&self) -> reqwest::header::HeaderMap {
         let mut headers = reqwest::header::HeaderMap::new();
         headers.insert("Api-Key", self.api_key.parse().unwrap());
@@ -64,6 +87,122 @@ impl PineconeVectorStorage {
         );
         headers
     }
+
+    async fn upsert_chunk(&self, records: &[(String, VectorInsert)]) -> Result<Vec<InsertResult>> {
+        if records.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let namespace = records
+            .first()
+            .and_then(|(_, r)| r.namespace.as_ref())
+            .cloned()
+            .unwrap_or_else(|| "default".to_string());
+
+        let vectors: Vec<Value> = records
+            .iter()
+            .map(|(id, record)| {
+                let mut metadata = record.metadata.clone();
+                if let Some(content) = &record.content {
+                    metadata.insert(self.keys.content.clone(), json!(content));
+                }
+                metadata.insert(self.keys.created_at.clone(), json!(Utc::now().to_rfc3339()));
+
+                let mut vector = json!({
+                    "id": id,
+                    "values": record.vector,
+                    "metadata": metadata
+                });
+                if let Some(sparse_vector) = &record.sparse_vector {
+                    vector["sparseValues"] = json!(sparse_vector);
+                }
+                vector
+            })
+            .collect();
+
+        let payload = json!({
+            "vectors": vectors,
+            "namespace": namespace
+        });
+        let response = self
+            .compression
+            .send(
+                || {
+                    self.client
+                        .post(format!("{}/vectors/upsert", self.base_url))
+                        .headers(self.build_headers())
+                },
+                &payload,
+            )
+            .await
+            .map_err(|e| {
+                BedrockError::RequestError(format!("Pinecone batch request failed: {}", e))
+            })?;
+
+        let mut results = Vec::new();
+
+        if response.status().is_success() {
+            for (id, _) in records {
+                results.push(InsertResult {
+                    id: id.clone(),
+                    success: true,
+                    message: Some("Vector inserted successfully".to_string()),
+                });
+            }
+        } else {
+            let error_text = response.text().await.unwrap_or_default();
+            for (id, _) in records {
+                results.push(InsertResult {
+                    id: id.clone(),
+                    success: false,
+                    message: Some(format!("Batch insert failed: {}", error_text)),
+                });
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn parse_record(&self, id: &str, vector_data: &Value, namespace: &str) -> VectorRecord {
+        let metadata: HashMap<String, serde_json::Value> = vector_data["metadata"]
+            .as_object()
+            .map(|obj| obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default();
+
+        let content = metadata
+            .get(&self.keys.content)
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        let created_at_str = metadata
+            .get(&self.keys.created_at)
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+
+        let created_at = DateTime::parse_from_rfc3339(created_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now());
+
+        let vector = vector_data["values"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_f64().map(|f| f as f32))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let sparse_vector = serde_json::from_value(vector_data["sparseValues"].clone()).ok();
+
+        VectorRecord {
+            id: id.to_string(),
+            vector,
+            sparse_vector,
+            metadata,
+            content,
+            namespace: Some(namespace.to_string()),
+            created_at,
+            updated_at: created_at, // Pinecone doesn't track update time separately
+        }
+    }
 }
 
 #[async_trait]
@@ -73,25 +212,30 @@ impl VectorStorage for PineconeVectorStorage {
 
         let mut metadata = record.metadata.clone();
         if let Some(content) = &record.content {
-            metadata.insert("content".to_string(), json!(content));
+            metadata.insert(self.keys.content.clone(), json!(content));
         }
         if let Some(namespace) = &record.namespace {
-            metadata.insert("namespace".to_string(), json!(namespace));
+            metadata.insert(self.keys.namespace.clone(), json!(namespace));
+        }
+        metadata.insert(self.keys.created_at.clone(), json!(Utc::now().to_rfc3339()));
+
+        let mut vector = json!({
+            "id": id,
+            "values": record.vector,
+            "metadata": metadata
+        });
+        if let Some(sparse_vector) = &record.sparse_vector {
+            vector["sparseValues"] = json!(sparse_vector);
         }
-        metadata.insert("created_at".to_string(), json!(Utc::now().to_rfc3339()));
 
         let payload = json!({
-            "vectors": [{
-                "id": id,
-                "values": record.vector,
-                "metadata": metadata
-            }],
+            "vectors": [vector],
             "namespace": record.namespace.unwrap_or_else(|| "default".to_string())
         });
 
         let response = self
             .client
-            .post(&format!("{}/vectors/upsert", self.base_url))
+            .post(format!("{}/vectors/upsert", self.base_url))
             .headers(self.build_headers())
             .json(&payload)
             .send()
@@ -115,74 +259,30 @@ impl VectorStorage for PineconeVectorStorage {
     }
 
     async fn insert_batch(&self, records: Vec<VectorInsert>) -> Result<Vec<InsertResult>> {
-        if records.is_empty() {
-            return Ok(vec![]);
-        }
-
-        let namespace = records
-            .first()
-            .and_then(|r| r.namespace.as_ref())
-            .cloned()
-            .unwrap_or_else(|| "default".to_string());
-
-        let vectors: Vec<Value> = records
-            .iter()
+        // Ids are fixed before the first request and never regenerated per
+        // chunk, so re-sending a chunk overwrites rather than duplicates.
+        let records: Vec<(String, VectorInsert)> = records
+            .into_iter()
             .map(|record| {
                 let id = record
                     .id
-                    .as_ref()
-                    .cloned()
+                    .clone()
                     .unwrap_or_else(|| Uuid::new_v4().to_string());
-
-                let mut metadata = record.metadata.clone();
-                if let Some(content) = &record.content {
-                    metadata.insert("content".to_string(), json!(content));
-                }
-                metadata.insert("created_at".to_string(), json!(Utc::now().to_rfc3339()));
-
-                json!({
-                    "id": id,
-                    "values": record.vector,
-                    "metadata": metadata
-                })
+                (id, record)
             })
             .collect();
 
-        let payload = json!({
-            "vectors": vectors,
-            "namespace": namespace
-        });
-        let response = self
-            .client
-            .post(&format!("{}/vectors/upsert", self.base_url))
-            .headers(self.build_headers())
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| {
-                BedrockError::RequestError(format!("Pinecone batch request failed: {}", e))
-            })?;
-
-        let mut results = Vec::new();
-
-        if response.status().is_success() {
-            for record in records {
-                let id = record.id.unwrap_or_else(|| Uuid::new_v4().to_string());
-                results.push(InsertResult {
-                    id,
-                    success: true,
-                    message: Some("Vector inserted successfully".to_string()),
-                });
-            }
-        } else {
-            let error_text = response.text().await.unwrap_or_default();
-            for record in records {
-                let id = record.id.unwrap_or_else(|| Uuid::new_v4().to_string());
-                results.push(InsertResult {
-                    id,
+        let mut results = Vec::with_capacity(records.len());
+        // A failed chunk is reported per record so results from chunks that
+        // already landed are not lost.
+        for chunk in records.chunks(self.batch_size) {
+            match self.upsert_chunk(chunk).await {
+                Ok(chunk_results) => results.extend(chunk_results),
+                Err(e) => results.extend(chunk.iter().map(|(id, _)| InsertResult {
+                    id: id.clone(),
                     success: false,
-                    message: Some(format!("Batch insert failed: {}", error_text)),
-                });
+                    message: Some(e.to_string()),
+                })),
             }
         }
 
@@ -190,18 +290,22 @@ impl VectorStorage for PineconeVectorStorage {
     }
 
     async fn search(&self, query: VectorSearch) -> Result<VectorSearchResponse> {
-        let payload = json!({
+        let mut payload = json!({
             "vector": query.vector,
             "topK": query.limit,
             "namespace": query.namespace.unwrap_or_else(|| "default".to_string()),
             "includeMetadata": query.include_metadata,
-            "includeValues": query.include_content,
+            "includeValues": query.include_vector,
             "filter": query.filter.unwrap_or_default()
         });
 
+        if let Some(sparse_vector) = &query.sparse_vector {
+            payload["sparseVector"] = json!(sparse_vector);
+        }
+
         let response = self
             .client
-            .post(&format!("{}/query", self.base_url))
+            .post(format!("{}/query", self.base_url))
             .headers(self.build_headers())
             .json(&payload)
             .send()
@@ -233,17 +337,21 @@ impl VectorStorage for PineconeVectorStorage {
 
             let content = if query.include_content {
                 metadata
-                    .get("content")
+                    .get(&self.keys.content)
                     .and_then(|v| v.as_str())
                     .map(String::from)
             } else {
                 None
             };
 
+            let raw_score = match_item["score"].as_f64().unwrap_or(0.0) as f32;
             results.push(VectorSearchResult {
                 id: match_item["id"].as_str().unwrap_or("").to_string(),
-                score: match_item["score"].as_f64().unwrap_or(0.0) as f32,
-                vector: if query.include_content {
+                score: self.score(raw_score),
+                raw_score: Some(raw_score),
+                rerank_score: None,
+                highlight: None,
+                vector: if query.include_vector {
                     match_item["values"].as_array().map(|arr| {
                         arr.iter()
                             .filter_map(|v| v.as_f64().map(|f| f as f32))
@@ -275,7 +383,7 @@ impl VectorStorage for PineconeVectorStorage {
 
         let response = self
             .client
-            .post(&format!("{}/vectors/fetch", self.base_url))
+            .post(format!("{}/vectors/fetch", self.base_url))
             .headers(self.build_headers())
             .json(&payload)
             .send()
@@ -294,46 +402,9 @@ impl VectorStorage for PineconeVectorStorage {
             .as_object()
             .ok_or_else(|| BedrockError::ResponseError("Invalid fetch response format".into()))?;
 
-        if let Some(vector_data) = vectors.get(id) {
-            let metadata: HashMap<String, serde_json::Value> = vector_data["metadata"]
-                .as_object()
-                .map(|obj| obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
-                .unwrap_or_default();
-
-            let content = metadata
-                .get("content")
-                .and_then(|v| v.as_str())
-                .map(String::from);
-            let created_at_str = metadata
-                .get("created_at")
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
-
-            let created_at = DateTime::parse_from_rfc3339(created_at_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or_else(|_| Utc::now());
-
-            let vector = vector_data["values"]
-                .as_array()
-                .map(|arr| {
-                    arr.iter()
-                        .filter_map(|v| v.as_f64().map(|f| f as f32))
-                        .collect()
-                })
-                .unwrap_or_default();
-
-            Ok(Some(VectorRecord {
-                id: id.to_string(),
-                vector,
-                metadata,
-                content,
-                namespace: Some(namespace.to_string()),
-                created_at,
-                updated_at: created_at, // Pinecone doesn't track update time separately
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(vectors
+            .get(id)
+            .map(|vector_data| self.parse_record(id, vector_data, namespace)))
     }
 
     async fn update(&self, update: VectorUpdate) -> Result<UpdateResult> {
@@ -352,7 +423,7 @@ impl VectorStorage for PineconeVectorStorage {
                 existing_record.content = Some(content);
                 existing_record
                     .metadata
-                    .insert("content".to_string(), json!(existing_record.content));
+                    .insert(self.keys.content.clone(), json!(existing_record.content));
             }
             if let Some(namespace) = update.namespace {
                 existing_record.namespace = Some(namespace);
@@ -361,17 +432,9 @@ impl VectorStorage for PineconeVectorStorage {
             // Add updated timestamp
             existing_record
                 .metadata
-                .insert("updated_at".to_string(), json!(Utc::now().to_rfc3339()));
-
-            let insert_record = VectorInsert {
-                id: Some(existing_record.id.clone()),
-                vector: existing_record.vector,
-                metadata: existing_record.metadata,
-                content: existing_record.content,
-                namespace: existing_record.namespace,
-            };
+                .insert(self.keys.updated_at.clone(), json!(Utc::now().to_rfc3339()));
 
-            let insert_result = self.insert(insert_record).await?;
+            let insert_result = self.insert(existing_record.into()).await?;
             Ok(UpdateResult {
                 id: update.id,
                 success: insert_result.success,
@@ -386,6 +449,47 @@ impl VectorStorage for PineconeVectorStorage {
         }
     }
 
+    // Pinecone's update endpoint replaces the values in place and merges
+    // `setMetadata` into the stored metadata, leaving other keys alone.
+    async fn update_vector(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        model_id: &str,
+        namespace: Option<&str>,
+    ) -> Result<UpdateResult> {
+        let payload = json!({
+            "id": id,
+            "setMetadata": vector_description(model_id, &vector),
+            "values": vector,
+            "namespace": namespace.unwrap_or("default")
+        });
+
+        let response = self
+            .client
+            .post(format!("{}/vectors/update", self.base_url))
+            .headers(self.build_headers())
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| BedrockError::RequestError(format!("Pinecone update failed: {}", e)))?;
+
+        if response.status().is_success() {
+            Ok(UpdateResult {
+                id: id.to_string(),
+                success: true,
+                message: Some("Vector updated successfully".to_string()),
+            })
+        } else {
+            let error_text = response.text().await.unwrap_or_default();
+            Ok(UpdateResult {
+                id: id.to_string(),
+                success: false,
+                message: Some(format!("Update failed: {}", error_text)),
+            })
+        }
+    }
+
     async fn delete(&self, id: &str, namespace: Option<&str>) -> Result<DeleteResult> {
         let namespace = namespace.unwrap_or("default");
 
@@ -396,7 +500,7 @@ impl VectorStorage for PineconeVectorStorage {
 
         let response = self
             .client
-            .delete(&format!("{}/vectors/delete", self.base_url))
+            .delete(format!("{}/vectors/delete", self.base_url))
             .headers(self.build_headers())
             .json(&payload)
             .send()
@@ -428,7 +532,7 @@ impl VectorStorage for PineconeVectorStorage {
 
         let response = self
             .client
-            .delete(&format!("{}/vectors/delete", self.base_url))
+            .delete(format!("{}/vectors/delete", self.base_url))
             .headers(self.build_headers())
             .json(&payload)
             .send()
@@ -456,8 +560,8 @@ impl VectorStorage for PineconeVectorStorage {
 
     async fn list(
         &self,
-        namespace: Option<&str>,
-        limit: Option<usize>,
+        _namespace: Option<&str>,
+        _limit: Option<usize>,
     ) -> Result<Vec<VectorRecord>> {
         // Pinecone doesn't have a direct list operation, so we'd need to implement pagination
         // For now, return empty - this would require storing IDs separately or using describe_index_stats
@@ -467,10 +571,167 @@ impl VectorStorage for PineconeVectorStorage {
         Ok(vec![])
     }
 
-    async fn stats(&self, namespace: Option<&str>) -> Result<StorageStats> {
+    // Pinecone has no filter-only fetch, so this is a filtered query with a
+    // constant vector (zero vectors are rejected under cosine) whose scores
+    // are ignored. At most `MAX_TOP_K` matches come back, so ordering only
+    // covers those.
+    async fn query_by_metadata(
+        &self,
+        filter: HashMap<String, Value>,
+        namespace: Option<&str>,
+        limit: Option<usize>,
+        order_by: Option<MetadataOrder>,
+    ) -> Result<Vec<VectorRecord>> {
+        let dimensions = self.stats(namespace).await?.dimensions.ok_or_else(|| {
+            BedrockError::ResponseError("Pinecone did not report the index dimension".into())
+        })?;
+        let namespace = namespace.unwrap_or("default");
+        let top_k = match (&order_by, limit) {
+            (None, Some(limit)) => limit.min(MAX_TOP_K),
+            _ => MAX_TOP_K,
+        };
+
+        let payload = json!({
+            "vector": vec![1.0_f32; dimensions],
+            "topK": top_k,
+            "namespace": namespace,
+            "filter": filter,
+            "includeMetadata": true,
+            "includeValues": true
+        });
+
         let response = self
             .client
-            .post(&format!("{}/describe_index_stats", self.base_url))
+            .post(format!("{}/query", self.base_url))
+            .headers(self.build_headers())
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| BedrockError::RequestError(format!("Pinecone query failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(BedrockError::RequestError(format!(
+                "Query failed: {}",
+                error_text
+            )));
+        }
+
+        let response_json: Value = response.json().await.map_err(|e| {
+            BedrockError::ResponseError(format!("Failed to parse query response: {}", e))
+        })?;
+
+        let mut records: Vec<VectorRecord> = response_json["matches"]
+            .as_array()
+            .ok_or_else(|| BedrockError::ResponseError("Invalid query response format".into()))?
+            .iter()
+            .map(|m| self.parse_record(m["id"].as_str().unwrap_or(""), m, namespace))
+            .collect();
+        if order_by.is_some() && records.len() == MAX_TOP_K {
+            log::warn!(
+                "query_by_metadata hit Pinecone's topK limit of {}; ordering covers only those matches",
+                MAX_TOP_K
+            );
+        }
+
+        if let Some(order) = &order_by {
+            sort_by_metadata(&mut records, order);
+        }
+        if let Some(limit) = limit {
+            records.truncate(limit);
+        }
+        Ok(records)
+    }
+
+    // Pinecone lists ids page by page, so each page is followed by a fetch.
+    async fn scroll(
+        &self,
+        namespace: Option<&str>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<ScrollPage> {
+        let namespace = namespace.unwrap_or("default");
+        let limit = limit.to_string();
+        let mut params = vec![("namespace", namespace), ("limit", limit.as_str())];
+        if let Some(cursor) = cursor {
+            params.push(("paginationToken", cursor));
+        }
+
+        let response = self
+            .client
+            .get(format!("{}/vectors/list", self.base_url))
+            .headers(self.build_headers())
+            .query(&params)
+            .send()
+            .await
+            .map_err(|e| BedrockError::RequestError(format!("Pinecone list failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(BedrockError::RequestError(format!(
+                "List failed: {}",
+                error_text
+            )));
+        }
+
+        let response_json: Value = response.json().await.map_err(|e| {
+            BedrockError::ResponseError(format!("Failed to parse list response: {}", e))
+        })?;
+
+        let ids: Vec<&str> = response_json["vectors"]
+            .as_array()
+            .map(|vectors| vectors.iter().filter_map(|v| v["id"].as_str()).collect())
+            .unwrap_or_default();
+        let next_cursor = response_json["pagination"]["next"]
+            .as_str()
+            .map(String::from);
+
+        if ids.is_empty() {
+            return Ok(ScrollPage {
+                records: vec![],
+                next_cursor,
+            });
+        }
+
+        let payload = json!({
+            "ids": ids,
+            "namespace": namespace,
+            "includeMetadata": true,
+            "includeValues": true
+        });
+
+        let response = self
+            .client
+            .post(format!("{}/vectors/fetch", self.base_url))
+            .headers(self.build_headers())
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| BedrockError::RequestError(format!("Pinecone fetch failed: {}", e)))?;
+
+        let fetched: Value = response.json().await.map_err(|e| {
+            BedrockError::ResponseError(format!("Failed to parse fetch response: {}", e))
+        })?;
+
+        let records = ids
+            .iter()
+            .filter_map(|id| {
+                fetched["vectors"]
+                    .get(*id)
+                    .map(|vector_data| self.parse_record(id, vector_data, namespace))
+            })
+            .collect();
+
+        Ok(ScrollPage {
+            records,
+            next_cursor,
+        })
+    }
+
+    async fn stats(&self, _namespace: Option<&str>) -> Result<StorageStats> {
+        let response = self
+            .client
+            .post(format!("{}/describe_index_stats", self.base_url))
             .headers(self.build_headers())
             .json(&json!({}))
             .send()
@@ -498,13 +759,25 @@ impl VectorStorage for PineconeVectorStorage {
             namespaces,
             dimensions,
             storage_size_bytes: None,
+            last_updated: None,
+            oldest: None,
         })
     }
 
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities {
+            supports_namespaces: true,
+            supports_list: false,
+            supports_filter: true,
+            supports_transactions: false,
+            supports_sparse: true,
+        }
+    }
+
     async fn health_check(&self) -> Result<bool> {
         let response = self
             .client
-            .post(&format!("{}/describe_index_stats", self.base_url))
+            .post(format!("{}/describe_index_stats", self.base_url))
             .headers(self.build_headers())
             .json(&json!({}))
             .send()
@@ -3,10 +3,11 @@ use crate::{
     config::PostgresConfig,
     error::{BedrockError, Result},
     models::storage::{
-        DeleteResult, InsertResult, UpdateResult, VectorInsert, VectorRecord, VectorSearch,
+        content_hash, normalize_cosine_score, vector_description, DeleteResult, InsertResult,
+        MetadataOrder, ScrollPage, UpdateResult, VectorInsert, VectorRecord, VectorSearch,
         VectorSearchResponse, VectorSearchResult, VectorUpdate,
     },
-    storage::traits::{StorageStats, VectorStorage},
+    storage::traits::{StorageCapabilities, StorageStats, VectorStorage},
 };
 
 #[cfg(feature = "postgres")]
@@ -24,9 +25,38 @@ use tokio_postgres::{types::ToSql, NoTls};
 #[cfg(feature = "postgres")]
 use uuid::Uuid;
 
+// Incremental schema changes, applied in order on top of the `vectors` table
+// from `initialize_schema`. Version n is the n-th entry; append new steps and
+// never edit or reorder released ones. Each step must be idempotent, since a
+// table created by the current code already has what older steps add.
+#[cfg(feature = "postgres")]
+const SCHEMA_MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "add content_hash column",
+        "ALTER TABLE vectors ADD COLUMN IF NOT EXISTS content_hash TEXT",
+    ),
+    (
+        "index content_hash",
+        "CREATE INDEX IF NOT EXISTS idx_vectors_content_hash ON vectors(namespace, content_hash)",
+    ),
+    // Rows stored before the column existed; must match `content_hash`.
+    (
+        "backfill content_hash",
+        "UPDATE vectors SET content_hash = encode(sha256(convert_to(content, 'UTF8')), 'hex') WHERE content_hash IS NULL AND content IS NOT NULL",
+    ),
+];
+
+// Serializes migrations when several processes start against one database.
+#[cfg(feature = "postgres")]
+const MIGRATION_LOCK_ID: i64 = 0x7267_656e_6169;
+
 #[cfg(feature = "postgres")]
 pub struct PostgresVectorStorage {
     pool: Pool,
+    index_lists: i32,
+    search_probes: Option<i32>,
+    dimensions: Option<usize>,
+    auto_migrate: bool,
 }
 
 #[cfg(feature = "postgres")]
@@ -43,14 +73,20 @@ impl PostgresVectorStorage {
             .create_pool(Some(Runtime::Tokio1), NoTls)
             .map_err(|e| BedrockError::ConfigError(format!("Failed to create pool: {}", e)))?;
 
-        let storage = Self { pool };
+        let storage = Self {
+            pool,
+            index_lists: config.index_lists.unwrap_or(100),
+            search_probes: config.search_probes,
+            dimensions: config.dimensions,
+            auto_migrate: config.auto_migrate.unwrap_or(true),
+        };
         storage.initialize_schema().await?;
 
         Ok(storage)
     }
 
     async fn initialize_schema(&self) -> Result<()> {
-        let client =
+        let mut client =
             self.pool.get().await.map_err(|e| {
                 BedrockError::InternalError(format!("Failed to get connection: {}", e))
             })?;
@@ -62,17 +98,26 @@ impl PostgresVectorStorage {
             .map_err(|e| {
                 BedrockError::InternalError(format!("Failed to create vector extension: {}", e))
             })?;
+        // Only applies to new tables; an existing column keeps its type.
+        let vector_type = match self.dimensions {
+            Some(dimensions) => format!("VECTOR({})", dimensions),
+            None => "VECTOR".to_string(),
+        };
         client
             .execute(
-                "CREATE TABLE IF NOT EXISTS vectors (
+                &format!(
+                    "CREATE TABLE IF NOT EXISTS vectors (
                 id TEXT PRIMARY KEY,
-                vector VECTOR,
-                metadata JSONB DEFAULT '{}',
+                vector {},
+                metadata JSONB DEFAULT '{{}}',
                 content TEXT,
+                content_hash TEXT,
                 namespace TEXT DEFAULT 'default',
                 created_at TIMESTAMPTZ DEFAULT NOW(),
                 updated_at TIMESTAMPTZ DEFAULT NOW()
             )",
+                    vector_type
+                ),
                 &[],
             )
             .await
@@ -80,6 +125,8 @@ impl PostgresVectorStorage {
                 BedrockError::InternalError(format!("Failed to create vectors table: {}", e))
             })?;
 
+        self.migrate_schema(&mut client).await?;
+
         client
             .execute(
                 "CREATE INDEX IF NOT EXISTS idx_vectors_namespace ON vectors(namespace)",
@@ -90,13 +137,169 @@ impl PostgresVectorStorage {
                 BedrockError::InternalError(format!("Failed to create namespace index: {}", e))
             })?;
         let _ = client.execute(
-            "CREATE INDEX IF NOT EXISTS idx_vectors_vector ON vectors USING ivfflat (vector vector_cosine_ops) WITH (lists = 100)",
+            &format!(
+                "CREATE INDEX IF NOT EXISTS idx_vectors_vector ON vectors USING ivfflat (vector vector_cosine_ops) WITH (lists = {})",
+                self.index_lists
+            ),
             &[],
         ).await;
 
         log::info!("PostgreSQL vector storage schema initialized");
         Ok(())
     }
+
+    // Brings tables created by older versions of the crate up to date. The
+    // applied version is recorded in `_rgen_schema_version`, and everything
+    // runs in one transaction so a failed step leaves the schema untouched.
+    async fn migrate_schema(&self, client: &mut deadpool_postgres::Object) -> Result<()> {
+        let migration_error = |e: tokio_postgres::Error| {
+            BedrockError::InternalError(format!("Schema migration failed: {}", e))
+        };
+
+        let transaction = client.transaction().await.map_err(migration_error)?;
+        transaction
+            .execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK_ID])
+            .await
+            .map_err(migration_error)?;
+        transaction
+            .execute(
+                "CREATE TABLE IF NOT EXISTS _rgen_schema_version (
+                version INTEGER PRIMARY KEY,
+                description TEXT NOT NULL,
+                applied_at TIMESTAMPTZ DEFAULT NOW()
+            )",
+                &[],
+            )
+            .await
+            .map_err(migration_error)?;
+        let current: i32 = transaction
+            .query_one(
+                "SELECT COALESCE(MAX(version), 0) FROM _rgen_schema_version",
+                &[],
+            )
+            .await
+            .map_err(migration_error)?
+            .get(0);
+
+        let latest = SCHEMA_MIGRATIONS.len() as i32;
+        if current > latest {
+            log::warn!(
+                "PostgreSQL schema is at version {}, newer than the {} this version of rgenai knows",
+                current,
+                latest
+            );
+            return transaction.commit().await.map_err(migration_error);
+        }
+        let pending = &SCHEMA_MIGRATIONS[current as usize..];
+        if pending.is_empty() {
+            return transaction.commit().await.map_err(migration_error);
+        }
+        if !self.auto_migrate {
+            log::warn!(
+                "PostgreSQL schema is at version {} of {}; auto_migrate is off, pending: {}",
+                current,
+                latest,
+                pending
+                    .iter()
+                    .map(|(description, _)| *description)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            return transaction.commit().await.map_err(migration_error);
+        }
+
+        for (offset, (description, statement)) in pending.iter().enumerate() {
+            let version = current + offset as i32 + 1;
+            transaction.execute(*statement, &[]).await.map_err(|e| {
+                BedrockError::InternalError(format!(
+                    "Schema migration {} ({}) failed: {}",
+                    version, description, e
+                ))
+            })?;
+            transaction
+                .execute(
+                    "INSERT INTO _rgen_schema_version (version, description) VALUES ($1, $2)",
+                    &[&version, description],
+                )
+                .await
+                .map_err(migration_error)?;
+        }
+        transaction.commit().await.map_err(migration_error)?;
+
+        log::info!(
+            "Migrated PostgreSQL schema from version {} to {}",
+            current,
+            latest
+        );
+        Ok(())
+    }
+
+    // Runs a similarity query returning `columns` plus `similarity`, so id-only
+    // lookups don't pay for fetching metadata, content and vectors.
+    async fn search_rows(
+        &self,
+        query: &VectorSearch,
+        columns: &str,
+    ) -> Result<Vec<tokio_postgres::Row>> {
+        let mut client =
+            self.pool.get().await.map_err(|e| {
+                BedrockError::InternalError(format!("Failed to get connection: {}", e))
+            })?;
+
+        if query.sparse_vector.is_some() {
+            log::warn!("Sparse vectors are not supported by PostgreSQL storage - ignoring");
+        }
+
+        let query_vector = Vector::from(query.vector.clone());
+        let namespace = query.namespace.as_deref().unwrap_or("default");
+        let limit = query.limit as i64;
+        // An empty object is contained by every metadata value, so unfiltered
+        // searches share the same statement.
+        let filter = serde_json::to_value(query.filter.clone().unwrap_or_default())
+            .map_err(|e| BedrockError::SerializationError(e.to_string()))?;
+
+        let transaction = client.transaction().await.map_err(|e| {
+            BedrockError::InternalError(format!("Failed to start search transaction: {}", e))
+        })?;
+
+        // SET LOCAL scopes the probe count to this transaction so pooled
+        // connections keep the server default for other queries.
+        if let Some(probes) = self.search_probes {
+            transaction
+                .batch_execute(&format!("SET LOCAL ivfflat.probes = {}", probes))
+                .await
+                .map_err(|e| {
+                    BedrockError::InternalError(format!("Failed to set ivfflat probes: {}", e))
+                })?;
+        }
+
+        let stmt = transaction
+            .prepare(&format!(
+                "SELECT {}, 1 - (vector <=> $1) as similarity
+             FROM vectors
+             WHERE namespace = $2 AND metadata @> $4
+             ORDER BY vector <=> $1
+             LIMIT $3",
+                columns
+            ))
+            .await
+            .map_err(|e| {
+                BedrockError::InternalError(format!("Failed to prepare search statement: {}", e))
+            })?;
+
+        let rows = transaction
+            .query(&stmt, &[&query_vector, &namespace, &limit, &filter])
+            .await
+            .map_err(|e| {
+                BedrockError::InternalError(format!("Failed to execute search query: {}", e))
+            })?;
+
+        transaction.commit().await.map_err(|e| {
+            BedrockError::InternalError(format!("Failed to commit search transaction: {}", e))
+        })?;
+
+        Ok(rows)
+    }
 }
 
 #[cfg(feature = "postgres")]
@@ -108,20 +311,26 @@ impl VectorStorage for PostgresVectorStorage {
                 BedrockError::InternalError(format!("Failed to get connection: {}", e))
             })?;
 
+        if record.sparse_vector.is_some() {
+            log::warn!("Sparse vectors are not supported by PostgreSQL storage - ignoring");
+        }
+
         let id = record.id.unwrap_or_else(|| Uuid::new_v4().to_string());
         let vector = Vector::from(record.vector);
         let namespace = record.namespace.as_deref().unwrap_or("default");
         let metadata = serde_json::to_value(&record.metadata)
             .map_err(|e| BedrockError::SerializationError(e.to_string()))?;
+        let hash = record.content.as_deref().map(content_hash);
 
         let stmt = client
             .prepare(
-                "INSERT INTO vectors (id, vector, metadata, content, namespace, created_at, updated_at)
-             VALUES ($1, $2, $3, $4, $5, NOW(), NOW())
+                "INSERT INTO vectors (id, vector, metadata, content, content_hash, namespace, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, NOW(), NOW())
              ON CONFLICT (id) DO UPDATE SET
                 vector = EXCLUDED.vector,
                 metadata = EXCLUDED.metadata,
                 content = EXCLUDED.content,
+                content_hash = EXCLUDED.content_hash,
                 namespace = EXCLUDED.namespace,
                 updated_at = NOW()",
             )
@@ -133,7 +342,7 @@ impl VectorStorage for PostgresVectorStorage {
         client
             .execute(
                 &stmt,
-                &[&id, &vector, &metadata, &record.content, &namespace],
+                &[&id, &vector, &metadata, &record.content, &hash, &namespace],
             )
             .await
             .map_err(|e| BedrockError::InternalError(format!("Failed to insert vector: {}", e)))?;
@@ -164,38 +373,13 @@ impl VectorStorage for PostgresVectorStorage {
     }
 
     async fn search(&self, query: VectorSearch) -> Result<VectorSearchResponse> {
-        let client =
-            self.pool.get().await.map_err(|e| {
-                BedrockError::InternalError(format!("Failed to get connection: {}", e))
-            })?;
-
-        let query_vector = Vector::from(query.vector);
-        let namespace = query.namespace.as_deref().unwrap_or("default");
-        let limit = query.limit as i64;
-
-        let stmt = client
-            .prepare(
-                "SELECT id, vector, metadata, content, 1 - (vector <=> $1) as similarity
-             FROM vectors
-             WHERE namespace = $2
-             ORDER BY vector <=> $1
-             LIMIT $3",
-            )
-            .await
-            .map_err(|e| {
-                BedrockError::InternalError(format!("Failed to prepare search statement: {}", e))
-            })?;
-
-        let rows = client
-            .query(&stmt, &[&query_vector, &namespace, &limit])
-            .await
-            .map_err(|e| {
-                BedrockError::InternalError(format!("Failed to execute search query: {}", e))
-            })?;
+        let rows = self
+            .search_rows(&query, "id, vector, metadata, content")
+            .await?;
 
         let mut results = Vec::new();
         for row in rows {
-            let vector: Option<Vector> = if query.include_content {
+            let vector: Option<Vector> = if query.include_vector {
                 Some(row.get("vector"))
             } else {
                 None
@@ -206,7 +390,10 @@ impl VectorStorage for PostgresVectorStorage {
 
             results.push(VectorSearchResult {
                 id: row.get("id"),
-                score: row.get("similarity"),
+                score: normalize_cosine_score(row.get("similarity")),
+                raw_score: Some(row.get("similarity")),
+                rerank_score: None,
+                highlight: None,
                 vector: vector.map(|v| v.to_vec()),
                 metadata: metadata_map,
                 content: if query.include_content {
@@ -223,6 +410,14 @@ impl VectorStorage for PostgresVectorStorage {
         })
     }
 
+    async fn search_ids(&self, query: VectorSearch) -> Result<Vec<(String, f32)>> {
+        let rows = self.search_rows(&query, "id").await?;
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("id"), normalize_cosine_score(row.get("similarity"))))
+            .collect())
+    }
+
     async fn get(&self, id: &str, namespace: Option<&str>) -> Result<Option<VectorRecord>> {
         let client =
             self.pool.get().await.map_err(|e| {
@@ -261,6 +456,7 @@ impl VectorStorage for PostgresVectorStorage {
         Ok(Some(VectorRecord {
             id: row.get("id"),
             vector: vector.to_vec(),
+            sparse_vector: None,
             metadata: metadata_map,
             content: row.get("content"),
             namespace: Some(row.get("namespace")),
@@ -297,6 +493,10 @@ impl VectorStorage for PostgresVectorStorage {
             param_count += 1;
             set_clauses.push(format!("content = ${}", param_count));
             params.push(Box::new(content.clone()));
+
+            param_count += 1;
+            set_clauses.push(format!("content_hash = ${}", param_count));
+            params.push(Box::new(content_hash(content)));
         }
 
         if let Some(namespace) = &update.namespace {
@@ -324,7 +524,10 @@ impl VectorStorage for PostgresVectorStorage {
             BedrockError::InternalError(format!("Failed to prepare update statement: {}", e))
         })?;
 
-        let param_refs: Vec<&(dyn ToSql + Sync)> = params.iter().map(|p| p.as_ref()).collect();
+        let param_refs: Vec<&(dyn ToSql + Sync)> = params
+            .iter()
+            .map(|p| p.as_ref() as &(dyn ToSql + Sync))
+            .collect();
 
         let rows_affected = client
             .execute(&stmt, &param_refs)
@@ -342,6 +545,45 @@ impl VectorStorage for PostgresVectorStorage {
         })
     }
 
+    // Leaves `updated_at` alone: re-embedding doesn't change the record.
+    async fn update_vector(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        model_id: &str,
+        namespace: Option<&str>,
+    ) -> Result<UpdateResult> {
+        let client =
+            self.pool.get().await.map_err(|e| {
+                BedrockError::InternalError(format!("Failed to get connection: {}", e))
+            })?;
+
+        let description = serde_json::to_value(vector_description(model_id, &vector))
+            .map_err(|e| BedrockError::SerializationError(e.to_string()))?;
+        let rows_affected = client
+            .execute(
+                "UPDATE vectors SET vector = $2, metadata = COALESCE(metadata, '{}'::jsonb) || $4 WHERE id = $1 AND namespace = $3",
+                &[
+                    &id,
+                    &Vector::from(vector),
+                    &namespace.unwrap_or("default"),
+                    &description,
+                ],
+            )
+            .await
+            .map_err(|e| BedrockError::InternalError(format!("Failed to execute update: {}", e)))?;
+
+        Ok(UpdateResult {
+            id: id.to_string(),
+            success: rows_affected > 0,
+            message: if rows_affected > 0 {
+                Some("Vector updated successfully".to_string())
+            } else {
+                Some("Vector not found".to_string())
+            },
+        })
+    }
+
     async fn delete(&self, id: &str, namespace: Option<&str>) -> Result<DeleteResult> {
         let client =
             self.pool.get().await.map_err(|e| {
@@ -438,6 +680,7 @@ impl VectorStorage for PostgresVectorStorage {
             records.push(VectorRecord {
                 id: row.get("id"),
                 vector: vector.to_vec(),
+                sparse_vector: None,
                 metadata: metadata_map,
                 content: row.get("content"),
                 namespace: Some(row.get("namespace")),
@@ -449,6 +692,197 @@ impl VectorStorage for PostgresVectorStorage {
         Ok(records)
     }
 
+    // Keyset pagination on id keeps pages stable while rows are being added.
+    async fn scroll(
+        &self,
+        namespace: Option<&str>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<ScrollPage> {
+        let client =
+            self.pool.get().await.map_err(|e| {
+                BedrockError::InternalError(format!("Failed to get connection: {}", e))
+            })?;
+
+        let namespace = namespace.unwrap_or("default");
+        let cursor = cursor.unwrap_or("");
+        let limit = limit as i64;
+
+        let stmt = client
+            .prepare(
+                "SELECT id, vector, metadata, content, namespace, created_at, updated_at
+             FROM vectors WHERE namespace = $1 AND id > $2 ORDER BY id LIMIT $3",
+            )
+            .await
+            .map_err(|e| {
+                BedrockError::InternalError(format!("Failed to prepare scroll statement: {}", e))
+            })?;
+
+        let rows = client
+            .query(&stmt, &[&namespace, &cursor, &limit])
+            .await
+            .map_err(|e| {
+                BedrockError::InternalError(format!("Failed to execute scroll query: {}", e))
+            })?;
+
+        let records: Vec<VectorRecord> = rows
+            .iter()
+            .map(|row| {
+                let vector: Vector = row.get("vector");
+                let metadata: serde_json::Value = row.get("metadata");
+
+                VectorRecord {
+                    id: row.get("id"),
+                    vector: vector.to_vec(),
+                    sparse_vector: None,
+                    metadata: serde_json::from_value(metadata).unwrap_or_default(),
+                    content: row.get("content"),
+                    namespace: Some(row.get("namespace")),
+                    created_at: row.get("created_at"),
+                    updated_at: row.get("updated_at"),
+                }
+            })
+            .collect();
+
+        let next_cursor = if records.len() as i64 == limit {
+            records.last().map(|record| record.id.clone())
+        } else {
+            None
+        };
+
+        Ok(ScrollPage {
+            records,
+            next_cursor,
+        })
+    }
+
+    // jsonb ordering compares numbers numerically and strings as text, the
+    // same as the client-side fallback.
+    async fn query_by_metadata(
+        &self,
+        filter: HashMap<String, serde_json::Value>,
+        namespace: Option<&str>,
+        limit: Option<usize>,
+        order_by: Option<MetadataOrder>,
+    ) -> Result<Vec<VectorRecord>> {
+        let client =
+            self.pool.get().await.map_err(|e| {
+                BedrockError::InternalError(format!("Failed to get connection: {}", e))
+            })?;
+
+        let namespace = namespace.unwrap_or("default");
+        let filter = serde_json::to_value(filter)
+            .map_err(|e| BedrockError::SerializationError(e.to_string()))?;
+        // LIMIT NULL returns every row.
+        let limit = limit.map(|limit| limit as i64);
+        let order = match &order_by {
+            Some(order) => format!(
+                "metadata -> $4::text {} NULLS LAST, id",
+                if order.descending { "DESC" } else { "ASC" }
+            ),
+            None => "id".to_string(),
+        };
+
+        let stmt = client
+            .prepare(&format!(
+                "SELECT id, vector, metadata, content, namespace, created_at, updated_at
+             FROM vectors WHERE namespace = $1 AND metadata @> $2
+             ORDER BY {} LIMIT $3",
+                order
+            ))
+            .await
+            .map_err(|e| {
+                BedrockError::InternalError(format!(
+                    "Failed to prepare metadata query statement: {}",
+                    e
+                ))
+            })?;
+
+        let rows = match &order_by {
+            Some(order) => {
+                client
+                    .query(&stmt, &[&namespace, &filter, &limit, &order.key])
+                    .await
+            }
+            None => client.query(&stmt, &[&namespace, &filter, &limit]).await,
+        }
+        .map_err(|e| {
+            BedrockError::InternalError(format!("Failed to execute metadata query: {}", e))
+        })?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let vector: Vector = row.get("vector");
+                let metadata: serde_json::Value = row.get("metadata");
+
+                VectorRecord {
+                    id: row.get("id"),
+                    vector: vector.to_vec(),
+                    sparse_vector: None,
+                    metadata: serde_json::from_value(metadata).unwrap_or_default(),
+                    content: row.get("content"),
+                    namespace: Some(row.get("namespace")),
+                    created_at: row.get("created_at"),
+                    updated_at: row.get("updated_at"),
+                }
+            })
+            .collect())
+    }
+
+    async fn find_by_content_hash(
+        &self,
+        hash: &str,
+        namespace: Option<&str>,
+    ) -> Result<Option<VectorRecord>> {
+        let client =
+            self.pool.get().await.map_err(|e| {
+                BedrockError::InternalError(format!("Failed to get connection: {}", e))
+            })?;
+
+        let namespace = namespace.unwrap_or("default");
+
+        let stmt = client
+            .prepare(
+                "SELECT id, vector, metadata, content, namespace, created_at, updated_at
+             FROM vectors WHERE content_hash = $1 AND namespace = $2
+             ORDER BY created_at LIMIT 1",
+            )
+            .await
+            .map_err(|e| {
+                BedrockError::InternalError(format!(
+                    "Failed to prepare content hash statement: {}",
+                    e
+                ))
+            })?;
+
+        let rows = client
+            .query(&stmt, &[&hash, &namespace])
+            .await
+            .map_err(|e| {
+                BedrockError::InternalError(format!("Failed to execute content hash query: {}", e))
+            })?;
+
+        let Some(row) = rows.first() else {
+            return Ok(None);
+        };
+        let vector: Vector = row.get("vector");
+        let metadata: serde_json::Value = row.get("metadata");
+        let metadata_map: HashMap<String, serde_json::Value> =
+            serde_json::from_value(metadata).unwrap_or_default();
+
+        Ok(Some(VectorRecord {
+            id: row.get("id"),
+            vector: vector.to_vec(),
+            sparse_vector: None,
+            metadata: metadata_map,
+            content: row.get("content"),
+            namespace: Some(row.get("namespace")),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }))
+    }
+
     async fn stats(&self, namespace: Option<&str>) -> Result<StorageStats> {
         let client =
             self.pool.get().await.map_err(|e| {
@@ -457,7 +891,9 @@ impl VectorStorage for PostgresVectorStorage {
 
         let namespace = namespace.unwrap_or("default");
         let count_stmt = client
-            .prepare("SELECT COUNT(*) FROM vectors WHERE namespace = $1")
+            .prepare(
+                "SELECT COUNT(*), MAX(updated_at), MIN(created_at) FROM vectors WHERE namespace = $1",
+            )
             .await
             .map_err(|e| {
                 BedrockError::InternalError(format!("Failed to prepare count statement: {}", e))
@@ -471,6 +907,8 @@ impl VectorStorage for PostgresVectorStorage {
             })?;
 
         let total_vectors: i64 = count_row.get(0);
+        let last_updated: Option<DateTime<Utc>> = count_row.get(1);
+        let oldest: Option<DateTime<Utc>> = count_row.get(2);
         let ns_stmt = client
             .prepare("SELECT DISTINCT namespace FROM vectors")
             .await
@@ -507,9 +945,29 @@ impl VectorStorage for PostgresVectorStorage {
             namespaces,
             dimensions,
             storage_size_bytes: None,
+            last_updated,
+            oldest,
         })
     }
 
+    // Closing the pool drops idle connections and makes pending waits fail
+    // fast instead of holding on until the pool times out.
+    async fn close(&self) -> Result<()> {
+        self.pool.close();
+        log::info!("PostgreSQL connection pool closed");
+        Ok(())
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities {
+            supports_namespaces: true,
+            supports_list: true,
+            supports_filter: true,
+            supports_transactions: true,
+            supports_sparse: false,
+        }
+    }
+
     async fn health_check(&self) -> Result<bool> {
         let client = self
             .pool
@@ -525,6 +983,13 @@ impl VectorStorage for PostgresVectorStorage {
         Ok(true)
     }
 }
+#[cfg(feature = "postgres")]
+impl Drop for PostgresVectorStorage {
+    fn drop(&mut self) {
+        self.pool.close();
+    }
+}
+
 #[cfg(not(feature = "postgres"))]
 pub struct PostgresVectorStorage;
 
@@ -1,20 +1,68 @@
 use crate::{
-    error::Result,
+    error::{BedrockError, Result},
     models::storage::{
-        DeleteResult, InsertResult, UpdateResult, VectorInsert, VectorRecord, VectorSearch,
+        metadata_matches, sort_by_metadata, vector_description, DeleteResult, InsertResult,
+        MetadataOrder, ScrollPage, UpdateResult, VectorInsert, VectorRecord, VectorSearch,
         VectorSearchResponse, VectorUpdate,
     },
 };
 use async_trait::async_trait;
+use std::collections::HashMap;
 
 #[async_trait]
 pub trait VectorStorage: Send + Sync {
     async fn insert(&self, record: VectorInsert) -> Result<InsertResult>;
     async fn insert_batch(&self, records: Vec<VectorInsert>) -> Result<Vec<InsertResult>>;
     async fn search(&self, query: VectorSearch) -> Result<VectorSearchResponse>;
+
+    // Ranking-only lookup. Backends that can skip hydrating rows should override it.
+    async fn search_ids(&self, mut query: VectorSearch) -> Result<Vec<(String, f32)>> {
+        query.include_metadata = false;
+        query.include_content = false;
+        query.include_vector = false;
+
+        Ok(self
+            .search(query)
+            .await?
+            .results
+            .into_iter()
+            .map(|result| (result.id, result.score))
+            .collect())
+    }
     async fn get(&self, id: &str, namespace: Option<&str>) -> Result<Option<VectorRecord>>;
     async fn update(&self, update: VectorUpdate) -> Result<UpdateResult>;
 
+    // Replaces the vector and rewrites the reserved `embedding_model`, `_dim`,
+    // `_normalized` and `_dtype` keys to describe it, leaving other metadata,
+    // content and timestamps as they are; meant for re-embedding with a new
+    // model. The default goes through `get` and `update`, which may touch
+    // `updated_at`.
+    async fn update_vector(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        model_id: &str,
+        namespace: Option<&str>,
+    ) -> Result<UpdateResult> {
+        let Some(existing) = self.get(id, namespace).await? else {
+            return Ok(UpdateResult {
+                id: id.to_string(),
+                success: false,
+                message: Some("Vector not found".to_string()),
+            });
+        };
+        let mut metadata = existing.metadata;
+        metadata.extend(vector_description(model_id, &vector));
+        self.update(VectorUpdate {
+            id: id.to_string(),
+            vector: Some(vector),
+            metadata: Some(metadata),
+            content: None,
+            namespace: namespace.map(String::from),
+        })
+        .await
+    }
+
     async fn delete(&self, id: &str, namespace: Option<&str>) -> Result<DeleteResult>;
 
     async fn delete_batch(
@@ -30,7 +78,88 @@ pub trait VectorStorage: Send + Sync {
     ) -> Result<Vec<VectorRecord>>;
     async fn stats(&self, namespace: Option<&str>) -> Result<StorageStats>;
 
+    async fn scroll(
+        &self,
+        _namespace: Option<&str>,
+        _cursor: Option<&str>,
+        _limit: usize,
+    ) -> Result<ScrollPage> {
+        Err(BedrockError::RequestError(
+            "Scrolling is not supported by this storage backend".into(),
+        ))
+    }
+
+    async fn find_by_content_hash(
+        &self,
+        _hash: &str,
+        _namespace: Option<&str>,
+    ) -> Result<Option<VectorRecord>> {
+        Err(BedrockError::RequestError(
+            "Content hash lookup is not supported by this storage backend".into(),
+        ))
+    }
+
+    // Records whose metadata matches every `filter` entry, without a
+    // similarity query. The default pages through `scroll` and filters
+    // client-side, reading the whole namespace when `order_by` is set;
+    // backends that can filter natively should override it.
+    async fn query_by_metadata(
+        &self,
+        filter: HashMap<String, serde_json::Value>,
+        namespace: Option<&str>,
+        limit: Option<usize>,
+        order_by: Option<MetadataOrder>,
+    ) -> Result<Vec<VectorRecord>> {
+        let mut records = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let page = self.scroll(namespace, cursor.as_deref(), 100).await?;
+            records.extend(
+                page.records
+                    .into_iter()
+                    .filter(|record| metadata_matches(&record.metadata, &filter)),
+            );
+            // Unordered, any `limit` matches will do.
+            if order_by.is_none() && limit.is_some_and(|limit| records.len() >= limit) {
+                break;
+            }
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        if let Some(order) = &order_by {
+            sort_by_metadata(&mut records, order);
+        }
+        if let Some(limit) = limit {
+            records.truncate(limit);
+        }
+        Ok(records)
+    }
+
     async fn health_check(&self) -> Result<bool>;
+
+    // Stateless HTTP backends have nothing to release.
+    async fn close(&self) -> Result<()> {
+        Ok(())
+    }
+
+    // Conservative default so custom backends don't advertise features
+    // they haven't implemented.
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StorageCapabilities {
+    pub supports_namespaces: bool,
+    pub supports_list: bool,
+    pub supports_filter: bool,
+    pub supports_transactions: bool,
+    pub supports_sparse: bool,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -39,4 +168,111 @@ pub struct StorageStats {
     pub namespaces: Vec<String>,
     pub dimensions: Option<usize>,
     pub storage_size_bytes: Option<u64>,
+    #[serde(default)]
+    pub last_updated: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub oldest: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Stats combined across several backends, e.g. a primary and its replica.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AggregatedStats {
+    pub combined: StorageStats,
+    pub per_backend: Vec<StorageStats>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BackendHealth {
+    pub name: String,
+    pub healthy: bool,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+impl StorageStats {
+    // Sums vector counts and sizes and unions namespaces. `dimensions` is
+    // only kept when every backend that reports it agrees; a disagreement is
+    // reported as a warning since it means the backends have drifted apart.
+    pub fn aggregate(stats: Vec<StorageStats>) -> AggregatedStats {
+        let mut namespaces: Vec<String> = stats
+            .iter()
+            .flat_map(|s| s.namespaces.iter().cloned())
+            .collect();
+        namespaces.sort();
+        namespaces.dedup();
+
+        let mut dimensions: Vec<usize> = stats.iter().filter_map(|s| s.dimensions).collect();
+        dimensions.sort_unstable();
+        dimensions.dedup();
+
+        let mut warnings = Vec::new();
+        if dimensions.len() > 1 {
+            let message = format!(
+                "Backends disagree on vector dimensions: {}",
+                stats
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, s)| s.dimensions.map(|d| format!("backend {}={}", i, d)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            log::warn!("{}", message);
+            warnings.push(message);
+        }
+
+        let sizes: Vec<u64> = stats.iter().filter_map(|s| s.storage_size_bytes).collect();
+        let combined = StorageStats {
+            total_vectors: stats.iter().map(|s| s.total_vectors).sum(),
+            namespaces,
+            dimensions: match dimensions.as_slice() {
+                [dim] => Some(*dim),
+                _ => None,
+            },
+            storage_size_bytes: (!sizes.is_empty()).then(|| sizes.iter().sum()),
+            last_updated: stats.iter().filter_map(|s| s.last_updated).max(),
+            oldest: stats.iter().filter_map(|s| s.oldest).min(),
+        };
+
+        AggregatedStats {
+            combined,
+            per_backend: stats,
+            warnings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(total: usize, namespaces: &[&str], dimensions: Option<usize>) -> StorageStats {
+        StorageStats {
+            total_vectors: total,
+            namespaces: namespaces.iter().map(|ns| ns.to_string()).collect(),
+            dimensions,
+            storage_size_bytes: None,
+            last_updated: None,
+            oldest: None,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_warns_when_dimensions_disagree() {
+        let agreeing = StorageStats::aggregate(vec![
+            stats(3, &["a", "b"], Some(1536)),
+            stats(2, &["b"], Some(1536)),
+        ]);
+        assert_eq!(agreeing.combined.total_vectors, 5);
+        assert_eq!(agreeing.combined.namespaces, vec!["a", "b"]);
+        assert_eq!(agreeing.combined.dimensions, Some(1536));
+        assert!(agreeing.warnings.is_empty());
+
+        let drifted = StorageStats::aggregate(vec![
+            stats(3, &["a"], Some(1536)),
+            stats(3, &["a"], Some(1024)),
+        ]);
+        assert_eq!(drifted.combined.dimensions, None);
+        assert_eq!(drifted.warnings.len(), 1);
+    }
 }
@@ -1,13 +1,17 @@
 use std::collections::HashMap;
 
 use crate::{
-    config::UpstashConfig,
+    config::{MetadataKeys, UpstashConfig},
     error::{BedrockError, Result},
     models::storage::{
-        DeleteResult, InsertResult, UpdateResult, VectorInsert, VectorRecord, VectorSearch,
-        VectorSearchResponse, VectorSearchResult, VectorUpdate,
+        sort_by_metadata, vector_description, DeleteResult, InsertResult, MetadataOrder,
+        ScrollPage, UpdateResult, VectorInsert, VectorRecord, VectorSearch, VectorSearchResponse,
+        VectorSearchResult, VectorUpdate,
+    },
+    storage::{
+        compression::RequestCompression,
+        traits::{StorageCapabilities, StorageStats, VectorStorage},
     },
-    storage::traits::{StorageStats, VectorStorage},
 };
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
@@ -16,14 +20,23 @@ use serde_json::{json, Value};
 
 use uuid::Uuid;
 
+// Largest `topK` Upstash accepts for a query.
+const MAX_TOP_K: usize = 1000;
+
+const DEFAULT_BATCH_SIZE: usize = 1000;
+
 pub struct UpstashVectorStorage {
     client: Client,
     base_url: String,
     token: String,
+    batch_size: usize,
+    compression: RequestCompression,
+    keys: MetadataKeys,
 }
 
 impl UpstashVectorStorage {
     pub async fn new(config: UpstashConfig) -> Result<Self> {
+        config.metadata_keys.validate()?;
         let base_url = config
             .url
             .ok_or_else(|| BedrockError::ConfigError("Upstash URL is required".into()))?;
@@ -36,6 +49,9 @@ impl UpstashVectorStorage {
             client: Client::new(),
             base_url,
             token,
+            batch_size: config.batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1),
+            compression: RequestCompression::new(config.compress_requests),
+            keys: config.metadata_keys,
         };
 
         // Test connection
@@ -56,6 +72,164 @@ impl UpstashVectorStorage {
         );
         headers
     }
+
+    async fn upsert_chunk(&self, records: &[(String, VectorInsert)]) -> Result<Vec<InsertResult>> {
+        let vectors: Vec<Value> = records
+            .iter()
+            .map(|(id, record)| {
+                let mut metadata = record.metadata.clone();
+                if let Some(content) = &record.content {
+                    metadata.insert(self.keys.content.clone(), json!(content));
+                }
+                if let Some(namespace) = &record.namespace {
+                    metadata.insert(self.keys.namespace.clone(), json!(namespace));
+                }
+                metadata.insert(self.keys.created_at.clone(), json!(Utc::now().to_rfc3339()));
+
+                let mut vector = json!({
+                    "id": id,
+                    "vector": record.vector,
+                    "metadata": metadata
+                });
+                if let Some(sparse_vector) = &record.sparse_vector {
+                    vector["sparseVector"] = json!(sparse_vector);
+                }
+                vector
+            })
+            .collect();
+
+        let payload = json!({
+            "vectors": vectors
+        });
+
+        let response = self
+            .compression
+            .send(
+                || {
+                    self.client
+                        .post(format!("{}/upsert-batch", self.base_url))
+                        .headers(self.build_headers())
+                },
+                &payload,
+            )
+            .await
+            .map_err(|e| {
+                BedrockError::RequestError(format!("Upstash batch request failed: {}", e))
+            })?;
+
+        let mut results = Vec::new();
+
+        if response.status().is_success() {
+            for (id, _) in records {
+                results.push(InsertResult {
+                    id: id.clone(),
+                    success: true,
+                    message: Some("Vector inserted successfully".to_string()),
+                });
+            }
+        } else {
+            let error_text = response.text().await.unwrap_or_default();
+            for (id, _) in records {
+                results.push(InsertResult {
+                    id: id.clone(),
+                    success: false,
+                    message: Some(format!("Batch insert failed: {}", error_text)),
+                });
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn parse_record(&self, id: &str, result: &Value) -> VectorRecord {
+        let metadata: HashMap<String, serde_json::Value> = result["metadata"]
+            .as_object()
+            .map(|obj| obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default();
+
+        let content = metadata
+            .get(&self.keys.content)
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        let namespace = metadata
+            .get(&self.keys.namespace)
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        let created_at_str = metadata
+            .get(&self.keys.created_at)
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+
+        let created_at = DateTime::parse_from_rfc3339(created_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now());
+
+        let vector = result["vector"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_f64().map(|f| f as f32))
+                    .collect()
+            })
+            .unwrap_or_default();
+        // Hybrid indexes return the sparse half next to the dense values and
+        // reject upserts without it, so it has to survive a re-upsert.
+        let sparse_vector = serde_json::from_value(result["sparseVector"].clone()).ok();
+
+        VectorRecord {
+            id: id.to_string(),
+            vector,
+            sparse_vector,
+            metadata,
+            content,
+            namespace,
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    // Upstash filters are SQL-like expressions rather than JSON objects, so
+    // keys are limited to (dotted) identifiers and string values are quoted
+    // with backslash escapes; anything else could rewrite the expression.
+    fn build_filter(filter: &HashMap<String, Value>) -> Result<String> {
+        let mut keys: Vec<&String> = filter.keys().collect();
+        keys.sort();
+        keys.into_iter()
+            .map(|key| {
+                if !Self::is_filter_identifier(key) {
+                    return Err(BedrockError::RequestError(format!(
+                        "Metadata key {:?} can't be used in an Upstash filter; use letters, digits, '_' and '.'",
+                        key
+                    )));
+                }
+                match &filter[key] {
+                    Value::String(s) => Ok(format!(
+                        "{} = '{}'",
+                        key,
+                        s.replace('\\', "\\\\").replace('\'', "\\'")
+                    )),
+                    value @ (Value::Number(_) | Value::Bool(_)) => {
+                        Ok(format!("{} = {}", key, value))
+                    }
+                    other => Err(BedrockError::RequestError(format!(
+                        "Upstash filters only match strings, numbers and booleans, not {} for {}",
+                        other, key
+                    ))),
+                }
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(|clauses| clauses.join(" AND "))
+    }
+
+    fn is_filter_identifier(key: &str) -> bool {
+        key.split('.').all(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+    }
 }
 
 #[async_trait]
@@ -65,22 +239,25 @@ impl VectorStorage for UpstashVectorStorage {
 
         let mut metadata = record.metadata.clone();
         if let Some(content) = &record.content {
-            metadata.insert("content".to_string(), json!(content));
+            metadata.insert(self.keys.content.clone(), json!(content));
         }
         if let Some(namespace) = &record.namespace {
-            metadata.insert("namespace".to_string(), json!(namespace));
+            metadata.insert(self.keys.namespace.clone(), json!(namespace));
         }
-        metadata.insert("created_at".to_string(), json!(Utc::now().to_rfc3339()));
+        metadata.insert(self.keys.created_at.clone(), json!(Utc::now().to_rfc3339()));
 
-        let payload = json!({
+        let mut payload = json!({
             "id": id,
             "vector": record.vector,
             "metadata": metadata
         });
+        if let Some(sparse_vector) = &record.sparse_vector {
+            payload["sparseVector"] = json!(sparse_vector);
+        }
 
         let response = self
             .client
-            .post(&format!("{}/upsert", self.base_url))
+            .post(format!("{}/upsert", self.base_url))
             .headers(self.build_headers())
             .json(&payload)
             .send()
@@ -104,67 +281,30 @@ impl VectorStorage for UpstashVectorStorage {
     }
 
     async fn insert_batch(&self, records: Vec<VectorInsert>) -> Result<Vec<InsertResult>> {
-        let vectors: Vec<Value> = records
-            .iter()
+        // Ids are fixed before the first request and never regenerated per
+        // chunk, so re-sending a chunk overwrites rather than duplicates.
+        let records: Vec<(String, VectorInsert)> = records
+            .into_iter()
             .map(|record| {
                 let id = record
                     .id
-                    .as_ref()
-                    .cloned()
+                    .clone()
                     .unwrap_or_else(|| Uuid::new_v4().to_string());
-
-                let mut metadata = record.metadata.clone();
-                if let Some(content) = &record.content {
-                    metadata.insert("content".to_string(), json!(content));
-                }
-                if let Some(namespace) = &record.namespace {
-                    metadata.insert("namespace".to_string(), json!(namespace));
-                }
-                metadata.insert("created_at".to_string(), json!(Utc::now().to_rfc3339()));
-
-                json!({
-                    "id": id,
-                    "vector": record.vector,
-                    "metadata": metadata
-                })
+                (id, record)
             })
             .collect();
 
-        let payload = json!({
-            "vectors": vectors
-        });
-
-        let response = self
-            .client
-            .post(&format!("{}/upsert-batch", self.base_url))
-            .headers(self.build_headers())
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| {
-                BedrockError::RequestError(format!("Upstash batch request failed: {}", e))
-            })?;
-
-        let mut results = Vec::new();
-
-        if response.status().is_success() {
-            for record in records {
-                let id = record.id.unwrap_or_else(|| Uuid::new_v4().to_string());
-                results.push(InsertResult {
-                    id,
-                    success: true,
-                    message: Some("Vector inserted successfully".to_string()),
-                });
-            }
-        } else {
-            let error_text = response.text().await.unwrap_or_default();
-            for record in records {
-                let id = record.id.unwrap_or_else(|| Uuid::new_v4().to_string());
-                results.push(InsertResult {
-                    id,
+        let mut results = Vec::with_capacity(records.len());
+        // A failed chunk is reported per record so results from chunks that
+        // already landed are not lost.
+        for chunk in records.chunks(self.batch_size) {
+            match self.upsert_chunk(chunk).await {
+                Ok(chunk_results) => results.extend(chunk_results),
+                Err(e) => results.extend(chunk.iter().map(|(id, _)| InsertResult {
+                    id: id.clone(),
                     success: false,
-                    message: Some(format!("Batch insert failed: {}", error_text)),
-                });
+                    message: Some(e.to_string()),
+                })),
             }
         }
 
@@ -176,16 +316,19 @@ impl VectorStorage for UpstashVectorStorage {
             "vector": query.vector,
             "topK": query.limit,
             "includeMetadata": query.include_metadata,
-            "includeVectors": query.include_content
+            "includeVectors": query.include_vector
         });
 
-        if let Some(filter) = query.filter {
-            payload["filter"] = json!(filter);
+        if let Some(filter) = query.filter.as_ref().filter(|f| !f.is_empty()) {
+            payload["filter"] = json!(Self::build_filter(filter)?);
+        }
+        if let Some(sparse_vector) = &query.sparse_vector {
+            payload["sparseVector"] = json!(sparse_vector);
         }
 
         let response = self
             .client
-            .post(&format!("{}/query", self.base_url))
+            .post(format!("{}/query", self.base_url))
             .headers(self.build_headers())
             .json(&payload)
             .send()
@@ -217,17 +360,22 @@ impl VectorStorage for UpstashVectorStorage {
 
             let content = if query.include_content {
                 metadata
-                    .get("content")
+                    .get(&self.keys.content)
                     .and_then(|v| v.as_str())
                     .map(String::from)
             } else {
                 None
             };
 
+            // Upstash already reports cosine scores as (1 + similarity) / 2.
+            let raw_score = match_item["score"].as_f64().unwrap_or(0.0) as f32;
             results.push(VectorSearchResult {
                 id: match_item["id"].as_str().unwrap_or("").to_string(),
-                score: match_item["score"].as_f64().unwrap_or(0.0) as f32,
-                vector: if query.include_content {
+                score: raw_score,
+                raw_score: Some(raw_score),
+                rerank_score: None,
+                highlight: None,
+                vector: if query.include_vector {
                     match_item["vector"].as_array().map(|arr| {
                         arr.iter()
                             .filter_map(|v| v.as_f64().map(|f| f as f32))
@@ -256,7 +404,7 @@ impl VectorStorage for UpstashVectorStorage {
 
         let response = self
             .client
-            .post(&format!("{}/fetch", self.base_url))
+            .post(format!("{}/fetch", self.base_url))
             .headers(self.build_headers())
             .json(&payload)
             .send()
@@ -280,46 +428,7 @@ impl VectorStorage for UpstashVectorStorage {
             return Ok(None);
         }
 
-        let metadata: HashMap<String, serde_json::Value> = result["metadata"]
-            .as_object()
-            .map(|obj| obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
-            .unwrap_or_default();
-
-        let content = metadata
-            .get("content")
-            .and_then(|v| v.as_str())
-            .map(String::from);
-        let namespace = metadata
-            .get("namespace")
-            .and_then(|v| v.as_str())
-            .map(String::from);
-        let created_at_str = metadata
-            .get("created_at")
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
-
-        let created_at = DateTime::parse_from_rfc3339(created_at_str)
-            .map(|dt| dt.with_timezone(&Utc))
-            .unwrap_or_else(|_| Utc::now());
-
-        let vector = result["vector"]
-            .as_array()
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_f64().map(|f| f as f32))
-                    .collect()
-            })
-            .unwrap_or_default();
-
-        Ok(Some(VectorRecord {
-            id: id.to_string(),
-            vector,
-            metadata,
-            content,
-            namespace,
-            created_at,
-            updated_at: created_at,
-        }))
+        Ok(Some(self.parse_record(id, result)))
     }
 
     async fn update(&self, update: VectorUpdate) -> Result<UpdateResult> {
@@ -338,7 +447,7 @@ impl VectorStorage for UpstashVectorStorage {
                 existing_record.content = Some(content);
                 existing_record
                     .metadata
-                    .insert("content".to_string(), json!(existing_record.content));
+                    .insert(self.keys.content.clone(), json!(existing_record.content));
             }
             if let Some(namespace) = update.namespace {
                 existing_record.namespace = Some(namespace);
@@ -346,17 +455,9 @@ impl VectorStorage for UpstashVectorStorage {
 
             existing_record
                 .metadata
-                .insert("updated_at".to_string(), json!(Utc::now().to_rfc3339()));
-
-            let insert_record = VectorInsert {
-                id: Some(existing_record.id.clone()),
-                vector: existing_record.vector,
-                metadata: existing_record.metadata,
-                content: existing_record.content,
-                namespace: existing_record.namespace,
-            };
+                .insert(self.keys.updated_at.clone(), json!(Utc::now().to_rfc3339()));
 
-            let insert_result = self.insert(insert_record).await?;
+            let insert_result = self.insert(existing_record.into()).await?;
             Ok(UpdateResult {
                 id: update.id,
                 success: insert_result.success,
@@ -371,6 +472,60 @@ impl VectorStorage for UpstashVectorStorage {
         }
     }
 
+    // Re-upserts with the fetched metadata, so content and created_at
+    // survive instead of being rewritten as by `insert`; only the reserved
+    // keys describing the vector change.
+    async fn update_vector(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        model_id: &str,
+        namespace: Option<&str>,
+    ) -> Result<UpdateResult> {
+        let Some(existing) = self.get(id, namespace).await? else {
+            return Ok(UpdateResult {
+                id: id.to_string(),
+                success: false,
+                message: Some("Vector not found".to_string()),
+            });
+        };
+        let mut metadata = existing.metadata;
+        metadata.extend(vector_description(model_id, &vector));
+
+        let mut payload = json!({
+            "id": id,
+            "vector": vector,
+            "metadata": metadata
+        });
+        if let Some(sparse_vector) = &existing.sparse_vector {
+            payload["sparseVector"] = json!(sparse_vector);
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/upsert", self.base_url))
+            .headers(self.build_headers())
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| BedrockError::RequestError(format!("Upstash request failed: {}", e)))?;
+
+        if response.status().is_success() {
+            Ok(UpdateResult {
+                id: id.to_string(),
+                success: true,
+                message: Some("Vector updated successfully".to_string()),
+            })
+        } else {
+            let error_text = response.text().await.unwrap_or_default();
+            Ok(UpdateResult {
+                id: id.to_string(),
+                success: false,
+                message: Some(format!("Update failed: {}", error_text)),
+            })
+        }
+    }
+
     async fn delete(&self, id: &str, _namespace: Option<&str>) -> Result<DeleteResult> {
         let payload = json!({
             "ids": [id]
@@ -378,7 +533,7 @@ impl VectorStorage for UpstashVectorStorage {
 
         let response = self
             .client
-            .delete(&format!("{}/delete", self.base_url))
+            .delete(format!("{}/delete", self.base_url))
             .headers(self.build_headers())
             .json(&payload)
             .send()
@@ -407,7 +562,7 @@ impl VectorStorage for UpstashVectorStorage {
 
         let response = self
             .client
-            .delete(&format!("{}/delete", self.base_url))
+            .delete(format!("{}/delete", self.base_url))
             .headers(self.build_headers())
             .json(&payload)
             .send()
@@ -443,10 +598,152 @@ impl VectorStorage for UpstashVectorStorage {
         Ok(vec![])
     }
 
+    // Namespaces live in metadata here, so the requested one is applied to
+    // each page after it is fetched.
+    // Upstash can't filter a range scan, so this is a filtered query with a
+    // constant vector whose scores are ignored. At most `MAX_TOP_K` matches
+    // come back, so ordering only covers those.
+    async fn query_by_metadata(
+        &self,
+        mut filter: HashMap<String, Value>,
+        namespace: Option<&str>,
+        limit: Option<usize>,
+        order_by: Option<MetadataOrder>,
+    ) -> Result<Vec<VectorRecord>> {
+        let dimensions = self.stats(namespace).await?.dimensions.ok_or_else(|| {
+            BedrockError::ResponseError("Upstash did not report the index dimension".into())
+        })?;
+        // Records in the default namespace may not carry the key at all, so
+        // that case is filtered below instead.
+        if let Some(ns) = namespace.filter(|ns| *ns != "default") {
+            filter.insert(self.keys.namespace.clone(), json!(ns));
+        }
+        let top_k = match (&order_by, limit) {
+            (None, Some(limit)) if namespace.is_none_or(|ns| ns != "default") => {
+                limit.min(MAX_TOP_K)
+            }
+            _ => MAX_TOP_K,
+        };
+
+        let mut payload = json!({
+            "vector": vec![1.0_f32; dimensions],
+            "topK": top_k,
+            "includeMetadata": true,
+            "includeVectors": true
+        });
+        if !filter.is_empty() {
+            payload["filter"] = json!(Self::build_filter(&filter)?);
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/query", self.base_url))
+            .headers(self.build_headers())
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| BedrockError::RequestError(format!("Upstash query failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(BedrockError::RequestError(format!(
+                "Query failed: {}",
+                error_text
+            )));
+        }
+
+        let response_json: Value = response.json().await.map_err(|e| {
+            BedrockError::ResponseError(format!("Failed to parse query response: {}", e))
+        })?;
+
+        let matches = response_json["result"]
+            .as_array()
+            .ok_or_else(|| BedrockError::ResponseError("Invalid query response format".into()))?;
+        if order_by.is_some() && matches.len() == MAX_TOP_K {
+            log::warn!(
+                "query_by_metadata hit Upstash's topK limit of {}; ordering covers only those matches",
+                MAX_TOP_K
+            );
+        }
+        let mut records: Vec<VectorRecord> = matches
+            .iter()
+            .map(|m| self.parse_record(m["id"].as_str().unwrap_or(""), m))
+            .filter(|record| {
+                namespace.is_none_or(|ns| record.namespace.as_deref().unwrap_or("default") == ns)
+            })
+            .collect();
+
+        if let Some(order) = &order_by {
+            sort_by_metadata(&mut records, order);
+        }
+        if let Some(limit) = limit {
+            records.truncate(limit);
+        }
+        Ok(records)
+    }
+
+    async fn scroll(
+        &self,
+        namespace: Option<&str>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<ScrollPage> {
+        let payload = json!({
+            "cursor": cursor.unwrap_or("0"),
+            "limit": limit,
+            "includeMetadata": true,
+            "includeVectors": true
+        });
+
+        let response = self
+            .client
+            .post(format!("{}/range", self.base_url))
+            .headers(self.build_headers())
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| BedrockError::RequestError(format!("Upstash range failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(BedrockError::RequestError(format!(
+                "Range failed: {}",
+                error_text
+            )));
+        }
+
+        let response_json: Value = response.json().await.map_err(|e| {
+            BedrockError::ResponseError(format!("Failed to parse range response: {}", e))
+        })?;
+
+        let records = response_json["result"]["vectors"]
+            .as_array()
+            .map(|vectors| {
+                vectors
+                    .iter()
+                    .map(|v| self.parse_record(v["id"].as_str().unwrap_or(""), v))
+                    .filter(|record| {
+                        namespace
+                            .is_none_or(|ns| record.namespace.as_deref().unwrap_or("default") == ns)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let next_cursor = response_json["result"]["nextCursor"]
+            .as_str()
+            .filter(|cursor| !cursor.is_empty())
+            .map(String::from);
+
+        Ok(ScrollPage {
+            records,
+            next_cursor,
+        })
+    }
+
     async fn stats(&self, _namespace: Option<&str>) -> Result<StorageStats> {
         let response = self
             .client
-            .get(&format!("{}/info", self.base_url))
+            .get(format!("{}/info", self.base_url))
             .headers(self.build_headers())
             .send()
             .await
@@ -468,13 +765,25 @@ impl VectorStorage for UpstashVectorStorage {
             namespaces: vec!["default".to_string()], // Upstash doesn't use namespaces
             dimensions,
             storage_size_bytes: None,
+            last_updated: None,
+            oldest: None,
         })
     }
 
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities {
+            supports_namespaces: false,
+            supports_list: false,
+            supports_filter: true,
+            supports_transactions: false,
+            supports_sparse: true,
+        }
+    }
+
     async fn health_check(&self) -> Result<bool> {
         let response = self
             .client
-            .get(&format!("{}/info", self.base_url))
+            .get(format!("{}/info", self.base_url))
             .headers(self.build_headers())
             .send()
             .await
@@ -483,3 +792,39 @@ impl VectorStorage for UpstashVectorStorage {
         Ok(response.status().is_success())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_filter_rejects_unsafe_keys_and_escapes_values() {
+        let filter = |key: &str, value: Value| HashMap::from([(key.to_string(), value)]);
+
+        assert_eq!(
+            UpstashVectorStorage::build_filter(&filter(
+                "author",
+                json!("O'Brien x' OR 1=1 OR a='")
+            ))
+            .unwrap(),
+            "author = 'O\\'Brien x\\' OR 1=1 OR a=\\''"
+        );
+        assert_eq!(
+            UpstashVectorStorage::build_filter(&filter("path", json!("C:\\dir\\"))).unwrap(),
+            "path = 'C:\\\\dir\\\\'"
+        );
+        assert_eq!(
+            UpstashVectorStorage::build_filter(&filter("meta.page_2", json!(3))).unwrap(),
+            "meta.page_2 = 3"
+        );
+
+        for key in ["x' OR 1=1 OR a='", "first name", "a.", "1st", ""] {
+            assert!(
+                UpstashVectorStorage::build_filter(&filter(key, json!("v"))).is_err(),
+                "{:?}",
+                key
+            );
+        }
+        assert!(UpstashVectorStorage::build_filter(&filter("tags", json!(["a"]))).is_err());
+    }
+}